@@ -43,8 +43,21 @@ class t_rs_generator : public t_oop_generator {
                  const string& option_string)
     : t_oop_generator(program)
   {
-    (void) parsed_options;
     (void) option_string;
+    std::map<std::string, std::string>::const_iterator iter;
+
+    iter = parsed_options.find("context");
+    context_ = (iter != parsed_options.end());
+
+    iter = parsed_options.find("builders");
+    builders_ = (iter != parsed_options.end());
+
+    iter = parsed_options.find("unknown_fields");
+    unknown_fields_ = (iter != parsed_options.end());
+
+    iter = parsed_options.find("unknown_enums");
+    unknown_enums_ = (iter != parsed_options.end());
+
     // FIXME: change back to gen-rs when we finalize mod structure for generated code
     out_dir_base_ = "src";
   }
@@ -60,6 +73,7 @@ class t_rs_generator : public t_oop_generator {
   void generate_enum(t_enum*     tenum);
   void generate_struct(t_struct*   tstruct);
   void generate_service(t_service*  tservice);
+  void generate_consts(vector<t_const*> consts);
 
  private:
   string rs_autogen_comment();
@@ -68,6 +82,9 @@ class t_rs_generator : public t_oop_generator {
   string render_rs_type(t_type* type);
   string render_suffix(t_type* type);
   string render_type_init(t_type* type);
+  string render_const_type(t_type* type);
+  string render_const_value(t_type* type, t_const_value* value);
+  bool is_const_expr(t_type* type);
 
   void generate_service_generics(t_service* tservice);
   void generate_service_fields(t_service* tservice);
@@ -114,6 +131,18 @@ class t_rs_generator : public t_oop_generator {
 
  private:
   ofstream f_mod_;
+
+  // Pass a RequestContext to the service handler methods.
+  bool context_;
+
+  // Generate a builder for every struct and exception.
+  bool builders_;
+
+  // Keep the fields of structs and exceptions that the IDL does not know about.
+  bool unknown_fields_;
+
+  // Give every enum an extra variant holding values the IDL does not declare.
+  bool unknown_enums_;
 };
 
 /*
@@ -175,9 +204,7 @@ void t_rs_generator::generate_program() {
   }
 
   // Generate constants
-  // TODO: Implement constant generation.
-  // vector<t_const*> consts = program_->get_consts();
-  // generate_consts(consts);
+  generate_consts(program_->get_consts());
 
   // Generate services
   for (sv_iter = services.begin(); sv_iter != services.end(); ++sv_iter) {
@@ -219,7 +246,7 @@ string t_rs_generator::rs_autogen_comment() {
 }
 
 string t_rs_generator::rs_imports() {
-  return string("#![allow(unused_mut, dead_code, non_snake_case, unused_imports)]\n") +
+  return string("#![allow(unused_mut, dead_code, non_snake_case, non_upper_case_globals, unused_imports)]\n") +
           "use ::thrift::rt::OrderedFloat;\n" +
           "use std::collections::{BTreeMap, BTreeSet};\n";
 }
@@ -249,11 +276,34 @@ void t_rs_generator::generate_enum(t_enum* tenum) {
   for (i = constants.begin(); i != end; ++i) {
     string name = capitalize((*i)->get_name());
     int value = (*i)->get_value();
-    indent(f_mod_) << name << " = " << value << ",\n";
+    indent(f_mod_) << name << " = " << value;
+    // Display and FromStr use the IDL name.
+    if (name != (*i)->get_name()) {
+      f_mod_ << " => \"" << (*i)->get_name() << "\"";
+    }
+    f_mod_ << ",\n";
   }
 
   indent_down();
   indent(f_mod_) << "],\n";
+
+  if (unknown_enums_) {
+    // Pick a variant name the IDL does not use already.
+    string unknown = "Unknown";
+    bool taken = true;
+    while (taken) {
+      taken = false;
+      for (i = constants.begin(); i != end; ++i) {
+        if (capitalize((*i)->get_name()) == unknown) {
+          unknown += "Value";
+          taken = true;
+          break;
+        }
+      }
+    }
+    indent(f_mod_) << "unknown = " << unknown << ",\n";
+  }
+
   indent(f_mod_) << "default = " << capitalize(constants.at(0)->get_name()) << "\n";
 
   indent_down();
@@ -261,13 +311,20 @@ void t_rs_generator::generate_enum(t_enum* tenum) {
 }
 
 // Generate a struct, translating a thrift struct into a rust struct.
+// Exceptions go through `exception!` so they also implement std::error::Error.
 void t_rs_generator::generate_struct(t_struct* tstruct) {
   string sname = pascalcase(tstruct->get_name());
 
-  indent(f_mod_) << "strukt! {\n";
+  indent(f_mod_) << (tstruct->is_xception() ? "exception! {\n" : "strukt! {\n");
   indent_up();
 
   indent(f_mod_) << "name = " << sname << ",\n";
+  if (builders_) {
+    indent(f_mod_) << "builder = " << sname << "Builder,\n";
+  }
+  if (unknown_fields_) {
+    indent(f_mod_) << "unknown_fields = true,\n";
+  }
 
   indent(f_mod_) << "fields = {\n";
   indent_up();
@@ -277,13 +334,21 @@ void t_rs_generator::generate_struct(t_struct* tstruct) {
   for (m_iter = members.begin(); m_iter != members.end(); ++m_iter) {
     t_field* tfield = *m_iter;
     string type = render_rs_type(tfield->get_type());
-    // like the Java generator, "default" requiredness is treated as required
+    // only "required" fields fail builders when unset, others keep their
+    // defaults
     if (tfield->get_req() == t_field::T_OPTIONAL) {
       type = "Option<" + type + ">";
     }
-    indent(f_mod_) << to_field_name(tfield->get_name())
-      << ": " << type
-      << " => " << tfield->get_key() << ",\n";
+    indent(f_mod_);
+    if (tfield->get_req() == t_field::T_REQUIRED) {
+      f_mod_ << "#[required] ";
+    }
+    f_mod_ << to_field_name(tfield->get_name()) << ": " << type;
+    if (tfield->get_req() != t_field::T_OPTIONAL && tfield->get_value() != NULL) {
+      f_mod_ << " = " << render_const_value(tfield->get_type(), tfield->get_value())
+             << render_suffix(tfield->get_type());
+    }
+    f_mod_ << " => " << tfield->get_key() << ",\n";
   }
 
   indent_down();
@@ -293,12 +358,35 @@ void t_rs_generator::generate_struct(t_struct* tstruct) {
   indent(f_mod_) << "}\n\n"; // Close strukt invocation.
 }
 
+// Generates all constants in a single `consts!` invocation. Base types and enums become
+// rust `const`s, everything else a lazily initialized `static`.
+void t_rs_generator::generate_consts(vector<t_const*> consts) {
+  if (consts.empty()) {
+    return;
+  }
+
+  indent(f_mod_) << "consts! {\n";
+  indent_up();
+
+  vector<t_const*>::iterator c_iter;
+  for (c_iter = consts.begin(); c_iter != consts.end(); ++c_iter) {
+    t_type* type = (*c_iter)->get_type();
+    indent(f_mod_) << (is_const_expr(type) ? "const " : "static ")
+      << normalize_id((*c_iter)->get_name()) << ": " << render_const_type(type)
+      << " = " << render_const_value(type, (*c_iter)->get_value()) << ";\n";
+  }
+
+  indent_down();
+  indent(f_mod_) << "}\n\n"; // Close consts invocation.
+}
+
 // Generate a service, translating from a thrift service to a rust trait.
 void t_rs_generator::generate_service(t_service* tservice) {
     const string sname = pascalcase(tservice->get_name());
     const string trait_name = sname;
     const string processor_name = sname + "Processor";
     const string client_name = sname + "Client";
+    const string async_client_name = sname + "AsyncClient";
 
     indent(f_mod_) << "service! {\n";
     indent_up();
@@ -307,6 +395,7 @@ void t_rs_generator::generate_service(t_service* tservice) {
     indent(f_mod_) << "trait_name = " << trait_name << ",\n";
     indent(f_mod_) << "processor_name = " << processor_name << ",\n";
     indent(f_mod_) << "client_name = " << client_name << ",\n";
+    indent(f_mod_) << "async_client_name = " << async_client_name << ",\n";
 
     // The methods originating in this service to go in the service trait.
     indent(f_mod_) << "service_methods = [\n";
@@ -332,6 +421,13 @@ void t_rs_generator::generate_service(t_service* tservice) {
     indent_down();
     indent(f_mod_) << "],\n";
 
+    // The service this one extends, for the service descriptor.
+    indent(f_mod_) << "extends = [";
+    if (tservice->get_extends()) {
+      f_mod_ << pascalcase(tservice->get_extends()->get_name());
+    }
+    f_mod_ << "],\n";
+
     indent(f_mod_) << "bounds = [";
     generate_service_generics(tservice);
     f_mod_ << "],\n";
@@ -375,7 +471,11 @@ void t_rs_generator::generate_service_methods(char field, t_service* tservice) {
           rettype = "Result<" + rettype + ", " + errname + ">";
 	}
 
-        indent(f_mod_) << "] (" << rettype << "),\n";
+        bool idempotent = tfunction->annotations_.count("idempotent") > 0;
+        indent(f_mod_) << "] (" << rettype << ") oneway = "
+          << (tfunction->is_oneway() ? "true" : "false")
+          << ", idempotent = " << (idempotent ? "true" : "false")
+          << ", context = " << (context_ ? "true" : "false") << ",\n";
     }
 }
 
@@ -408,8 +508,13 @@ void t_rs_generator::generate_service_method_arglist(const vector<t_field*>& fie
     for (field_iter = fields.begin(); field_iter != fields.end(); ++field_iter) {
         t_field* tfield = *field_iter;
         indent(f_mod_) << to_field_name(tfield->get_name())
-            << ": " << render_rs_type(tfield->get_type())
-            << " => " << tfield->get_key() << ",\n";
+            << ": " << render_rs_type(tfield->get_type());
+        // Used by processors when a call leaves the argument out.
+        if (tfield->get_value() != NULL) {
+          f_mod_ << " = " << render_const_value(tfield->get_type(), tfield->get_value())
+                 << render_suffix(tfield->get_type());
+        }
+        f_mod_ << " => " << tfield->get_key() << ",\n";
     }
 }
 
@@ -483,5 +588,175 @@ string t_rs_generator::render_rs_type(t_type* type) {
   return ""; // silence the compiler warning
 }
 
-THRIFT_REGISTER_GENERATOR(rs, "Rust", "")
+bool t_rs_generator::is_const_expr(t_type* type) {
+  type = get_true_type(type);
+  return type->is_base_type() || type->is_enum();
+}
+
+// Renders the type of a top-level constant. Strings are borrowed so that they can be
+// declared as `const`.
+string t_rs_generator::render_const_type(t_type* type) {
+  if (is_string(get_true_type(type))) {
+    return "&'static str";
+  } else if (is_binary(get_true_type(type))) {
+    return "&'static [u8]";
+  }
+  return render_rs_type(type);
+}
+
+// Renders a rust expression building the passed in constant value. Type checking has
+// already been done by the parser.
+string t_rs_generator::render_const_value(t_type* type, t_const_value* value) {
+  type = get_true_type(type);
+  std::ostringstream out;
+
+  if (type->is_base_type()) {
+    t_base_type::t_base tbase = ((t_base_type*)type)->get_base();
+    switch (tbase) {
+    case t_base_type::TYPE_STRING:
+      if (is_binary(type)) {
+        out << "b\"" << get_escaped_string(value) << "\"";
+      } else {
+        out << "\"" << get_escaped_string(value) << "\"";
+      }
+      break;
+    case t_base_type::TYPE_BOOL:
+      out << (value->get_integer() > 0 ? "true" : "false");
+      break;
+    case t_base_type::TYPE_BYTE:
+    case t_base_type::TYPE_I16:
+    case t_base_type::TYPE_I32:
+    case t_base_type::TYPE_I64:
+      out << value->get_integer();
+      break;
+    case t_base_type::TYPE_DOUBLE: {
+      std::ostringstream num;
+      if (value->get_type() == t_const_value::CV_INTEGER) {
+        num << value->get_integer();
+      } else {
+        num.precision(17);
+        num << value->get_double();
+      }
+      string literal = num.str();
+      if (literal.find_first_of(".eE") == string::npos) {
+        literal += ".0";
+      }
+      out << "OrderedFloat(" << literal << ")";
+      break;
+    }
+    default:
+      throw "compiler error: no const of base type " + t_base_type::t_base_name(tbase);
+    }
+
+  } else if (type->is_enum()) {
+    t_enum_value* tvalue = ((t_enum*)type)->get_constant_by_value(value->get_integer());
+    if (tvalue == NULL) {
+      throw "compiler error: no enum value " + value->get_identifier() + " in " + type->get_name();
+    }
+    out << render_rs_type(type) << "::" << capitalize(tvalue->get_name());
+
+  } else if (type->is_struct() || type->is_xception()) {
+    const vector<t_field*>& fields = ((t_struct*)type)->get_members();
+    const map<t_const_value*, t_const_value*>& val = value->get_map();
+    map<t_const_value*, t_const_value*>::const_iterator v_iter;
+
+    out << "{\n";
+    indent_up();
+    indent(out) << "let mut s = " << render_rs_type(type) << "::default();\n";
+    for (v_iter = val.begin(); v_iter != val.end(); ++v_iter) {
+      t_field* tfield = NULL;
+      vector<t_field*>::const_iterator f_iter;
+      for (f_iter = fields.begin(); f_iter != fields.end(); ++f_iter) {
+        if ((*f_iter)->get_name() == v_iter->first->get_string()) {
+          tfield = *f_iter;
+        }
+      }
+      if (tfield == NULL) {
+        throw "type error: " + type->get_name() + " has no field " + v_iter->first->get_string();
+      }
+
+      string rendered = render_const_value(tfield->get_type(), v_iter->second);
+      rendered += render_suffix(tfield->get_type());
+      if (tfield->get_req() == t_field::T_OPTIONAL) {
+        rendered = "Some(" + rendered + ")";
+      }
+      indent(out) << "s." << to_field_name(tfield->get_name()) << " = " << rendered << ";\n";
+    }
+    indent(out) << "s\n";
+    indent_down();
+    indent(out) << "}";
+
+  } else if (type->is_map()) {
+    t_type* ktype = ((t_map*)type)->get_key_type();
+    t_type* vtype = ((t_map*)type)->get_val_type();
+    const map<t_const_value*, t_const_value*>& val = value->get_map();
+    map<t_const_value*, t_const_value*>::const_iterator v_iter;
+
+    out << "{\n";
+    indent_up();
+    indent(out) << "let mut m = BTreeMap::new();\n";
+    for (v_iter = val.begin(); v_iter != val.end(); ++v_iter) {
+      indent(out) << "m.insert("
+        << render_const_value(ktype, v_iter->first) << render_suffix(ktype) << ", "
+        << render_const_value(vtype, v_iter->second) << render_suffix(vtype) << ");\n";
+    }
+    indent(out) << "m\n";
+    indent_down();
+    indent(out) << "}";
+
+  } else if (type->is_set()) {
+    t_type* etype = ((t_set*)type)->get_elem_type();
+    const vector<t_const_value*>& val = value->get_list();
+    vector<t_const_value*>::const_iterator v_iter;
+
+    out << "{\n";
+    indent_up();
+    indent(out) << "let mut s = BTreeSet::new();\n";
+    for (v_iter = val.begin(); v_iter != val.end(); ++v_iter) {
+      indent(out) << "s.insert(" << render_const_value(etype, *v_iter) << render_suffix(etype) << ");\n";
+    }
+    indent(out) << "s\n";
+    indent_down();
+    indent(out) << "}";
+
+  } else if (type->is_list()) {
+    t_type* etype = ((t_list*)type)->get_elem_type();
+    const vector<t_const_value*>& val = value->get_list();
+    vector<t_const_value*>::const_iterator v_iter;
+
+    out << "vec![";
+    for (v_iter = val.begin(); v_iter != val.end(); ++v_iter) {
+      if (v_iter != val.begin()) {
+        out << ", ";
+      }
+      out << render_const_value(etype, *v_iter) << render_suffix(etype);
+    }
+    out << "]";
+
+  } else {
+    throw "CANNOT GENERATE CONSTANT FOR TYPE: " + type->get_name();
+  }
+
+  return out.str();
+}
+
+// Renders the conversion needed to turn a borrowed string literal into the owned
+// value stored inside composite constants.
+string t_rs_generator::render_suffix(t_type* type) {
+  type = get_true_type(type);
+  if (is_string(type)) {
+    return ".to_owned()";
+  } else if (is_binary(type)) {
+    return ".to_vec()";
+  }
+  return "";
+}
+
+THRIFT_REGISTER_GENERATOR(
+    rs,
+    "Rust",
+    "    context:         Pass a RequestContext to service handler methods.\n"
+    "    builders:        Generate a builder for every struct and exception.\n"
+    "    unknown_fields:  Keep unknown struct fields and write them back out when encoding.\n"
+    "    unknown_enums:   Decode unknown enum values to an extra Unknown(i32) variant.\n")
 
@@ -0,0 +1,137 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+//! The parsed form of an IDL file. Names of other definitions are kept as
+//! written and only resolved when generating code.
+
+#[derive(Debug, Default)]
+pub struct Program {
+    pub name: String,
+    pub includes: Vec<String>,
+    pub typedefs: Vec<Typedef>,
+    pub enums: Vec<Enum>,
+    pub senums: Vec<String>,
+    /// Structs, unions and exceptions, in declaration order.
+    pub objects: Vec<Struct>,
+    pub consts: Vec<Const>,
+    pub services: Vec<Service>,
+}
+
+#[derive(Debug)]
+pub struct Typedef {
+    pub name: String,
+    pub ty: Type,
+}
+
+#[derive(Debug)]
+pub struct Enum {
+    pub name: String,
+    pub values: Vec<EnumValue>,
+}
+
+#[derive(Debug)]
+pub struct EnumValue {
+    pub name: String,
+    pub value: i32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StructKind {
+    Struct,
+    Union,
+    Exception,
+}
+
+#[derive(Debug)]
+pub struct Struct {
+    pub name: String,
+    pub kind: StructKind,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Requiredness {
+    Required,
+    Optional,
+    Default,
+}
+
+#[derive(Debug)]
+pub struct Field {
+    pub id: i32,
+    pub name: String,
+    pub ty: Type,
+    pub requiredness: Requiredness,
+    pub default: Option<ConstValue>,
+}
+
+#[derive(Debug)]
+pub struct Const {
+    pub name: String,
+    pub ty: Type,
+    pub value: ConstValue,
+}
+
+#[derive(Debug)]
+pub struct Service {
+    pub name: String,
+    pub extends: Option<String>,
+    pub functions: Vec<Function>,
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub returns: Type,
+    pub args: Vec<Field>,
+    pub throws: Vec<Field>,
+    pub oneway: bool,
+    /// Marked `(idempotent)`, so clients may retry it.
+    pub idempotent: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Void,
+    Bool,
+    Byte,
+    I16,
+    I32,
+    I64,
+    Double,
+    String,
+    Binary,
+    List(Box<Type>),
+    Set(Box<Type>),
+    Map(Box<Type>, Box<Type>),
+    /// A typedef, enum or struct, possibly prefixed with the name of an
+    /// included program.
+    Named(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConstValue {
+    Int(i64),
+    Double(f64),
+    String(String),
+    /// An enum value or another constant.
+    Ident(String),
+    List(Vec<ConstValue>),
+    Map(Vec<(ConstValue, ConstValue)>),
+}
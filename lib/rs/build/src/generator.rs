@@ -0,0 +1,697 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+//! Renders a parsed program the way the C++ compiler's `rs` generator does,
+//! so that either can be used to build the same crate.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use ast::*;
+use Options;
+
+const THRIFT_VERSION: &str = "1.0.0-dev";
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "alignof", "as", "be", "box", "break", "const", "continue", "crate", "do", "else", "enum",
+    "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match", "mod",
+    "move", "mut", "offsetof", "override", "priv", "pub", "pure", "ref", "return", "sizeof", "static",
+    "self", "struct", "super", "true", "trait", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// Every program that has been parsed, by name.
+pub type Programs = HashMap<String, Program>;
+
+pub type GenerateResult<T> = Result<T, String>;
+
+/// A type with all typedefs resolved.
+enum TrueType<'a> {
+    Void,
+    Bool,
+    Byte,
+    I16,
+    I32,
+    I64,
+    Double,
+    String,
+    Binary,
+    Enum(&'a Enum),
+    Struct(&'a Program, &'a Struct),
+    List(Box<TrueType<'a>>),
+    Set(Box<TrueType<'a>>),
+    Map(Box<TrueType<'a>>, Box<TrueType<'a>>),
+}
+
+/// How generated code names the types it uses from outside the module.
+pub struct Paths {
+    ordered_float: String,
+    btree_map: String,
+    btree_set: String,
+}
+
+impl Paths {
+    /// The names brought into scope by the module header.
+    pub fn imported() -> Self {
+        Paths {
+            ordered_float: String::from("OrderedFloat"),
+            btree_map: String::from("BTreeMap"),
+            btree_set: String::from("BTreeSet"),
+        }
+    }
+
+    /// Absolute paths, with the runtime found at `runtime`.
+    pub fn absolute(runtime: &str) -> Self {
+        Paths {
+            ordered_float: format!("{}::rt::OrderedFloat", runtime),
+            btree_map: String::from("::std::collections::BTreeMap"),
+            btree_set: String::from("::std::collections::BTreeSet"),
+        }
+    }
+}
+
+pub struct Generator<'a> {
+    programs: &'a Programs,
+    program: &'a Program,
+    options: &'a Options,
+    paths: Paths,
+    inline: bool,
+    out: String,
+    indent: usize,
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(programs: &'a Programs, program: &'a Program, options: &'a Options) -> Self {
+        Generator {
+            programs,
+            program,
+            options,
+            paths: Paths::imported(),
+            inline: false,
+            out: String::new(),
+            indent: 0,
+        }
+    }
+
+    /// Generate bare items that can be expanded into any module: without the
+    /// header, and naming everything from outside the module by `paths`.
+    /// Parent services from other programs must already be in scope.
+    pub fn inline(mut self, paths: Paths) -> Self {
+        self.paths = paths;
+        self.inline = true;
+        self
+    }
+
+    pub fn generate(mut self) -> GenerateResult<String> {
+        let program = self.program;
+
+        if !self.inline {
+            self.header()?;
+        }
+        for enom in &program.enums {
+            self.enom(enom)?;
+        }
+        for typedef in &program.typedefs {
+            let rendered = self.render_type(program, &typedef.ty)?;
+            self.line(&format!("pub type {} = {};", pascalcase(&typedef.name), rendered));
+            self.out.push('\n');
+        }
+        for strukt in &program.objects {
+            self.strukt(strukt)?;
+        }
+        self.consts(&program.consts)?;
+        for service in &program.services {
+            self.service(service)?;
+        }
+
+        Ok(self.out)
+    }
+
+    fn header(&mut self) -> GenerateResult<()> {
+        self.out.push_str("///////////////////////////////////////////////////////////////\n");
+        let _ = writeln!(self.out, "// Autogenerated by Thrift Compiler ({})", THRIFT_VERSION);
+        self.out.push_str("//\n");
+        self.out.push_str("// DO NOT EDIT UNLESS YOU ARE SURE YOU KNOW WHAT YOU ARE DOING\n");
+        self.out.push_str("///////////////////////////////////////////////////////////////\n\n");
+        self.out.push_str("#![allow(unused_mut, dead_code, non_snake_case, non_upper_case_globals, unused_imports)]\n");
+        self.out.push_str("use ::thrift::rt::OrderedFloat;\n");
+        self.out.push_str("use std::collections::{BTreeMap, BTreeSet};\n\n");
+
+        for service in &self.program.services {
+            self.service_uses(service)?;
+        }
+        Ok(())
+    }
+
+    fn line(&mut self, text: &str) {
+        self.start_line();
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn start_line(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str("  ");
+        }
+    }
+
+    fn enom(&mut self, enom: &Enum) -> GenerateResult<()> {
+        if enom.values.is_empty() {
+            return Err(format!("enum {} has no values", enom.name));
+        }
+
+        self.line("enom! {");
+        self.indent += 1;
+        self.line(&format!("name = {},", pascalcase(&enom.name)));
+        self.line("values = [");
+        self.indent += 1;
+        for value in &enom.values {
+            let name = capitalize(&value.name);
+            // Display and FromStr use the IDL name.
+            if name != value.name {
+                self.line(&format!("{} = {} => \"{}\",", name, value.value, value.name));
+            } else {
+                self.line(&format!("{} = {},", name, value.value));
+            }
+        }
+        self.indent -= 1;
+        self.line("],");
+
+        if self.options.unknown_enums {
+            // Pick a variant name the IDL does not use already.
+            let mut unknown = String::from("Unknown");
+            while enom.values.iter().any(|value| capitalize(&value.name) == unknown) {
+                unknown.push_str("Value");
+            }
+            self.line(&format!("unknown = {},", unknown));
+        }
+
+        self.line(&format!("default = {}", capitalize(&enom.values[0].name)));
+        self.indent -= 1;
+        self.line("}");
+        self.out.push('\n');
+        Ok(())
+    }
+
+    fn strukt(&mut self, strukt: &Struct) -> GenerateResult<()> {
+        let name = pascalcase(&strukt.name);
+
+        self.line(if strukt.kind == StructKind::Exception { "exception! {" } else { "strukt! {" });
+        self.indent += 1;
+        self.line(&format!("name = {},", name));
+        if self.options.builders {
+            self.line(&format!("builder = {}Builder,", name));
+        }
+        if self.options.unknown_fields {
+            self.line("unknown_fields = true,");
+        }
+
+        self.line("fields = {");
+        self.indent += 1;
+        for field in &strukt.fields {
+            let mut ty = self.render_type(self.program, &field.ty)?;
+            // Only required fields fail builders when unset, others keep
+            // their defaults.
+            let mut default = String::new();
+            match field.requiredness {
+                Requiredness::Optional => ty = format!("Option<{}>", ty),
+                Requiredness::Required | Requiredness::Default => if let Some(ref value) = field.default {
+                    let true_type = self.true_type(self.program, &field.ty)?;
+                    let value = self.render_const_value(self.program, &true_type, value)?;
+                    default = format!(" = {}{}", value, suffix(&true_type));
+                },
+            }
+            let required = if field.requiredness == Requiredness::Required { "#[required] " } else { "" };
+            self.line(&format!("{}{}: {}{} => {},", required, to_field_name(&field.name), ty, default, field.id));
+        }
+        self.indent -= 1;
+        self.line("}");
+
+        self.indent -= 1;
+        self.line("}");
+        self.out.push('\n');
+        Ok(())
+    }
+
+    fn consts(&mut self, consts: &[Const]) -> GenerateResult<()> {
+        if consts.is_empty() {
+            return Ok(());
+        }
+
+        let program = self.program;
+        self.line("consts! {");
+        self.indent += 1;
+        for constant in consts {
+            let ty = self.true_type(program, &constant.ty)?;
+            let keyword = match ty {
+                TrueType::List(_) | TrueType::Set(_) | TrueType::Map(..) | TrueType::Struct(..) => "static",
+                _ => "const",
+            };
+            let rendered_type = match ty {
+                TrueType::String => String::from("&'static str"),
+                TrueType::Binary => String::from("&'static [u8]"),
+                _ => self.render_type(program, &constant.ty)?,
+            };
+            let value = self.render_const_value(program, &ty, &constant.value)?;
+            self.line(&format!("{} {}: {} = {};", keyword, normalize_id(&constant.name), rendered_type, value));
+        }
+        self.indent -= 1;
+        self.line("}");
+        self.out.push('\n');
+        Ok(())
+    }
+
+    fn service_uses(&mut self, service: &Service) -> GenerateResult<()> {
+        let mut current = (self.program, service);
+        while let Some(parent) = self.parent(current.0, current.1)? {
+            // Parents from this file are already in scope.
+            if parent.0.name != self.program.name {
+                self.line(&format!("use {}::*;", parent.0.name));
+            }
+            current = parent;
+        }
+        self.out.push('\n');
+        Ok(())
+    }
+
+    fn service(&mut self, service: &Service) -> GenerateResult<()> {
+        let name = pascalcase(&service.name);
+
+        self.line("service! {");
+        self.indent += 1;
+        self.line(&format!("trait_name = {},", name));
+        self.line(&format!("processor_name = {}Processor,", name));
+        self.line(&format!("client_name = {}Client,", name));
+        self.line(&format!("async_client_name = {}AsyncClient,", name));
+
+        self.line("service_methods = [");
+        self.indent += 1;
+        self.service_methods('a', self.program, service)?;
+        self.indent -= 1;
+        self.line("],");
+
+        let mut ancestors = Vec::new();
+        let mut current = (self.program, service);
+        while let Some(parent) = self.parent(current.0, current.1)? {
+            ancestors.push(parent);
+            current = parent;
+        }
+
+        self.line("parent_methods = [");
+        self.indent += 1;
+        for (&(program, parent), field) in ancestors.iter().zip((b'b'..b'z' + 1).map(|b| b as char)) {
+            self.service_methods(field, program, parent)?;
+        }
+        self.indent -= 1;
+        self.line("],");
+
+        let extends = ancestors.first().map_or(String::new(), |&(_, parent)| pascalcase(&parent.name));
+        self.line(&format!("extends = [{}],", extends));
+
+        let chain: Vec<_> = Some(service).into_iter().chain(ancestors.iter().map(|&(_, parent)| parent))
+            .take(26).collect();
+        self.start_line();
+        self.out.push_str("bounds = [");
+        for (service, generic) in chain.iter().zip(b'A'..b'Z' + 1) {
+            let _ = write!(self.out, "{}: {}, ", generic as char, service.name);
+        }
+        self.out.push_str("],\n");
+
+        self.start_line();
+        self.out.push_str("fields = [");
+        for (generic, field) in (b'A'..b'A' + chain.len() as u8).zip(b'a'..b'z' + 1) {
+            let _ = write!(self.out, "{}: {}, ", field as char, generic as char);
+        }
+        self.out.push_str("]\n");
+
+        self.indent -= 1;
+        self.line("}");
+        self.out.push('\n');
+        Ok(())
+    }
+
+    fn service_methods(&mut self, field: char, program: &'a Program, service: &Service) -> GenerateResult<()> {
+        let sname = pascalcase(&service.name);
+
+        for function in &service.functions {
+            let method = pascalcase(&function.name);
+            let errname = format!("{}{}Error", sname, method);
+
+            self.line(&format!("{}{}Args -> {}{}Result = {}.{}(", sname, method, sname, method, field, function.name));
+            self.indent += 1;
+            for arg in &function.args {
+                let ty = self.render_type(program, &arg.ty)?;
+                let default = match arg.default {
+                    Some(ref value) => {
+                        let true_type = self.true_type(program, &arg.ty)?;
+                        let value = self.render_const_value(program, &true_type, value)?;
+                        format!(" = {}{}", value, suffix(&true_type))
+                    }
+                    None => String::new(),
+                };
+                self.line(&format!("{}: {}{} => {},", to_field_name(&arg.name), ty, default, arg.id));
+            }
+            self.indent -= 1;
+
+            let returns = self.render_type(program, &function.returns)?;
+            self.line(&format!(") -> {} => {} = [", returns, errname));
+            self.indent += 1;
+            for exception in &function.throws {
+                let name = to_field_name(&exception.name);
+                let ty = self.render_type(program, &exception.ty)?;
+                self.line(&format!("{}({}: {} => {}),", pascalcase(&name), name, ty, exception.id));
+            }
+            self.indent -= 1;
+
+            let rettype = if function.throws.is_empty() {
+                returns
+            } else {
+                format!("Result<{}, {}>", returns, errname)
+            };
+            self.line(&format!("] ({}) oneway = {}, idempotent = {}, context = {},",
+                               rettype, function.oneway, function.idempotent, self.options.context));
+        }
+
+        Ok(())
+    }
+
+    fn parent(&self, program: &'a Program, service: &Service) -> GenerateResult<Option<(&'a Program, &'a Service)>> {
+        let extends = match service.extends {
+            Some(ref extends) => extends,
+            None => return Ok(None),
+        };
+
+        let (program, name) = self.scope(program, extends);
+        match program.services.iter().find(|service| service.name == name) {
+            Some(parent) => Ok(Some((program, parent))),
+            None => Err(format!("service {} extends unknown service {}", service.name, extends)),
+        }
+    }
+
+    /// Split an `include.Name` reference into the included program and the
+    /// name.
+    fn scope<'n>(&self, program: &'a Program, name: &'n str) -> (&'a Program, &'n str) {
+        if let Some(dot) = name.find('.') {
+            if program.includes.iter().any(|include| *include == name[..dot]) {
+                if let Some(included) = self.programs.get(&name[..dot]) {
+                    return (included, &name[dot + 1..]);
+                }
+            }
+        }
+        (program, name)
+    }
+
+    fn true_type(&self, program: &'a Program, ty: &Type) -> GenerateResult<TrueType<'a>> {
+        Ok(match *ty {
+            Type::Void => TrueType::Void,
+            Type::Bool => TrueType::Bool,
+            Type::Byte => TrueType::Byte,
+            Type::I16 => TrueType::I16,
+            Type::I32 => TrueType::I32,
+            Type::I64 => TrueType::I64,
+            Type::Double => TrueType::Double,
+            Type::String => TrueType::String,
+            Type::Binary => TrueType::Binary,
+            Type::List(ref elem) => TrueType::List(Box::new(self.true_type(program, elem)?)),
+            Type::Set(ref elem) => TrueType::Set(Box::new(self.true_type(program, elem)?)),
+            Type::Map(ref key, ref value) => TrueType::Map(Box::new(self.true_type(program, key)?),
+                                                           Box::new(self.true_type(program, value)?)),
+            Type::Named(ref name) => {
+                let (program, name) = self.scope(program, name);
+                if let Some(typedef) = program.typedefs.iter().find(|typedef| typedef.name == name) {
+                    self.true_type(program, &typedef.ty)?
+                } else if let Some(enom) = program.enums.iter().find(|enom| enom.name == name) {
+                    TrueType::Enum(enom)
+                } else if let Some(strukt) = program.objects.iter().find(|strukt| strukt.name == name) {
+                    TrueType::Struct(program, strukt)
+                } else if program.senums.iter().any(|senum| senum == name) {
+                    TrueType::String
+                } else {
+                    return Err(format!("type {} has not been defined", name));
+                }
+            }
+        })
+    }
+
+    fn render_type(&self, program: &'a Program, ty: &Type) -> GenerateResult<String> {
+        let ty = self.true_type(program, ty)?;
+        Ok(render_true_type(&ty, &self.paths))
+    }
+
+    /// Render an expression building a constant. Strings are borrowed at the
+    /// top level and owned inside composite constants.
+    fn render_const_value(&mut self, program: &'a Program, ty: &TrueType<'a>, value: &ConstValue)
+                          -> GenerateResult<String> {
+        if let ConstValue::Ident(ref name) = *value {
+            if let TrueType::Enum(enom) = *ty {
+                let short = name.rsplit('.').next().unwrap();
+                if let Some(value) = enom.values.iter().find(|value| value.name == short) {
+                    return self.render_const_value(program, ty, &ConstValue::Int(value.value as i64));
+                }
+            }
+
+            let (scope, short) = self.scope(program, name);
+            return match scope.consts.iter().find(|constant| constant.name == short) {
+                Some(constant) => self.render_const_value(scope, ty, &constant.value),
+                None => Err(format!("constant {} has not been defined", name)),
+            };
+        }
+
+        let mismatch = || format!("constant value {:?} does not match its type", value);
+
+        Ok(match (ty, value) {
+            (&TrueType::String, ConstValue::String(s)) => format!("\"{}\"", escape(s)),
+            (&TrueType::Binary, ConstValue::String(s)) => format!("b\"{}\"", escape(s)),
+            (&TrueType::Bool, &ConstValue::Int(i)) => String::from(if i > 0 { "true" } else { "false" }),
+            (&TrueType::Byte, &ConstValue::Int(i)) | (&TrueType::I16, &ConstValue::Int(i)) |
+            (&TrueType::I32, &ConstValue::Int(i)) | (&TrueType::I64, &ConstValue::Int(i)) => i.to_string(),
+            (&TrueType::Double, &ConstValue::Int(i)) => format!("{}({}.0)", self.paths.ordered_float, i),
+            (&TrueType::Double, &ConstValue::Double(d)) => {
+                let mut literal = format_double(d);
+                if !literal.contains(['.', 'e', 'E']) {
+                    literal.push_str(".0");
+                }
+                format!("{}({})", self.paths.ordered_float, literal)
+            }
+            (&TrueType::Enum(enom), &ConstValue::Int(i)) => {
+                match enom.values.iter().find(|value| value.value as i64 == i) {
+                    Some(value) => format!("{}::{}", capitalize(&enom.name), capitalize(&value.name)),
+                    None => return Err(format!("no enum value {} in {}", i, enom.name)),
+                }
+            }
+            (&TrueType::Struct(scope, strukt), ConstValue::Map(entries)) => {
+                let mut out = String::from("{\n");
+                self.indent += 1;
+                out.push_str(&self.indented(&format!("let mut s = {}::default();\n", capitalize(&strukt.name))));
+                for (key, value) in entries {
+                    let field = match *key {
+                        ConstValue::String(ref name) => strukt.fields.iter().find(|field| field.name == *name),
+                        _ => None,
+                    };
+                    let field = match field {
+                        Some(field) => field,
+                        None => return Err(format!("type error: {} has no field {:?}", strukt.name, key)),
+                    };
+
+                    let field_ty = self.true_type(scope, &field.ty)?;
+                    let mut rendered = self.render_const_value(program, &field_ty, value)?;
+                    rendered.push_str(suffix(&field_ty));
+                    if field.requiredness == Requiredness::Optional {
+                        rendered = format!("Some({})", rendered);
+                    }
+                    out.push_str(&self.indented(&format!("s.{} = {};\n", to_field_name(&field.name), rendered)));
+                }
+                out.push_str(&self.indented("s\n"));
+                self.indent -= 1;
+                out.push_str(&self.indented("}"));
+                out
+            }
+            (TrueType::Map(key_ty, value_ty), ConstValue::Map(entries)) => {
+                let mut out = String::from("{\n");
+                self.indent += 1;
+                let new = format!("let mut m = {}::new();\n", self.paths.btree_map);
+                out.push_str(&self.indented(&new));
+                for (key, value) in entries {
+                    let key = self.render_const_value(program, key_ty, key)?;
+                    let value = self.render_const_value(program, value_ty, value)?;
+                    out.push_str(&self.indented(&format!("m.insert({}{}, {}{});\n",
+                                                         key, suffix(key_ty), value, suffix(value_ty))));
+                }
+                out.push_str(&self.indented("m\n"));
+                self.indent -= 1;
+                out.push_str(&self.indented("}"));
+                out
+            }
+            (TrueType::Set(elem_ty), ConstValue::List(elems)) => {
+                let mut out = String::from("{\n");
+                self.indent += 1;
+                let new = format!("let mut s = {}::new();\n", self.paths.btree_set);
+                out.push_str(&self.indented(&new));
+                for elem in elems {
+                    let elem = self.render_const_value(program, elem_ty, elem)?;
+                    out.push_str(&self.indented(&format!("s.insert({}{});\n", elem, suffix(elem_ty))));
+                }
+                out.push_str(&self.indented("s\n"));
+                self.indent -= 1;
+                out.push_str(&self.indented("}"));
+                out
+            }
+            (TrueType::List(elem_ty), ConstValue::List(elems)) => {
+                let mut rendered = Vec::new();
+                for elem in elems {
+                    let elem = self.render_const_value(program, elem_ty, elem)?;
+                    rendered.push(format!("{}{}", elem, suffix(elem_ty)));
+                }
+                format!("vec![{}]", rendered.join(", "))
+            }
+            _ => return Err(mismatch()),
+        })
+    }
+
+    fn indented(&self, text: &str) -> String {
+        let mut out = String::new();
+        for _ in 0..self.indent {
+            out.push_str("  ");
+        }
+        out.push_str(text);
+        out
+    }
+}
+
+fn render_true_type(ty: &TrueType, paths: &Paths) -> String {
+    match *ty {
+        TrueType::Void => String::from("()"),
+        TrueType::Bool => String::from("bool"),
+        TrueType::Byte => String::from("i8"),
+        TrueType::I16 => String::from("i16"),
+        TrueType::I32 => String::from("i32"),
+        TrueType::I64 => String::from("i64"),
+        TrueType::Double => format!("{}<f64>", paths.ordered_float),
+        TrueType::String => String::from("String"),
+        TrueType::Binary => String::from("Vec<u8>"),
+        TrueType::Enum(enom) => capitalize(&enom.name),
+        TrueType::Struct(_, strukt) => capitalize(&strukt.name),
+        TrueType::List(ref elem) => format!("Vec<{}>", render_true_type(elem, paths)),
+        TrueType::Set(ref elem) => format!("{}<{}>", paths.btree_set, render_true_type(elem, paths)),
+        TrueType::Map(ref key, ref value) => {
+            format!("{}<{}, {}>", paths.btree_map, render_true_type(key, paths), render_true_type(value, paths))
+        }
+    }
+}
+
+/// The conversion turning a borrowed string literal into the owned value
+/// stored inside composite constants.
+fn suffix(ty: &TrueType) -> &'static str {
+    match *ty {
+        TrueType::String => ".to_owned()",
+        TrueType::Binary => ".to_vec()",
+        _ => "",
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Format like `printf("%.17g")`, which is how the C++ compiler prints
+/// doubles.
+fn format_double(d: f64) -> String {
+    if d == 0.0 || !d.is_finite() {
+        return format!("{}", d);
+    }
+
+    let scientific = format!("{:.16e}", d);
+    let e = scientific.find('e').unwrap();
+    let exponent: i32 = scientific[e + 1..].parse().unwrap();
+
+    if !(-4..17).contains(&exponent) {
+        let mantissa = trim_fraction(&scientific[..e]);
+        format!("{}e{}{:02}", mantissa, if exponent < 0 { '-' } else { '+' }, exponent.abs())
+    } else {
+        trim_fraction(&format!("{:.*}", (16 - exponent) as usize, d)).to_owned()
+    }
+}
+
+fn trim_fraction(number: &str) -> &str {
+    if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    }
+}
+
+pub fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+/// `a_multi_word` becomes `AMultiWord`.
+pub fn pascalcase(name: &str) -> String {
+    let mut out = String::new();
+    let mut underscore = false;
+    for c in name.chars() {
+        if c == '_' {
+            underscore = true;
+        } else if underscore {
+            out.push(c.to_ascii_uppercase());
+            underscore = false;
+        } else {
+            out.push(c);
+        }
+    }
+    capitalize(&out)
+}
+
+/// `aMultiWord` becomes `a_multi_word`.
+pub fn underscore(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if i == 0 {
+            out.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_uppercase() {
+            out.push('_');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn normalize_id(id: &str) -> String {
+    if KEYWORDS.contains(&id) { format!("{}_", id) } else { id.to_owned() }
+}
+
+fn to_field_name(name: &str) -> String {
+    normalize_id(&underscore(name))
+}
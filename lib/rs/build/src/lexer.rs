@@ -0,0 +1,211 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::str::Chars;
+use std::iter::Peekable;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Ident(String),
+    Int(i64),
+    Double(f64),
+    Literal(String),
+    Symbol(char),
+}
+
+pub struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Lexer { chars: source.chars().peekable(), line: 1 }
+    }
+
+    /// Split the whole source into tokens, each with the line it starts on.
+    pub fn tokenize(mut self) -> Result<Vec<(Token, usize)>, (usize, String)> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_blanks()?;
+            let line = self.line;
+            match self.next_token()? {
+                Some(token) => tokens.push((token, line)),
+                None => return Ok(tokens),
+            }
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().cloned()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        let mut chars = self.chars.clone();
+        chars.next();
+        chars.next()
+    }
+
+    fn skip_blanks(&mut self) -> Result<(), (usize, String)> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => { self.bump(); }
+                Some('#') => self.skip_line(),
+                Some('/') if self.peek_second() == Some('/') => self.skip_line(),
+                Some('/') if self.peek_second() == Some('*') => {
+                    let line = self.line;
+                    self.bump();
+                    self.bump();
+                    let mut last = None;
+                    loop {
+                        match self.bump() {
+                            Some('/') if last == Some('*') => break,
+                            Some(c) => last = Some(c),
+                            None => return Err((line, String::from("unterminated comment"))),
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.bump() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, (usize, String)> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+
+        if c.is_ascii_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while let Some(c) = self.peek() {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' {
+                    ident.push(c);
+                    self.bump();
+                } else {
+                    break;
+                }
+            }
+            Ok(Some(match &ident[..] {
+                "true" => Token::Int(1),
+                "false" => Token::Int(0),
+                _ => Token::Ident(ident),
+            }))
+        } else if c.is_ascii_digit() || c == '+' || c == '-' || (c == '.' && self.peek_second().is_some_and(|c| c.is_ascii_digit())) {
+            self.number().map(Some)
+        } else if c == '"' || c == '\'' {
+            self.literal().map(Some)
+        } else if "{}[]()<>,;:=*&".contains(c) {
+            self.bump();
+            Ok(Some(Token::Symbol(c)))
+        } else {
+            Err((self.line, format!("unexpected character {:?}", c)))
+        }
+    }
+
+    fn number(&mut self) -> Result<Token, (usize, String)> {
+        let mut text = String::new();
+        if let Some(sign) = self.peek().filter(|&c| c == '+' || c == '-') {
+            text.push(sign);
+            self.bump();
+        }
+
+        if self.peek() == Some('0') && self.peek_second().is_some_and(|c| c == 'x' || c == 'X') {
+            self.bump();
+            self.bump();
+            let mut digits = String::new();
+            while let Some(c) = self.peek().filter(|c| c.is_ascii_hexdigit()) {
+                digits.push(c);
+                self.bump();
+            }
+            let value = i64::from_str_radix(&digits, 16)
+                             .map_err(|_| (self.line, format!("bad hex constant 0x{}", digits)))?;
+            return Ok(Token::Int(if text == "-" { -value } else { value }));
+        }
+
+        let mut double = false;
+        self.digits(&mut text);
+        if self.peek() == Some('.') && self.peek_second().is_some_and(|c| c.is_ascii_digit()) {
+            double = true;
+            text.push('.');
+            self.bump();
+            self.digits(&mut text);
+        }
+        if let Some(e) = self.peek().filter(|&c| c == 'e' || c == 'E') {
+            double = true;
+            text.push(e);
+            self.bump();
+            if let Some(sign) = self.peek().filter(|&c| c == '+' || c == '-') {
+                text.push(sign);
+                self.bump();
+            }
+            self.digits(&mut text);
+        }
+
+        let line = self.line;
+        if double {
+            text.parse().map(Token::Double).map_err(|_| (line, format!("bad double constant {}", text)))
+        } else {
+            text.parse().map(Token::Int).map_err(|_| (line, format!("bad integer constant {}", text)))
+        }
+    }
+
+    fn digits(&mut self, text: &mut String) {
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_digit()) {
+            text.push(c);
+            self.bump();
+        }
+    }
+
+    fn literal(&mut self) -> Result<Token, (usize, String)> {
+        let line = self.line;
+        let mark = self.bump();
+        let mut result = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err((line, String::from("unterminated string literal"))),
+                Some('\\') => match self.bump() {
+                    Some('r') => result.push('\r'),
+                    Some('n') => result.push('\n'),
+                    Some('t') => result.push('\t'),
+                    Some(c @ '"') | Some(c @ '\'') | Some(c @ '\\') => result.push(c),
+                    _ => return Err((self.line, String::from("bad escape character"))),
+                },
+                Some(c) if Some(c) == mark => return Ok(Token::Literal(result)),
+                Some(c) => result.push(c),
+            }
+        }
+    }
+}
@@ -0,0 +1,308 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+//! A Thrift IDL compiler written in Rust, for build scripts. It generates the
+//! same `strukt!`, `enom!` and `service!` invocations as the C++ compiler's
+//! `rs` generator, without needing the C++ compiler to be built.
+//!
+//! ```no_run
+//! // build.rs
+//! extern crate thrift_build;
+//!
+//! fn main() {
+//!     thrift_build::compile("../tutorial.thrift");
+//! }
+//! ```
+//!
+//! This writes the module to Cargo's `OUT_DIR`, along with a `tutorial.rs`
+//! that declares it, for the crate to include at its root:
+//!
+//! ```ignore
+//! include!(concat!(env!("OUT_DIR"), "/tutorial.rs"));
+//! ```
+//!
+//! `Compiler::new().out_dir("src")` writes `src/tutorial/mod.rs` instead,
+//! like `thrift --gen rs --out src`, to be declared with `mod tutorial;`.
+//! The generated code refers to the runtime as `thrift`, so the crate needs
+//! an `extern crate terminal_thrift as thrift;`.
+
+use std::env;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+mod ast;
+mod generator;
+mod lexer;
+mod parser;
+
+use generator::{Generator, Paths, Programs};
+
+/// Compile an IDL file with the default settings, panicking on errors as is
+/// customary in build scripts.
+pub fn compile<P: AsRef<Path>>(idl: P) {
+    if let Err(e) = Compiler::new().compile(idl) {
+        panic!("{}", e);
+    }
+}
+
+/// The generator options of the C++ compiler.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Pass a `RequestContext` to service handler methods.
+    pub context: bool,
+    /// Generate a builder for every struct and exception.
+    pub builders: bool,
+    /// Keep unknown struct fields and write them back out when encoding.
+    pub unknown_fields: bool,
+    /// Decode unknown enum values to an extra `Unknown(i32)` variant.
+    pub unknown_enums: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Compiler {
+    out_dir: Option<PathBuf>,
+    /// Whether to write a file declaring the module, for `include!`.
+    declare: bool,
+    include_dirs: Vec<PathBuf>,
+    options: Options,
+    runtime: Option<String>,
+}
+
+impl Compiler {
+    /// Write to `OUT_DIR`, as build scripts should.
+    pub fn new() -> Self {
+        Compiler {
+            out_dir: None,
+            declare: true,
+            include_dirs: Vec::new(),
+            options: Options::default(),
+            runtime: None,
+        }
+    }
+
+    /// Write modules to declare with `mod` to this directory instead, e.g.
+    /// `src` like `--out src`.
+    pub fn out_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.out_dir = Some(dir.into());
+        self.declare = false;
+        self
+    }
+
+    /// Write to this directory instead of `OUT_DIR`, but still for the crate
+    /// to `include!`, for instance outside of build scripts.
+    pub fn include_out_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.out_dir = Some(dir.into());
+        self.declare = true;
+        self
+    }
+
+    /// Look for included files in this directory too, like `-I`. Includes
+    /// are first looked up next to the file including them.
+    pub fn include_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Generate bare items that can be expanded into any module, as done by
+    /// a procedural macro: the module header is left out, and the runtime and
+    /// standard collections are named by absolute paths, with the runtime at
+    /// `runtime` (e.g. `::terminal_thrift`). Services extending a service
+    /// from an included file need that file's items in scope.
+    pub fn inline<S: Into<String>>(mut self, runtime: S) -> Self {
+        self.runtime = Some(runtime.into());
+        self
+    }
+
+    /// Generate the module for an IDL file and write it to
+    /// `<out_dir>/<name>/mod.rs`. Included files are parsed but not
+    /// generated.
+    ///
+    /// Without an `out_dir`, the module goes to `OUT_DIR`, or the
+    /// `include_out_dir`, and is declared by `<name>.rs` next to it, whose
+    /// path is returned so that the crate can `include!` it. Otherwise the
+    /// path of the module is returned.
+    pub fn compile<P: AsRef<Path>>(&self, idl: P) -> Result<PathBuf, Error> {
+        let mut files = Vec::new();
+        let (name, code) = self.generate_with_files(idl.as_ref(), &mut files)?;
+
+        for file in files {
+            println!("cargo:rerun-if-changed={}", file.display());
+        }
+
+        let out_dir = match self.out_dir {
+            Some(ref dir) => dir.clone(),
+            None => match env::var_os("OUT_DIR") {
+                Some(dir) => PathBuf::from(dir),
+                None => return Err(Error::Io(PathBuf::from("OUT_DIR"), io::Error::new(
+                    io::ErrorKind::NotFound, "not set, use Compiler::out_dir outside of build scripts"))),
+            },
+        };
+
+        let module = generator::underscore(&name);
+        let dir = out_dir.join(&module);
+        let path = dir.join("mod.rs");
+        fs::create_dir_all(&dir).map_err(|e| Error::Io(dir.clone(), e))?;
+        write(&path, &code)?;
+        if !self.declare {
+            return Ok(path);
+        }
+
+        // Generated modules start with inner attributes, which `include!`
+        // does not allow, so the included file only declares the module.
+        let declaration = out_dir.join(format!("{}.rs", module));
+        write(&declaration, &format!("#[path = {:?}]\npub mod {};\n", path.to_string_lossy(), module))?;
+        Ok(declaration)
+    }
+
+    /// Generate the module for an IDL file without writing it.
+    pub fn generate<P: AsRef<Path>>(&self, idl: P) -> Result<String, Error> {
+        self.generate_with_files(idl.as_ref(), &mut Vec::new()).map(|(_, code)| code)
+    }
+
+    /// Generate the module for an IDL file without writing it, along with
+    /// every file that was read to do so.
+    pub fn generate_tracked<P: AsRef<Path>>(&self, idl: P) -> Result<(String, Vec<PathBuf>), Error> {
+        let mut files = Vec::new();
+        let (_, code) = self.generate_with_files(idl.as_ref(), &mut files)?;
+        Ok((code, files))
+    }
+
+    /// Generate a module from IDL source. Includes are looked up in the
+    /// include directories.
+    pub fn generate_str(&self, name: &str, source: &str) -> Result<String, Error> {
+        let mut programs = Programs::new();
+        self.parse(name, source, &PathBuf::from(name), None, &mut programs, &mut Vec::new())?;
+        self.render(name, &programs)
+    }
+
+    fn generate_with_files(&self, idl: &Path, files: &mut Vec<PathBuf>) -> Result<(String, String), Error> {
+        let mut programs = Programs::new();
+        let name = self.load(idl, &mut programs, files)?;
+        let code = self.render(&name, &programs)?;
+        Ok((name, code))
+    }
+
+    fn render(&self, name: &str, programs: &Programs) -> Result<String, Error> {
+        let mut generator = Generator::new(programs, &programs[name], &self.options);
+        if let Some(ref runtime) = self.runtime {
+            generator = generator.inline(Paths::absolute(runtime));
+        }
+        generator.generate()
+            .map_err(|message| Error::Semantic { file: name.to_owned(), message })
+    }
+
+    /// Parse a file and everything it includes, returning the name of its
+    /// program.
+    fn load(&self, path: &Path, programs: &mut Programs, files: &mut Vec<PathBuf>) -> Result<String, Error> {
+        let name = program_name(path);
+        if programs.contains_key(&name) {
+            return Ok(name);
+        }
+
+        let mut source = String::new();
+        File::open(path).and_then(|mut f| f.read_to_string(&mut source))
+             .map_err(|e| Error::Io(path.to_owned(), e))?;
+        files.push(path.to_owned());
+
+        self.parse(&name, &source, path, path.parent(), programs, files)?;
+        Ok(name)
+    }
+
+    fn parse(&self, name: &str, source: &str, path: &Path, dir: Option<&Path>,
+             programs: &mut Programs, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+        let syntax = |(line, message)| Error::Syntax { file: path.to_owned(), line, message };
+
+        let tokens = lexer::Lexer::new(source).tokenize().map_err(&syntax)?;
+        let mut program = parser::Parser::new(tokens).program(name).map_err(&syntax)?;
+
+        // Insert the program before loading its includes so that cycles end.
+        let includes = std::mem::take(&mut program.includes);
+        programs.insert(name.to_owned(), program);
+
+        let mut names = Vec::new();
+        for include in includes {
+            let found = dir.into_iter().chain(self.include_dirs.iter().map(|dir| dir.as_path()))
+                .map(|dir| dir.join(&include))
+                .find(|candidate| candidate.is_file());
+            match found {
+                Some(found) => names.push(self.load(&found, programs, files)?),
+                None => return Err(Error::Semantic {
+                    file: name.to_owned(),
+                    message: format!("could not find include file {}", include),
+                }),
+            }
+        }
+
+        programs.get_mut(name).unwrap().includes = names;
+        Ok(())
+    }
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Compiler::new()
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// An IDL file could not be read, or the generated code written
+    Io(PathBuf, io::Error),
+    /// An IDL file is malformed
+    Syntax { file: PathBuf, line: usize, message: String },
+    /// An IDL file refers to something that does not exist, or a constant
+    /// does not match its type
+    Semantic { file: String, message: String },
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::Io(_, ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref path, ref e) => write!(f, "{}: {}", path.display(), e),
+            Error::Syntax { ref file, line, ref message } => write!(f, "{}:{}: {}", file.display(), line, message),
+            Error::Semantic { ref file, ref message } => write!(f, "{}: {}", file, message),
+        }
+    }
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), Error> {
+    File::create(path).and_then(|mut f| f.write_all(contents.as_bytes()))
+        .map_err(|e| Error::Io(path.to_owned(), e))
+}
+
+fn program_name(path: &Path) -> String {
+    path.file_stem().map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+}
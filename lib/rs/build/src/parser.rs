@@ -0,0 +1,389 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use ast::*;
+use lexer::Token;
+
+pub type ParseResult<T> = Result<T, (usize, String)>;
+
+/// A recursive descent parser following the grammar of the C++ compiler.
+pub struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<(Token, usize)>) -> Self {
+        Parser { tokens, pos: 0 }
+    }
+
+    pub fn program(mut self, name: &str) -> ParseResult<Program> {
+        let mut program = Program { name: name.to_owned(), ..Program::default() };
+
+        while self.pos < self.tokens.len() {
+            let keyword = self.ident()?;
+            match &keyword[..] {
+                "include" => program.includes.push(self.literal()?),
+                "cpp_include" => { self.literal()?; }
+                "namespace" => {
+                    if !self.eat('*') {
+                        self.ident()?;
+                    }
+                    self.ident()?;
+                    self.annotations()?;
+                }
+                "xsd_namespace" => { self.literal()?; }
+                "cpp_namespace" | "php_namespace" | "py_module" | "perl_package" | "ruby_namespace" |
+                "java_package" | "cocoa_prefix" | "csharp_namespace" | "delphi_namespace" => {
+                    self.ident()?;
+                }
+                "smalltalk_category" | "smalltalk_prefix" => { self.ident()?; }
+                "const" => {
+                    let ty = self.field_type()?;
+                    let name = self.ident()?;
+                    self.expect('=')?;
+                    let value = self.const_value()?;
+                    self.separator();
+                    program.consts.push(Const { name, ty, value });
+                }
+                "typedef" => {
+                    let ty = self.field_type()?;
+                    let name = self.ident()?;
+                    self.annotations()?;
+                    self.separator();
+                    program.typedefs.push(Typedef { name, ty });
+                }
+                "enum" => program.enums.push(self.enom()?),
+                "senum" => {
+                    program.senums.push(self.ident()?);
+                    self.expect('{')?;
+                    while !self.eat('}') {
+                        self.literal()?;
+                        self.separator();
+                    }
+                    self.annotations()?;
+                }
+                "struct" => program.objects.push(self.strukt(StructKind::Struct)?),
+                "union" => program.objects.push(self.strukt(StructKind::Union)?),
+                "exception" => program.objects.push(self.strukt(StructKind::Exception)?),
+                "service" => program.services.push(self.service()?),
+                other => return Err(self.error_at(self.pos - 1, format!("unexpected {}", other))),
+            }
+        }
+
+        Ok(program)
+    }
+
+    fn enom(&mut self) -> ParseResult<Enum> {
+        let name = self.ident()?;
+        self.expect('{')?;
+
+        // Values without a number follow the previous one.
+        let mut values = Vec::new();
+        let mut next = 0i64;
+        while !self.eat('}') {
+            let name = self.ident()?;
+            let value = if self.eat('=') { self.int()? } else { next };
+            if value < i32::MIN as i64 || value > i32::MAX as i64 {
+                return Err(self.error(format!("enum value {} out of range", value)));
+            }
+            next = value + 1;
+            self.annotations()?;
+            self.separator();
+            values.push(EnumValue { name, value: value as i32 });
+        }
+
+        self.annotations()?;
+        Ok(Enum { name, values })
+    }
+
+    fn strukt(&mut self, kind: StructKind) -> ParseResult<Struct> {
+        let name = self.ident()?;
+        self.eat_ident("xsd_all");
+        self.expect('{')?;
+        let fields = self.fields('}')?;
+        self.annotations()?;
+        Ok(Struct { name, kind, fields })
+    }
+
+    fn service(&mut self) -> ParseResult<Service> {
+        let name = self.ident()?;
+        let extends = if self.eat_ident("extends") { Some(self.ident()?) } else { None };
+        self.expect('{')?;
+
+        let mut functions = Vec::new();
+        while !self.eat('}') {
+            let oneway = self.eat_ident("oneway") || self.eat_ident("async");
+            let returns = if self.eat_ident("void") { Type::Void } else { self.field_type()? };
+            let name = self.ident()?;
+            self.expect('(')?;
+            let args = self.fields(')')?;
+            let throws = if self.eat_ident("throws") {
+                self.expect('(')?;
+                self.fields(')')?
+            } else {
+                Vec::new()
+            };
+            let idempotent = self.annotations()?.iter().any(|key| key == "idempotent");
+            self.separator();
+            functions.push(Function { name, returns, args, throws, oneway, idempotent });
+        }
+
+        self.annotations()?;
+        Ok(Service { name, extends, functions })
+    }
+
+    /// Parse fields up to the closing delimiter. Fields without an id get
+    /// negative ones, counting down from -1.
+    fn fields(&mut self, close: char) -> ParseResult<Vec<Field>> {
+        let mut fields: Vec<Field> = Vec::new();
+        let mut auto_id = -1;
+
+        while !self.eat(close) {
+            let start = self.pos;
+            let id = match (self.peek().cloned(), self.peek_at(1).cloned()) {
+                (Some(Token::Int(id)), Some(Token::Symbol(':'))) => {
+                    self.pos += 2;
+                    if id > 0 {
+                        if id > i16::MAX as i64 {
+                            return Err(self.error(format!("field id {} out of range", id)));
+                        }
+                        id as i32
+                    } else {
+                        auto_id -= 1;
+                        auto_id + 1
+                    }
+                }
+                _ => {
+                    auto_id -= 1;
+                    auto_id + 1
+                }
+            };
+
+            let requiredness = if self.eat_ident("required") {
+                Requiredness::Required
+            } else if self.eat_ident("optional") {
+                Requiredness::Optional
+            } else {
+                Requiredness::Default
+            };
+
+            let ty = self.field_type()?;
+            self.eat('&');
+            let name = self.ident()?;
+            let default = if self.eat('=') { Some(self.const_value()?) } else { None };
+
+            self.eat_ident("xsd_optional");
+            self.eat_ident("xsd_nillable");
+            if self.eat_ident("xsd_attrs") {
+                self.expect('{')?;
+                self.fields('}')?;
+            }
+            self.annotations()?;
+            self.separator();
+
+            if fields.iter().any(|field| field.id == id || field.name == name) {
+                return Err(self.error_at(start, format!("{}: {} - field identifier/name has already been used",
+                                                        id, name)));
+            }
+            fields.push(Field { id, name, ty, requiredness, default });
+        }
+
+        Ok(fields)
+    }
+
+    fn field_type(&mut self) -> ParseResult<Type> {
+        let name = self.ident()?;
+        let ty = match &name[..] {
+            "bool" => Type::Bool,
+            "byte" | "i8" => Type::Byte,
+            "i16" => Type::I16,
+            "i32" => Type::I32,
+            "i64" => Type::I64,
+            "double" => Type::Double,
+            "string" | "slist" => Type::String,
+            "binary" => Type::Binary,
+            "map" => {
+                self.cpp_type()?;
+                self.expect('<')?;
+                let key = self.field_type()?;
+                self.expect(',')?;
+                let value = self.field_type()?;
+                self.expect('>')?;
+                Type::Map(Box::new(key), Box::new(value))
+            }
+            "set" => {
+                self.cpp_type()?;
+                self.expect('<')?;
+                let elem = self.field_type()?;
+                self.expect('>')?;
+                Type::Set(Box::new(elem))
+            }
+            "list" => {
+                self.expect('<')?;
+                let elem = self.field_type()?;
+                self.expect('>')?;
+                self.cpp_type()?;
+                Type::List(Box::new(elem))
+            }
+            _ => return Ok(Type::Named(name)),
+        };
+
+        self.annotations()?;
+        Ok(ty)
+    }
+
+    fn cpp_type(&mut self) -> ParseResult<()> {
+        if self.eat_ident("cpp_type") {
+            self.literal()?;
+        }
+        Ok(())
+    }
+
+    fn const_value(&mut self) -> ParseResult<ConstValue> {
+        match self.next() {
+            Some(Token::Int(value)) => Ok(ConstValue::Int(value)),
+            Some(Token::Double(value)) => Ok(ConstValue::Double(value)),
+            Some(Token::Literal(value)) => Ok(ConstValue::String(value)),
+            Some(Token::Ident(name)) => Ok(ConstValue::Ident(name)),
+            Some(Token::Symbol('[')) => {
+                let mut list = Vec::new();
+                while !self.eat(']') {
+                    list.push(self.const_value()?);
+                    self.separator();
+                }
+                Ok(ConstValue::List(list))
+            }
+            Some(Token::Symbol('{')) => {
+                let mut map = Vec::new();
+                while !self.eat('}') {
+                    let key = self.const_value()?;
+                    self.expect(':')?;
+                    let value = self.const_value()?;
+                    self.separator();
+                    map.push((key, value));
+                }
+                Ok(ConstValue::Map(map))
+            }
+            other => Err(self.unexpected(other, "a constant value")),
+        }
+    }
+
+    /// Parse `(key = "value", ...)` annotations, returning their keys. Only
+    /// `idempotent` on functions changes the generated code.
+    fn annotations(&mut self) -> ParseResult<Vec<String>> {
+        let mut keys = Vec::new();
+        if self.eat('(') {
+            while !self.eat(')') {
+                keys.push(self.ident()?);
+                if self.eat('=') {
+                    self.literal()?;
+                }
+                self.separator();
+            }
+        }
+        Ok(keys)
+    }
+
+    fn separator(&mut self) {
+        if !self.eat(',') {
+            self.eat(';');
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_ident(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(ident)) if ident == keyword => {}
+            _ => return false,
+        }
+        self.pos += 1;
+        true
+    }
+
+    fn expect(&mut self, symbol: char) -> ParseResult<()> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            let next = self.next();
+            Err(self.unexpected(next, &format!("'{}'", symbol)))
+        }
+    }
+
+    fn ident(&mut self) -> ParseResult<String> {
+        match self.next() {
+            Some(Token::Ident(ident)) => Ok(ident),
+            other => Err(self.unexpected(other, "an identifier")),
+        }
+    }
+
+    fn int(&mut self) -> ParseResult<i64> {
+        match self.next() {
+            Some(Token::Int(value)) => Ok(value),
+            other => Err(self.unexpected(other, "an integer")),
+        }
+    }
+
+    fn literal(&mut self) -> ParseResult<String> {
+        match self.next() {
+            Some(Token::Literal(literal)) => Ok(literal),
+            other => Err(self.unexpected(other, "a string literal")),
+        }
+    }
+
+    fn unexpected(&self, found: Option<Token>, expected: &str) -> (usize, String) {
+        match found {
+            Some(token) => self.error_at(self.pos - 1, format!("expected {}, found {:?}", expected, token)),
+            None => self.error(format!("expected {}, found end of file", expected)),
+        }
+    }
+
+    fn error(&self, message: String) -> (usize, String) {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, pos: usize, message: String) -> (usize, String) {
+        let line = self.tokens.get(pos).or(self.tokens.last()).map_or(1, |&(_, line)| line);
+        (line, message)
+    }
+}
@@ -0,0 +1,146 @@
+extern crate thrift_build;
+
+use std::error::Error as StdError;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use thrift_build::{Compiler, Error, Options};
+
+fn repo(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../..").join(path)
+}
+
+fn read(path: &Path) -> String {
+    let mut contents = String::new();
+    File::open(path).unwrap().read_to_string(&mut contents).unwrap();
+    contents
+}
+
+/// The modules the C++ compiler generates for the tutorial.
+fn expected(name: &str) -> String {
+    read(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/expected").join(name))
+}
+
+#[test]
+fn test_matches_cpp_tutorial() {
+    let compiler = Compiler::new();
+    assert_eq!(compiler.generate(repo("tutorial/shared.thrift")).unwrap(), expected("shared.rs"));
+    assert_eq!(compiler.generate(repo("tutorial/tutorial.thrift")).unwrap(), expected("tutorial.rs"));
+}
+
+#[test]
+fn test_compile_writes_module() {
+    let out = std::env::temp_dir().join(format!("thrift_build_test_{}", std::process::id()));
+    let path = Compiler::new().out_dir(&out).compile(repo("test/ThriftTest.thrift")).unwrap();
+    assert_eq!(path, out.join("thrift_test").join("mod.rs"));
+
+    let code = read(&path);
+    assert!(code.contains("service! {\n  trait_name = ThriftTest,"));
+    assert!(code.contains("exception! {\n  name = Xception,"));
+    assert!(code.contains("  extends = [],\n"));
+    std::fs::remove_dir_all(&out).unwrap();
+}
+
+#[test]
+fn test_compile_to_out_dir() {
+    let out = std::env::temp_dir().join(format!("thrift_build_out_dir_{}", std::process::id()));
+    let path = Compiler::new().include_out_dir(&out).compile(repo("tutorial/shared.thrift")).unwrap();
+    assert_eq!(path, out.join("shared.rs"));
+
+    let module = out.join("shared").join("mod.rs");
+    assert_eq!(read(&path), format!("#[path = {:?}]\npub mod shared;\n", module.to_string_lossy()));
+    assert_eq!(read(&module), expected("shared.rs"));
+    std::fs::remove_dir_all(&out).unwrap();
+}
+
+#[test]
+fn test_generate_str() {
+    let options = Options { builders: true, context: true, ..Options::default() };
+    let code = Compiler::new().options(options).generate_str("inline", r#"
+        namespace rs inline  // comments are skipped
+        typedef i64 Timestamp (annotated = "yes")
+
+        enum Level { low, HIGH = 5, Higher }
+
+        /** Doc comments too. */
+        struct Event {
+            1: required Timestamp at,
+            2: optional string note = "none";
+            3: Level level = Level.HIGH
+            4: list<map<string, double>> samples,
+            5: bool type,
+        }
+
+        const double PI = 3.141592653589793
+        const double TWO = 2
+        const Level DEFAULT_LEVEL = Level.HIGH
+        const list<Level> LEVELS = [low, DEFAULT_LEVEL]
+        const Event FIRST = {"at": 1, "note": "first\n"}
+
+        service Log {
+            oneway void record(1: Event event) (annotated = "yes"),
+            Event last() throws (1: Event missingEvent) (idempotent)
+            list<Event> recent(1: i32 count = 10, 2: string tag = "all")
+        }
+    "#).unwrap();
+
+    assert!(code.contains("pub type Timestamp = i64;\n"));
+    assert!(code.contains("    Low = 0 => \"low\",\n    HIGH = 5,\n    Higher = 6,\n"));
+    assert!(code.contains("  builder = EventBuilder,\n"));
+    assert!(code.contains("    #[required] at: i64 => 1,\n    note: Option<String> => 2,\n    level: Level = Level::HIGH => 3,\n"));
+    assert!(code.contains("    samples: Vec<BTreeMap<String, OrderedFloat<f64>>> => 4,\n    type_: bool => 5,\n"));
+    assert!(code.contains("  const PI: OrderedFloat<f64> = OrderedFloat(3.1415926535897931);\n"));
+    assert!(code.contains("  const TWO: OrderedFloat<f64> = OrderedFloat(2.0);\n"));
+    assert!(code.contains("  const DEFAULT_LEVEL: Level = Level::HIGH;\n"));
+    assert!(code.contains("  static LEVELS: Vec<Level> = vec![Level::Low, Level::HIGH];\n"));
+    assert!(code.contains("  static FIRST: Event = {\n    let mut s = Event::default();\n    s.at = 1;\n    \
+                           s.note = Some(\"first\\n\".to_owned());\n    s\n  };\n"));
+    assert!(code.contains("    LogRecordArgs -> LogRecordResult = a.record(\n      event: Event => 1,\n    \
+                           ) -> () => LogRecordError = [\n    ] (()) oneway = true, idempotent = false, context = true,\n"));
+    assert!(code.contains("      MissingEvent(missing_event: Event => 1),\n    \
+                           ] (Result<Event, LogLastError>) oneway = false, idempotent = true, context = true,\n"));
+    assert!(code.contains("      count: i32 = 10 => 1,\n      tag: String = \"all\".to_owned() => 2,\n"));
+}
+
+#[test]
+fn test_generate_inline() {
+    let code = Compiler::new().inline("::terminal_thrift").generate_str("inline", r#"
+        struct Sample { 1: map<string, set<double>> values }
+        const map<string, double> ZERO = {"zero": 0}
+    "#).unwrap();
+
+    assert!(code.starts_with("strukt! {\n"));
+    assert!(!code.contains("use "));
+    assert!(code.contains("    values: ::std::collections::BTreeMap<String, \
+                           ::std::collections::BTreeSet<::terminal_thrift::rt::OrderedFloat<f64>>> => 1,\n"));
+    assert!(code.contains("    let mut m = ::std::collections::BTreeMap::new();\n    \
+                           m.insert(\"zero\".to_owned(), ::terminal_thrift::rt::OrderedFloat(0.0));\n"));
+}
+
+#[test]
+fn test_extends_service_in_same_file() {
+    let code = Compiler::new().generate_str("family", r#"
+        service Parent { void ping() }
+        service Child extends Parent { void pong() }
+    "#).unwrap();
+
+    assert!(!code.contains("use family::*;"));
+    assert!(code.contains("  extends = [Parent],\n"));
+}
+
+#[test]
+fn test_errors() {
+    match Compiler::new().generate_str("broken", "struct A {\n 1: i32 a\n 1: i32 b\n}") {
+        Err(Error::Syntax { line: 3, .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    match Compiler::new().generate_str("undefined", "struct A { 1: Missing a }") {
+        Err(Error::Semantic { ref message, .. }) => assert_eq!(message, "type Missing has not been defined"),
+        other => panic!("unexpected result {:?}", other),
+    }
+    match Compiler::new().generate(repo("tutorial/missing.thrift")) {
+        Err(ref e @ Error::Io(..)) => assert!(e.source().is_some()),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
@@ -4,7 +4,7 @@
 // DO NOT EDIT UNLESS YOU ARE SURE YOU KNOW WHAT YOU ARE DOING
 ///////////////////////////////////////////////////////////////
 
-#![allow(unused_mut, dead_code, non_snake_case, unused_imports)]
+#![allow(unused_mut, dead_code, non_snake_case, non_upper_case_globals, unused_imports)]
 use ::thrift::rt::OrderedFloat;
 use std::collections::{BTreeMap, BTreeSet};
 
@@ -21,14 +21,16 @@ service! {
   trait_name = SharedService,
   processor_name = SharedServiceProcessor,
   client_name = SharedServiceClient,
+  async_client_name = SharedServiceAsyncClient,
   service_methods = [
     SharedServiceGetStructArgs -> SharedServiceGetStructResult = a.getStruct(
       key: i32 => 1,
     ) -> SharedStruct => SharedServiceGetStructError = [
-    ] (SharedStruct),
+    ] (SharedStruct) oneway = false, idempotent = false, context = false,
   ],
   parent_methods = [
   ],
+  extends = [],
   bounds = [A: SharedService, ],
   fields = [a: A, ]
 }
@@ -4,7 +4,7 @@
 // DO NOT EDIT UNLESS YOU ARE SURE YOU KNOW WHAT YOU ARE DOING
 ///////////////////////////////////////////////////////////////
 
-#![allow(unused_mut, dead_code, non_snake_case, unused_imports)]
+#![allow(unused_mut, dead_code, non_snake_case, non_upper_case_globals, unused_imports)]
 use ::thrift::rt::OrderedFloat;
 use std::collections::{BTreeMap, BTreeSet};
 
@@ -26,14 +26,14 @@ pub type MyInteger = i32;
 strukt! {
   name = Work,
   fields = {
-    num1: i32 => 1,
+    num1: i32 = 0 => 1,
     num2: i32 => 2,
     op: Operation => 3,
     comment: Option<String> => 4,
   }
 }
 
-strukt! {
+exception! {
   name = InvalidOperation,
   fields = {
     what_op: i32 => 1,
@@ -41,35 +41,47 @@ strukt! {
   }
 }
 
+consts! {
+  const INT32CONSTANT: i32 = 9853;
+  static MAPCONSTANT: BTreeMap<String, String> = {
+    let mut m = BTreeMap::new();
+    m.insert("hello".to_owned(), "world".to_owned());
+    m.insert("goodnight".to_owned(), "moon".to_owned());
+    m
+  };
+}
+
 service! {
   trait_name = Calculator,
   processor_name = CalculatorProcessor,
   client_name = CalculatorClient,
+  async_client_name = CalculatorAsyncClient,
   service_methods = [
     CalculatorPingArgs -> CalculatorPingResult = a.ping(
     ) -> () => CalculatorPingError = [
-    ] (()),
+    ] (()) oneway = false, idempotent = false, context = false,
     CalculatorAddArgs -> CalculatorAddResult = a.add(
       num1: i32 => 1,
       num2: i32 => 2,
     ) -> i32 => CalculatorAddError = [
-    ] (i32),
+    ] (i32) oneway = false, idempotent = false, context = false,
     CalculatorCalculateArgs -> CalculatorCalculateResult = a.calculate(
       logid: i32 => 1,
       w: Work => 2,
     ) -> i32 => CalculatorCalculateError = [
       Ouch(ouch: InvalidOperation => 1),
-    ] (Result<i32, CalculatorCalculateError>),
+    ] (Result<i32, CalculatorCalculateError>) oneway = false, idempotent = false, context = false,
     CalculatorZipArgs -> CalculatorZipResult = a.zip(
     ) -> () => CalculatorZipError = [
-    ] (()),
+    ] (()) oneway = true, idempotent = false, context = false,
   ],
   parent_methods = [
     SharedServiceGetStructArgs -> SharedServiceGetStructResult = b.getStruct(
       key: i32 => 1,
     ) -> SharedStruct => SharedServiceGetStructError = [
-    ] (SharedStruct),
+    ] (SharedStruct) oneway = false, idempotent = false, context = false,
   ],
+  extends = [SharedService],
   bounds = [A: Calculator, B: SharedService, ],
   fields = [a: A, b: B, ]
 }
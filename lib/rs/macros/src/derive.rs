@@ -0,0 +1,396 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use proc_macro2::TokenStream;
+use syn::ext::IdentExt;
+use syn::{Attribute, Data, DeriveInput, Error, Fields, Ident, LitInt, LitStr, Path, Result, Type};
+
+struct Field<'a> {
+    ident: &'a Ident,
+    name: String,
+    ty: &'a Type,
+    id: i16,
+    required: bool,
+}
+
+pub fn thrift_struct(input: &DeriveInput) -> Result<TokenStream> {
+    check_not_generic(input)?;
+    let krate = runtime(input)?;
+
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref named) => named.named.iter().map(field).collect::<Result<Vec<_>>>()?,
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(_) => {
+                return Err(Error::new_spanned(&input.ident, "ThriftStruct needs named fields"))
+            }
+        },
+        _ => return Err(Error::new_spanned(&input.ident, "ThriftStruct can only be derived for structs")),
+    };
+
+    for (i, one) in fields.iter().enumerate() {
+        if fields[..i].iter().any(|other| other.id == one.id) {
+            return Err(Error::new_spanned(one.ident, format!("duplicate field id {}", one.id)));
+        }
+    }
+
+    let name = &input.ident;
+    let strukt = name.unraw().to_string();
+
+    let encode_fields = fields.iter().map(|field| {
+        let Field { ident, ref name, ty, id, .. } = *field;
+        quote! {
+            if #krate::protocol::Encode::should_encode(&self.#ident) {
+                #krate::Protocol::write_field_begin(
+                    protocol, transport, #name, #krate::protocol::helpers::typ::<#ty>(), #id)?;
+                #krate::protocol::Encode::encode(&self.#ident, protocol, transport)?;
+                #krate::Protocol::write_field_end(protocol, transport)?;
+            }
+        }
+    });
+
+    let seen = |field: &Field| format_ident!("seen_{}", field.ident.unraw());
+    let required: Vec<_> = fields.iter().filter(|field| field.required).collect();
+    let declare_seen = required.iter().map(|field| {
+        let seen = seen(field);
+        quote! { let mut #seen = false; }
+    });
+    let check_seen = required.iter().map(|field| {
+        let seen = seen(field);
+        let name = &field.name;
+        quote! {
+            if !#seen {
+                return Err(#krate::Error::from(
+                    #krate::protocol::Error::MissingRequiredField { strukt: #strukt, field: #name }));
+            }
+        }
+    });
+
+    let decode_fields = fields.iter().map(|field| {
+        let Field { ident, ty, id, .. } = *field;
+        let mark_seen = if field.required {
+            let seen = seen(field);
+            quote! { #seen = true; }
+        } else {
+            quote! {}
+        };
+        quote! {
+            else if (typ, id) == (#krate::protocol::helpers::typ::<#ty>(), #id) {
+                #krate::protocol::Decode::decode(&mut self.#ident, protocol, transport)?;
+                #mark_seen
+            }
+        }
+    });
+
+    let check_types = fields.iter().map(|field| {
+        let Field { ref name, ty, id, .. } = *field;
+        quote! {
+            if id == #id {
+                return Err(#krate::Error::from(#krate::protocol::Error::FieldTypeMismatch {
+                    strukt: #strukt,
+                    field: #name,
+                    expected: #krate::protocol::helpers::typ::<#ty>(),
+                    actual: typ,
+                }));
+            }
+        }
+    });
+
+    let descriptors = fields.iter().map(|field| {
+        let Field { ref name, ty, id, .. } = *field;
+        let requiredness = if field.required {
+            quote! { #krate::reflect::Requiredness::Required }
+        } else {
+            quote! { <#ty as #krate::reflect::Reflect>::REQUIREDNESS }
+        };
+        quote! {
+            #krate::reflect::FieldDescriptor {
+                id: #id,
+                name: #name,
+                typ: <#ty as #krate::reflect::Reflect>::TYPE.typ(),
+                requiredness: #requiredness,
+                ty: <#ty as #krate::reflect::Reflect>::TYPE,
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #krate::protocol::ThriftTyped for #name {
+            fn typ(&self) -> #krate::protocol::Type { #krate::protocol::Type::Struct }
+        }
+
+        impl #krate::protocol::Encode for #name {
+            fn encode<P, T>(&self, protocol: &mut P, transport: &mut T) -> #krate::Result<()>
+            where P: #krate::Protocol, T: #krate::Transport {
+                #krate::Protocol::write_struct_begin(protocol, transport, #strukt)?;
+                #(#encode_fields)*
+                #krate::Protocol::write_field_stop(protocol, transport)?;
+                #krate::Protocol::write_struct_end(protocol, transport)?;
+                Ok(())
+            }
+        }
+
+        impl #krate::protocol::Decode for #name {
+            fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> #krate::Result<()>
+            where P: #krate::Protocol, T: #krate::Transport {
+                #(#declare_seen)*
+
+                #krate::Protocol::read_struct_begin(protocol, transport)?;
+
+                loop {
+                    let (_, typ, id) = #krate::Protocol::read_field_begin(protocol, transport)?;
+
+                    if typ == #krate::protocol::Type::Stop {
+                        break;
+                    } #(#decode_fields)* else {
+                        if #krate::Protocol::strict_fields(protocol) {
+                            #(#check_types)*
+                        }
+
+                        #krate::Protocol::skip(protocol, transport, typ)?;
+                    }
+
+                    #krate::Protocol::read_field_end(protocol, transport)?;
+                }
+
+                #krate::Protocol::read_struct_end(protocol, transport)?;
+
+                #(#check_seen)*
+
+                Ok(())
+            }
+        }
+
+        impl #krate::reflect::ThriftStruct for #name {
+            fn descriptor() -> &'static #krate::reflect::StructDescriptor {
+                static DESCRIPTOR: #krate::reflect::StructDescriptor = #krate::reflect::StructDescriptor {
+                    name: #strukt,
+                    fields: &[#(#descriptors,)*],
+                };
+
+                &DESCRIPTOR
+            }
+        }
+
+        impl #krate::reflect::Reflect for #name {
+            const TYPE: #krate::reflect::TypeDescriptor = #krate::reflect::TypeDescriptor::Struct(
+                <#name as #krate::reflect::ThriftStruct>::descriptor);
+        }
+    })
+}
+
+pub fn thrift_enum(input: &DeriveInput) -> Result<TokenStream> {
+    check_not_generic(input)?;
+    let krate = runtime(input)?;
+
+    let variants = match input.data {
+        Data::Enum(ref data) => &data.variants,
+        _ => return Err(Error::new_spanned(&input.ident, "ThriftEnum can only be derived for enums")),
+    };
+
+    for variant in variants {
+        if let Fields::Unit = variant.fields {} else {
+            return Err(Error::new_spanned(&variant.ident, "ThriftEnum variants cannot have fields"));
+        }
+    }
+
+    let name = &input.ident;
+    let enom = name.unraw().to_string();
+    let idents: Vec<_> = variants.iter().map(|variant| &variant.ident).collect();
+    let names: Vec<_> = idents.iter().map(|ident| ident.unraw().to_string()).collect();
+
+    // Casting the variants leaves the discriminants, implicit or not, to the
+    // compiler.
+    Ok(quote! {
+        impl #name {
+            /// Every value the enum declares, in declaration order.
+            pub const ALL: &'static [#name] = &[#(#name::#idents),*];
+
+            pub fn values() -> &'static [#name] {
+                #name::ALL
+            }
+
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    #(#name::#idents => #names,)*
+                }
+            }
+
+            pub fn as_i32(&self) -> i32 {
+                match *self {
+                    #(#name::#idents => #name::#idents as i32,)*
+                }
+            }
+        }
+
+        impl ::std::convert::From<#name> for i32 {
+            fn from(value: #name) -> i32 {
+                value.as_i32()
+            }
+        }
+
+        impl ::std::convert::TryFrom<i32> for #name {
+            type Error = #krate::EnumError;
+
+            fn try_from(num: i32) -> ::std::result::Result<Self, #krate::EnumError> {
+                <#name as #krate::protocol::FromNum>::from_num(num)
+                    .ok_or(#krate::EnumError::UnknownValue { enom: #enom, value: num })
+            }
+        }
+
+        impl ::std::fmt::Display for #name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+
+        impl ::std::str::FromStr for #name {
+            type Err = #krate::EnumError;
+
+            fn from_str(name: &str) -> ::std::result::Result<Self, #krate::EnumError> {
+                #(if name == #names {
+                    return Ok(#name::#idents);
+                })*
+
+                Err(#krate::EnumError::UnknownName { enom: #enom, name: name.to_owned() })
+            }
+        }
+
+        impl #krate::protocol::FromNum for #name {
+            fn from_num(num: i32) -> Option<Self> {
+                #(if num == #name::#idents as i32 {
+                    return Some(#name::#idents);
+                })*
+
+                None
+            }
+        }
+
+        impl #krate::protocol::ThriftTyped for #name {
+            fn typ(&self) -> #krate::protocol::Type { #krate::protocol::Type::I32 }
+        }
+
+        impl #krate::protocol::Encode for #name {
+            fn encode<P, T>(&self, protocol: &mut P, transport: &mut T) -> #krate::Result<()>
+            where P: #krate::Protocol, T: #krate::Transport {
+                #krate::Protocol::write_i32(protocol, transport, self.as_i32())
+            }
+        }
+
+        impl #krate::protocol::Decode for #name {
+            fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> #krate::Result<()>
+            where P: #krate::Protocol, T: #krate::Transport {
+                *self = #krate::protocol::helpers::read_enum(protocol, transport)?;
+                Ok(())
+            }
+        }
+
+        impl #krate::reflect::ThriftEnum for #name {
+            fn descriptor() -> &'static #krate::reflect::EnumDescriptor {
+                static DESCRIPTOR: #krate::reflect::EnumDescriptor = #krate::reflect::EnumDescriptor {
+                    name: #enom,
+                    values: &[#((#names, #name::#idents as i32),)*],
+                };
+
+                &DESCRIPTOR
+            }
+        }
+
+        impl #krate::reflect::Reflect for #name {
+            const TYPE: #krate::reflect::TypeDescriptor = #krate::reflect::TypeDescriptor::Enum(
+                <#name as #krate::reflect::ThriftEnum>::descriptor);
+        }
+    })
+}
+
+/// The path to the runtime, `::terminal_thrift` unless the type says
+/// otherwise with `#[thrift(crate = "...")]`.
+fn runtime(input: &DeriveInput) -> Result<Path> {
+    let mut krate = None;
+    for attr in thrift_attrs(&input.attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                let lit: LitStr = meta.value()?.parse()?;
+                krate = Some(lit.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `crate = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(krate.unwrap_or_else(|| syn::parse_quote!(::terminal_thrift)))
+}
+
+// Descriptors are statics, which cannot depend on type parameters.
+fn check_not_generic(input: &DeriveInput) -> Result<()> {
+    if input.generics.params.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new_spanned(&input.generics, "Thrift types cannot be generic"))
+    }
+}
+
+fn field(field: &syn::Field) -> Result<Field<'_>> {
+    let ident = field.ident.as_ref().expect("named field");
+    let mut id = None;
+    let mut required = false;
+
+    for attr in thrift_attrs(&field.attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                let lit: LitInt = meta.value()?.parse()?;
+                id = Some(lit.base10_parse::<i16>()?);
+                Ok(())
+            } else if meta.path.is_ident("required") {
+                required = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `id = N` or `required`"))
+            }
+        })?;
+    }
+
+    let id = match id {
+        Some(id) => id,
+        None => return Err(Error::new_spanned(ident, "missing #[thrift(id = N)]")),
+    };
+
+    if required && is_option(&field.ty) {
+        return Err(Error::new_spanned(ident, "optional fields cannot be required"));
+    }
+
+    Ok(Field {
+        ident,
+        name: ident.unraw().to_string(),
+        ty: &field.ty,
+        id,
+        required,
+    })
+}
+
+fn thrift_attrs(attrs: &[Attribute]) -> Vec<&Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("thrift")).collect()
+}
+
+fn is_option(ty: &Type) -> bool {
+    match *ty {
+        Type::Path(ref path) => path.path.segments.last().is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
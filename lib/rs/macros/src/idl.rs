@@ -0,0 +1,113 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+//! `thrift_idl!` and `include_thrift!`, expanding IDL into the items the
+//! `rs` generator would write to a module.
+
+use std::env;
+use std::path::PathBuf;
+
+use proc_macro2::TokenStream;
+use syn::{Ident, LitStr, Token};
+use syn::parse::{Parse, ParseStream};
+
+use thrift_build::{Compiler, Options};
+
+/// Where the generated code finds the runtime, unless `crate = "..."` says
+/// otherwise.
+const RUNTIME: &str = "::terminal_thrift";
+
+/// Generator options as idents, followed by a string literal.
+pub struct Input {
+    options: Options,
+    runtime: String,
+    literal: LitStr,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut options = Options::default();
+        let mut runtime = String::from(RUNTIME);
+        while input.peek(Token![crate]) || input.peek(Ident) {
+            if input.peek(Token![crate]) {
+                input.parse::<Token![crate]>()?;
+                input.parse::<Token![=]>()?;
+                let path: LitStr = input.parse()?;
+                path.parse::<syn::Path>()?;
+                runtime = path.value();
+                input.parse::<Token![,]>()?;
+                continue;
+            }
+
+            let option: Ident = input.parse()?;
+            match &*option.to_string() {
+                "context" => options.context = true,
+                "builders" => options.builders = true,
+                "unknown_fields" => options.unknown_fields = true,
+                "unknown_enums" => options.unknown_enums = true,
+                other => return Err(syn::Error::new(option.span(), format!(
+                    "unknown option `{}`, expected one of `context`, `builders`, \
+                     `unknown_fields`, `unknown_enums` or `crate = \"...\"`", other))),
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        let literal = input.parse()?;
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+        Ok(Input { options, runtime, literal })
+    }
+}
+
+pub fn thrift_idl(input: &Input) -> syn::Result<TokenStream> {
+    let code = compiler(input).generate_str("thrift_idl", &input.literal.value())
+        .map_err(|e| syn::Error::new(input.literal.span(), e))?;
+    parse(&code, input)
+}
+
+pub fn include_thrift(input: &Input) -> syn::Result<TokenStream> {
+    let path = manifest_dir().join(input.literal.value());
+    let (code, files) = compiler(input).generate_tracked(&path)
+        .map_err(|e| syn::Error::new(input.literal.span(), e))?;
+    let items = parse(&code, input)?;
+
+    // Make the compiler rebuild the crate when one of the files changes.
+    let files = files.iter().map(|file| file.to_string_lossy().into_owned());
+    Ok(quote! {
+        #(const _: &'static str = include_str!(#files);)*
+        #items
+    })
+}
+
+/// Includes are looked up relative to the crate being built.
+fn compiler(input: &Input) -> Compiler {
+    Compiler::new()
+        .include_dir(manifest_dir())
+        .options(input.options.clone())
+        .inline(&input.runtime)
+}
+
+fn manifest_dir() -> PathBuf {
+    env::var_os("CARGO_MANIFEST_DIR").map_or_else(PathBuf::new, PathBuf::from)
+}
+
+fn parse(code: &str, input: &Input) -> syn::Result<TokenStream> {
+    code.parse().map_err(|e| syn::Error::new(input.literal.span(), format!("invalid generated code: {}", e)))
+}
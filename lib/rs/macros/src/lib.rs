@@ -0,0 +1,90 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+//! `#[derive(ThriftStruct)]` and `#[derive(ThriftEnum)]`, for hand-written
+//! types that encode exactly like the ones declared with `strukt!` and
+//! `enom!`.
+//!
+//! Every field of a struct needs an id, given with `#[thrift(id = N)]`.
+//! Fields of type `Option<T>` are only written when set; all others are
+//! always written. Fields marked `#[thrift(required)]` must be present when
+//! decoding, and are the only ones described as `Requiredness::Required`.
+//! Both kinds of types must also implement `Default`. Derived
+//! enums get the same `ALL`, `name`, `Display`, `FromStr` and `TryFrom<i32>`
+//! helpers as `enom!` ones.
+//!
+//! The derived code refers to the runtime as `::terminal_thrift`. Crates that
+//! rename it, e.g. with `extern crate terminal_thrift as thrift;`, say so with
+//! `#[thrift(crate = "::thrift")]` on the type.
+//!
+//! `thrift_idl!` expands IDL source into the `strukt!`, `enom!`, `service!`
+//! and `consts!` invocations that the `rs` generator would write to a module,
+//! so that the items are declared where the macro is invoked:
+//!
+//! ```ignore
+//! thrift_idl! { r#"struct Foo { 1: i32 a }"# }
+//! ```
+//!
+//! `include_thrift!("idl/foo.thrift")` does the same for a file, relative to
+//! the crate's manifest directory. Either can be preceded by generator
+//! options, e.g. `thrift_idl!(builders, unknown_fields, "...")`. Includes are
+//! looked up relative to the manifest directory too, but only parsed: their
+//! items must already be in scope.
+//!
+//! The generated code uses the runtime's macros, so it needs a
+//! `#[macro_use] extern crate terminal_thrift;`. It refers to the runtime as
+//! `::terminal_thrift` unless given another path, e.g.
+//! `thrift_idl!(crate = "::thrift", "...")` for crates that follow the
+//! `thrift_build` convention of `extern crate terminal_thrift as thrift;`.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+extern crate thrift_build;
+
+use proc_macro::TokenStream;
+
+mod derive;
+mod idl;
+
+#[proc_macro_derive(ThriftStruct, attributes(thrift))]
+pub fn derive_thrift_struct(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    derive::thrift_struct(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[proc_macro_derive(ThriftEnum, attributes(thrift))]
+pub fn derive_thrift_enum(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    derive::thrift_enum(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[proc_macro]
+pub fn thrift_idl(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as idl::Input);
+    idl::thrift_idl(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[proc_macro]
+pub fn include_thrift(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as idl::Input);
+    idl::include_thrift(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
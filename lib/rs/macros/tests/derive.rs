@@ -0,0 +1,210 @@
+#[macro_use]
+extern crate terminal_thrift;
+#[macro_use]
+extern crate terminal_thrift_macros;
+extern crate terminal_thrift as thrift;
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use terminal_thrift::{EnumError, Error, ThriftEnum, ThriftStruct};
+use terminal_thrift::transport::BufferTransport;
+use terminal_thrift::protocol::{self, Decode, Encode, Protocol, StrictFields, Type};
+use terminal_thrift::protocol::binary_protocol::BinaryProtocol;
+use terminal_thrift::reflect::Requiredness;
+
+enom! {
+    name = Kind,
+    values = [
+        Plain = 1,
+        Fancy = 2,
+    ],
+    default = Plain
+}
+
+strukt! {
+    name = Inner,
+    fields = {
+        key: String => 1,
+    }
+}
+
+strukt! {
+    name = Macro,
+    fields = {
+        #[required] id: i32 => 1,
+        name: Option<String> => 2,
+        tags: BTreeMap<String, Vec<i64>> => 3,
+        kind: Kind => 4,
+        inner: Inner => 7,
+    }
+}
+
+/// A hand-written equivalent of `Kind`.
+#[derive(ThriftEnum, Debug, PartialEq)]
+pub enum Flavor {
+    Plain = 1,
+    Fancy,
+}
+
+impl Default for Flavor {
+    fn default() -> Self { Flavor::Plain }
+}
+
+/// A hand-written equivalent of `Macro`.
+#[derive(ThriftStruct, Debug, Default, PartialEq)]
+pub struct Derived {
+    #[thrift(id = 1, required)]
+    pub id: i32,
+    #[thrift(id = 2)]
+    pub name: Option<String>,
+    #[thrift(id = 3)]
+    tags: BTreeMap<String, Vec<i64>>,
+    #[thrift(id = 4)]
+    pub kind: Flavor,
+    #[thrift(id = 7)]
+    pub inner: DerivedInner,
+}
+
+#[derive(ThriftStruct, Debug, Default, PartialEq)]
+pub struct DerivedInner {
+    #[thrift(id = 1)]
+    key: String,
+}
+
+#[derive(ThriftStruct, Debug, Default, PartialEq)]
+struct Unit;
+
+/// Derived through the runtime under another name.
+#[derive(ThriftStruct, Debug, Default, PartialEq)]
+#[thrift(crate = "::thrift")]
+pub struct Renamed {
+    #[thrift(id = 4)]
+    pub kind: RenamedFlavor,
+}
+
+#[derive(ThriftEnum, Debug, PartialEq)]
+#[thrift(crate = "::thrift")]
+pub enum RenamedFlavor {
+    Plain = 1,
+    Fancy,
+}
+
+impl Default for RenamedFlavor {
+    fn default() -> Self { RenamedFlavor::Plain }
+}
+
+fn binary<E: Encode>(value: &E) -> Vec<u8> {
+    let mut transport = BufferTransport::default();
+    value.encode(&mut BinaryProtocol, &mut transport).unwrap();
+    transport.into_output()
+}
+
+fn from_binary<D: Decode>(bytes: Vec<u8>) -> D {
+    from_binary_with(BinaryProtocol, bytes).unwrap()
+}
+
+fn from_binary_with<D: Decode, P: Protocol>(mut protocol: P, bytes: Vec<u8>) -> Result<D, Error> {
+    let mut value = D::default();
+    try!(value.decode(&mut protocol, &mut BufferTransport::new(bytes)));
+    Ok(value)
+}
+
+fn tags() -> BTreeMap<String, Vec<i64>> {
+    let mut tags = BTreeMap::new();
+    tags.insert(String::from("primes"), vec![2, 3, 5]);
+    tags
+}
+
+#[test]
+fn test_derived_struct_matches_strukt() {
+    let declared = Macro {
+        id: 3,
+        name: Some(String::from("three")),
+        tags: tags(),
+        kind: Kind::Fancy,
+        inner: Inner { key: String::from("inner") },
+    };
+    let derived = Derived {
+        id: 3,
+        name: Some(String::from("three")),
+        tags: tags(),
+        kind: Flavor::Fancy,
+        inner: DerivedInner { key: String::from("inner") },
+    };
+
+    assert_eq!(binary(&derived), binary(&declared));
+    assert_eq!(from_binary::<Derived>(binary(&declared)), derived);
+    assert_eq!(from_binary::<Macro>(binary(&derived)), declared);
+
+    let unset = Derived { name: None, ..Derived::default() };
+    assert_eq!(binary(&unset), binary(&Macro { name: None, ..Macro::default() }));
+    // Just the stop field.
+    assert_eq!(binary(&Unit), vec![0]);
+}
+
+#[test]
+fn test_derived_struct_checks_required_fields() {
+    let missing = protocol::Error::MissingRequiredField { strukt: "Derived", field: "id" };
+    match from_binary_with::<Derived, _>(BinaryProtocol, binary(&Unit)) {
+        Err(Error::ProtocolError(ref e)) if *e == missing => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_derived_struct_strict_fields() {
+    let mismatch = protocol::Error::FieldTypeMismatch {
+        strukt: "DerivedInner",
+        field: "key",
+        expected: Type::String,
+        actual: Type::I32,
+    };
+    let bytes = binary(&Macro::default());
+    assert_eq!(from_binary::<DerivedInner>(bytes.clone()), DerivedInner::default());
+    match from_binary_with::<DerivedInner, _>(StrictFields(BinaryProtocol), bytes) {
+        Err(Error::ProtocolError(ref e)) if *e == mismatch => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_derived_descriptors() {
+    let descriptor = Derived::descriptor();
+    assert_eq!(descriptor.name, "Derived");
+    assert_eq!(descriptor.fields.len(), Macro::descriptor().fields.len());
+    for (derived, declared) in descriptor.fields.iter().zip(Macro::descriptor().fields) {
+        assert_eq!((derived.id, derived.name, derived.typ, derived.requiredness),
+                   (declared.id, declared.name, declared.typ, declared.requiredness));
+    }
+    assert_eq!(descriptor.field(1).unwrap().requiredness, Requiredness::Required);
+    assert_eq!(descriptor.field(2).unwrap().requiredness, Requiredness::Optional);
+    assert_eq!(descriptor.field(3).unwrap().requiredness, Requiredness::Default);
+
+    assert_eq!(Flavor::descriptor().values, &[("Plain", 1), ("Fancy", 2)]);
+    assert_eq!(Flavor::descriptor().values, Kind::descriptor().values);
+}
+
+#[test]
+fn test_derived_enum_matches_enom() {
+    assert_eq!(Flavor::ALL, &[Flavor::Plain, Flavor::Fancy]);
+    assert_eq!(Flavor::values().iter().map(Flavor::as_i32).collect::<Vec<_>>(),
+               Kind::values().iter().map(Kind::as_i32).collect::<Vec<_>>());
+    assert_eq!(Flavor::Fancy.name(), Kind::Fancy.name());
+    assert_eq!(Flavor::Fancy.to_string(), Kind::Fancy.to_string());
+    assert_eq!("Fancy".parse::<Flavor>(), Ok(Flavor::Fancy));
+    assert_eq!("fancy".parse::<Flavor>(),
+               Err(EnumError::UnknownName { enom: "Flavor", name: String::from("fancy") }));
+    assert_eq!(i32::from(Flavor::Fancy), 2);
+    assert_eq!(Flavor::try_from(1), Ok(Flavor::Plain));
+    assert_eq!(Flavor::try_from(3), Err(EnumError::UnknownValue { enom: "Flavor", value: 3 }));
+}
+
+#[test]
+fn test_derive_with_renamed_runtime() {
+    let renamed = Renamed { kind: RenamedFlavor::Fancy };
+    let declared = Macro { kind: Kind::Fancy, ..Macro::default() };
+    assert_eq!(from_binary::<Renamed>(binary(&declared)), renamed);
+    assert_eq!(Renamed::descriptor().fields[0].name, "kind");
+    assert_eq!(RenamedFlavor::Fancy.to_string(), "Fancy");
+}
@@ -0,0 +1,154 @@
+#[macro_use]
+extern crate terminal_thrift;
+#[macro_use]
+extern crate terminal_thrift_macros;
+extern crate terminal_thrift as thrift;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use terminal_thrift::{ThriftService, ThriftStruct};
+use terminal_thrift::transport::BufferTransport;
+use terminal_thrift::protocol::{Decode, Encode};
+use terminal_thrift::protocol::binary_protocol::BinaryProtocol;
+use terminal_thrift::rt::OrderedFloat;
+
+thrift_idl! { r#"
+    enum Shape { ROUND = 1, square }
+
+    typedef map<string, list<i64>> Tags
+
+    struct Point {
+      1: double x,
+      2: double y,
+    }
+
+    struct Drawing {
+      1: string title,
+      2: optional Shape shape,
+      3: Tags tags,
+      4: set<Point> points,
+    }
+
+    exception Smudged {
+      1: i32 where,
+    }
+
+    const double ORIGIN_X = 0
+    const Tags NO_TAGS = {}
+    const set<string> NAMES = ["one", "two"]
+
+    service Canvas {
+      void draw(1: Drawing drawing) throws (1: Smudged smudged),
+      oneway void clear(),
+    }
+"# }
+
+// `Drawing`, as `strukt!` is used by hand.
+strukt! {
+    name = HandDrawing,
+    fields = {
+        title: String => 1,
+        shape: Option<Shape> => 2,
+        tags: BTreeMap<String, Vec<i64>> => 3,
+        points: BTreeSet<Point> => 4,
+    }
+}
+
+mod options {
+    thrift_idl!(builders, unknown_enums, "
+        enum Mood { HAPPY = 1 }
+        struct Face { 1: Mood mood, 2: optional string name }
+    ");
+}
+
+mod renamed {
+    thrift_idl!(crate = "::thrift", "
+        struct Scale { 1: double factor }
+    ");
+}
+
+mod shared {
+    include_thrift!("../../../tutorial/shared.thrift");
+}
+
+mod tutorial {
+    use shared::*;
+    include_thrift!("../../../tutorial/tutorial.thrift");
+}
+
+fn binary<E: Encode>(value: &E) -> Vec<u8> {
+    let mut transport = BufferTransport::default();
+    value.encode(&mut BinaryProtocol, &mut transport).unwrap();
+    transport.into_output()
+}
+
+fn from_binary<D: Decode>(bytes: Vec<u8>) -> D {
+    let mut value = D::default();
+    value.decode(&mut BinaryProtocol, &mut BufferTransport::new(bytes)).unwrap();
+    value
+}
+
+#[test]
+fn test_thrift_idl_items() {
+    let mut tags = BTreeMap::new();
+    tags.insert(String::from("primes"), vec![2, 3, 5]);
+    let mut points = BTreeSet::new();
+    points.insert(Point { x: OrderedFloat(1.5), y: ORIGIN_X });
+
+    let drawing = Drawing {
+        title: String::from("dot"),
+        shape: Some(Shape::Square),
+        tags: tags.clone(),
+        points: points.clone(),
+    };
+    let hand = HandDrawing {
+        title: String::from("dot"),
+        shape: Some(Shape::Square),
+        tags: tags,
+        points: points,
+    };
+
+    assert_eq!(binary(&drawing), binary(&hand));
+    assert_eq!(from_binary::<Drawing>(binary(&hand)), drawing);
+    assert_eq!(Shape::Square.to_string(), "square");
+    assert_eq!(*NO_TAGS, Tags::new());
+    assert!(NAMES.contains("two"));
+    assert_eq!(Drawing::descriptor().field_by_name("points").unwrap().id, 4);
+}
+
+#[test]
+fn test_thrift_idl_service() {
+    let descriptor = <CanvasClient<BinaryProtocol, BufferTransport> as ThriftService>::descriptor();
+    assert_eq!(descriptor.name, "Canvas");
+
+    let draw = descriptor.method("draw").unwrap();
+    assert_eq!(draw.exceptions[0].name, "smudged");
+    assert!(descriptor.method("clear").unwrap().oneway);
+}
+
+#[test]
+fn test_thrift_idl_options() {
+    let face = options::Face::builder().mood(options::Mood::HAPPY).build().unwrap();
+    assert_eq!(face.name, None);
+    assert_eq!(from_binary::<options::Mood>(binary(&7)), options::Mood::Unknown(7));
+}
+
+#[test]
+fn test_thrift_idl_renamed_runtime() {
+    let scale = renamed::Scale { factor: OrderedFloat(2.5) };
+    assert_eq!(from_binary::<renamed::Scale>(binary(&scale)), scale);
+}
+
+#[test]
+fn test_include_thrift() {
+    let work = tutorial::Work { num1: 1, num2: 2, op: tutorial::Operation::ADD, comment: None };
+    assert_eq!(from_binary::<tutorial::Work>(binary(&work)), work);
+    assert_eq!(tutorial::INT32CONSTANT, 9853);
+    assert_eq!(tutorial::MAPCONSTANT["goodnight"], "moon");
+
+    let calculator = <dyn tutorial::Calculator as ThriftService>::descriptor();
+    let parents = calculator.parents().iter().map(|parent| parent.name).collect::<Vec<_>>();
+    assert_eq!(parents, vec!["SharedService"]);
+    assert!(calculator.all_methods().iter().any(|method| method.name == "getStruct"));
+    assert_eq!(shared::SharedStruct::descriptor().name, "SharedStruct");
+}
@@ -0,0 +1,44 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::fmt;
+use std::error::Error as StdError;
+
+/// Why a builder, such as a struct builder generated by `strukt!` with a
+/// `builder` name, could not build its value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BuildError {
+    /// A required field was never set.
+    MissingField { strukt: &'static str, field: &'static str },
+    /// A field was set to a value that cannot be used.
+    InvalidValue { strukt: &'static str, field: &'static str, reason: &'static str },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuildError::MissingField { strukt, field } =>
+                write!(f, "missing required field {}.{}", strukt, field),
+            BuildError::InvalidValue { strukt, field, reason } =>
+                write!(f, "invalid value for {}.{}: {}", strukt, field, reason),
+        }
+    }
+}
+
+impl StdError for BuildError {}
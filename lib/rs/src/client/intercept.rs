@@ -0,0 +1,125 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use protocol::{MessageType, Value};
+use transport::{Transport, DEADLINE_HEADER};
+use virt::VirtualEncodeObject;
+use {Error, Result};
+
+/// Sees every call made by a client, for logging, metrics or tracing.
+pub trait Interceptor: Send + Sync {
+    /// Called before a call is sent. Returning a reply answers the call
+    /// without sending it; the reply is the method's result struct, as
+    /// captured by `Value::from_encode`, or an error.
+    fn before(&self, _call: &mut Call) -> Option<Result<Value>> { None }
+
+    /// Called with the result struct of the reply, `None` for oneway calls,
+    /// or the error the call failed with. Interceptors see every call,
+    /// including those another interceptor answered.
+    fn after(&self, _call: &Call, _reply: Reply) {}
+}
+
+/// What a reply looks like to an interceptor.
+pub type Reply<'a> = ::std::result::Result<Option<VirtualEncodeObject<'a>>, &'a Error>;
+
+/// A call being made, as seen by interceptors.
+pub struct Call<'a> {
+    pub method: &'static str,
+    /// `Call` or `Oneway`.
+    pub message_type: MessageType,
+    pub sequence_id: i32,
+    /// The arguments struct.
+    pub args: VirtualEncodeObject<'a>,
+    /// Sent with the request through `Transport::set_headers`, if any are
+    /// added. Only transports that carry headers, like `FramedTransport`,
+    /// send them; others drop them with a warning.
+    pub headers: BTreeMap<String, String>,
+    pub started: Instant,
+}
+
+impl<'a> Call<'a> {
+    pub fn new(method: &'static str, message_type: MessageType, sequence_id: i32,
+               args: VirtualEncodeObject<'a>) -> Call<'a> {
+        Call {
+            method: method,
+            message_type: message_type,
+            sequence_id: sequence_id,
+            args: args,
+            headers: BTreeMap::new(),
+            started: Instant::now(),
+        }
+    }
+}
+
+/// The interceptors of a client, called in the order they were added.
+#[derive(Clone, Default)]
+pub struct Interceptors {
+    interceptors: Vec<Arc<Interceptor>>,
+}
+
+impl Interceptors {
+    pub fn new() -> Interceptors { Interceptors::default() }
+
+    pub fn add<I: Interceptor + 'static>(&mut self, interceptor: I) {
+        self.interceptors.push(Arc::new(interceptor));
+    }
+
+    pub fn is_empty(&self) -> bool { self.interceptors.is_empty() }
+
+    /// Call `before` on each interceptor until one answers the call.
+    pub fn before(&self, call: &mut Call) -> Option<Result<Value>> {
+        self.interceptors.iter().filter_map(|interceptor| interceptor.before(call)).next()
+    }
+
+    pub fn after(&self, call: &Call, reply: Reply) {
+        for interceptor in &self.interceptors {
+            interceptor.after(call, reply);
+        }
+    }
+}
+
+impl fmt::Debug for Interceptors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Interceptors({})", self.interceptors.len())
+    }
+}
+
+/// Hand the headers added by interceptors to the transport, along with
+/// `DEADLINE_HEADER` for calls with a timeout if the transport carries
+/// headers.
+pub fn set_headers<T: Transport>(transport: &mut T, headers: &BTreeMap<String, String>,
+                                 timeout: Option<Duration>) -> Result<()> {
+    match timeout {
+        Some(timeout) if transport.carries_headers() => {
+            let millis = timeout.as_secs() * 1000 + u64::from(timeout.subsec_millis());
+            let mut headers = headers.clone();
+            headers.insert(DEADLINE_HEADER.to_owned(), millis.to_string());
+            try!(transport.set_headers(&headers));
+        }
+        _ => if !headers.is_empty() {
+            try!(transport.set_headers(headers));
+        },
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+//! Support for the clients declared with `service!`.
+
+use std::io;
+
+use protocol::ProtocolFactory;
+use transport::server::TransportServer;
+use {Protocol, Transport};
+
+pub mod pool;
+pub mod reconnect;
+pub mod pipeline;
+pub mod intercept;
+
+pub use self::pool::{Pool, PoolBuilder, Pooled};
+pub use self::reconnect::Reconnecting;
+pub use self::pipeline::{Pipelined, PipelinedTransport};
+pub use self::intercept::{Call, Interceptor, Interceptors};
+
+/// Implemented by every client declared with `service!`, so that clients can
+/// be managed without knowing the service they call.
+pub trait Client {
+    type Protocol: Protocol;
+    type Transport: Transport;
+
+    fn from_parts(protocol: Self::Protocol, transport: Self::Transport) -> Self;
+
+    /// Whether an earlier call failed in a way that leaves the connection in
+    /// an unknown state, as described by `Error::breaks_connection`. The
+    /// client should then be discarded.
+    fn is_broken(&self) -> bool;
+
+    /// The last call made through the client, if any.
+    fn last_call(&self) -> Option<LastCall>;
+
+    /// Number the next call `seqid` instead of following on from the last
+    /// one, so that clients sharing a connection can keep their calls apart.
+    fn set_next_seqid(&mut self, seqid: i32);
+
+    /// Replace the interceptors that see the client's calls.
+    fn set_interceptors(&mut self, interceptors: Interceptors);
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LastCall {
+    pub method: &'static str,
+    /// Whether the request was completely written. When it was not, the
+    /// server cannot have received the call.
+    pub sent: bool,
+    /// Whether the IDL marks the method `(idempotent)`, so that it is safe
+    /// to call more than once.
+    pub idempotent: bool,
+}
+
+type Connect<C> = Box<Fn() -> io::Result<C> + Send + Sync>;
+
+/// Create clients with new connections.
+fn connector<C, PF, TS>(protocols: PF, transports: TS) -> Connect<C>
+where C: Client,
+      PF: ProtocolFactory<Protocol = C::Protocol> + Send + Sync + 'static,
+      TS: TransportServer<Transport = C::Transport> + Send + Sync + 'static {
+    Box::new(move || {
+        let transport = try!(transports.accept());
+        Ok(C::from_parts(protocols.new_protocol(), transport))
+    })
+}
@@ -0,0 +1,344 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::collections::HashMap;
+use std::io::{self, BufReader, Read, Write};
+use std::marker::PhantomData;
+use std::mem;
+use std::net::{Shutdown, TcpStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use protocol::{ProtocolFactory, Type};
+use transport::Transport;
+use {Protocol, Result};
+use super::{Client, Interceptors};
+
+/// A client that can have many calls outstanding on one connection, and be
+/// shared between threads.
+///
+/// Calls are written as soon as they are made, and a thread reading the
+/// connection hands each reply to the caller waiting for its sequence id, so
+/// the server may reply in any order. Each call is made through a client of
+/// its own, whose transport is a `PipelinedTransport`:
+///
+/// ```ignore
+/// let stream = TcpStream::connect(addr)?;
+/// let client: Pipelined<NotifierClient<BinaryProtocol, PipelinedTransport>> =
+///     Pipelined::tcp(|| BinaryProtocol, stream)?;
+/// let pong = client.call(|client| client.ping())?;
+/// ```
+///
+/// The reading thread stops when the connection is closed or fails, after
+/// which every call fails. Dropping a client made by `tcp` shuts the
+/// connection down and waits for the thread to stop.
+pub struct Pipelined<C: Client> {
+    protocols: Box<Fn() -> C::Protocol + Send + Sync>,
+    shared: Arc<Shared>,
+    /// What to shut down so that the reading thread stops, and the thread.
+    connection: Option<(TcpStream, thread::JoinHandle<()>)>,
+    next_seqid: AtomicUsize,
+    timeout: Option<Duration>,
+    interceptors: Interceptors,
+    client: PhantomData<fn() -> C>,
+}
+
+/// The state shared by the callers and the reading thread.
+struct Shared {
+    writer: Mutex<Box<Write + Send>>,
+    waiting: Mutex<Waiting>,
+}
+
+struct Waiting {
+    replies: HashMap<i32, mpsc::Sender<io::Result<Vec<u8>>>>,
+    /// Why the connection can no longer be used.
+    failure: Option<String>,
+}
+
+impl<C: Client<Transport = PipelinedTransport>> Pipelined<C> {
+    /// Pipeline calls over a TCP connection.
+    pub fn tcp<PF>(protocols: PF, stream: TcpStream) -> io::Result<Self>
+    where PF: ProtocolFactory<Protocol = C::Protocol> + Send + Sync + 'static,
+          C::Protocol: Send + 'static {
+        let reader = try!(stream.try_clone());
+        let connection = try!(stream.try_clone());
+        let (mut pipelined, dispatcher) = Pipelined::start(protocols, reader, stream);
+        pipelined.connection = Some((connection, dispatcher));
+        Ok(pipelined)
+    }
+
+    /// Pipeline calls over a connection, read through `reader` and written
+    /// through `writer`. Dropping the client fails the calls still waiting,
+    /// but the reading thread only stops once `reader` is closed.
+    pub fn new<PF, R, W>(protocols: PF, reader: R, writer: W) -> Self
+    where PF: ProtocolFactory<Protocol = C::Protocol> + Send + Sync + 'static,
+          C::Protocol: Send + 'static,
+          R: Read + Send + 'static,
+          W: Write + Send + 'static {
+        Pipelined::start(protocols, reader, writer).0
+    }
+
+    fn start<PF, R, W>(protocols: PF, reader: R, writer: W) -> (Self, thread::JoinHandle<()>)
+    where PF: ProtocolFactory<Protocol = C::Protocol> + Send + Sync + 'static,
+          C::Protocol: Send + 'static,
+          R: Read + Send + 'static,
+          W: Write + Send + 'static {
+        let shared = Arc::new(Shared {
+            writer: Mutex::new(Box::new(writer)),
+            waiting: Mutex::new(Waiting { replies: HashMap::new(), failure: None }),
+        });
+
+        let dispatcher = shared.clone();
+        let protocol = protocols.new_protocol();
+        let dispatcher = thread::spawn(move || {
+            dispatch(protocol, Recorder { reader: BufReader::new(reader), recorded: Vec::new() }, dispatcher)
+        });
+
+        let pipelined = Pipelined {
+            protocols: Box::new(move || protocols.new_protocol()),
+            shared: shared,
+            connection: None,
+            next_seqid: AtomicUsize::new(1),
+            timeout: None,
+            interceptors: Interceptors::new(),
+            client: PhantomData,
+        };
+        (pipelined, dispatcher)
+    }
+
+    /// Fail calls that get no reply within `timeout` with `Error::Timeout`.
+    /// Unlike with other clients, the connection can still be used.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Have `interceptors` see every call.
+    pub fn interceptors(mut self, interceptors: Interceptors) -> Self {
+        self.interceptors = interceptors;
+        self
+    }
+
+    /// Make a call with a new client. It can only make one call.
+    pub fn call<R, F>(&self, call: F) -> Result<R>
+    where F: FnOnce(&mut C) -> Result<R> {
+        let seqid = self.next_seqid.fetch_add(1, Ordering::SeqCst) as i32;
+        let transport = PipelinedTransport {
+            shared: self.shared.clone(),
+            seqid: seqid,
+            request: Vec::new(),
+            sent: false,
+            waiting: None,
+            reply: io::Cursor::new(Vec::new()),
+            timeout: self.timeout,
+        };
+
+        let mut client = C::from_parts((self.protocols)(), transport);
+        client.set_next_seqid(seqid);
+        client.set_interceptors(self.interceptors.clone());
+        call(&mut client)
+    }
+}
+
+/// The transport of a single call made through `Pipelined`.
+pub struct PipelinedTransport {
+    shared: Arc<Shared>,
+    /// The sequence id the client was told to use for its call.
+    seqid: i32,
+    request: Vec<u8>,
+    sent: bool,
+    /// Where the reply will be sent, until it arrives.
+    waiting: Option<mpsc::Receiver<io::Result<Vec<u8>>>>,
+    reply: io::Cursor<Vec<u8>>,
+    timeout: Option<Duration>,
+}
+
+impl PipelinedTransport {
+    fn send(&mut self) -> io::Result<()> {
+        let request = mem::replace(&mut self.request, Vec::new());
+
+        {
+            let mut waiting = self.shared.waiting.lock().unwrap();
+            if let Some(ref failure) = waiting.failure {
+                return Err(io::Error::new(io::ErrorKind::NotConnected, failure.clone()));
+            }
+
+            // Oneway calls are never answered, and stop waiting when the
+            // transport is dropped.
+            let (sender, receiver) = mpsc::channel();
+            waiting.replies.insert(self.seqid, sender);
+            self.waiting = Some(receiver);
+        }
+
+        let mut writer = self.shared.writer.lock().unwrap();
+        let written = writer.write_all(&request).and_then(|()| writer.flush());
+        if let Err(ref e) = written {
+            // Part of the request may have been written.
+            self.shared.fail(format!("writing a request failed: {}", e));
+        }
+        written
+    }
+
+    fn receive(&mut self) -> io::Result<()> {
+        let reply = match self.waiting {
+            Some(ref receiver) => match self.timeout {
+                Some(timeout) => receiver.recv_timeout(timeout).map_err(|e| match e {
+                    mpsc::RecvTimeoutError::Timeout => io::Error::new(io::ErrorKind::TimedOut, "no reply in time"),
+                    mpsc::RecvTimeoutError::Disconnected => disconnected(),
+                }),
+                None => receiver.recv().map_err(|_| disconnected()),
+            },
+            None => return Ok(()),
+        };
+
+        self.waiting = None;
+        self.reply = io::Cursor::new(try!(try!(reply)));
+        Ok(())
+    }
+}
+
+fn disconnected() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionAborted, "the pipelined connection was closed")
+}
+
+impl Read for PipelinedTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.reply.position() == self.reply.get_ref().len() as u64 {
+            try!(self.receive());
+        }
+        self.reply.read(buf)
+    }
+}
+
+impl Write for PipelinedTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.sent {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "only one call can be made with a pipelined client"));
+        }
+        self.request.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.sent || self.request.is_empty() {
+            return Ok(());
+        }
+        self.sent = true;
+        self.send()
+    }
+}
+
+impl Transport for PipelinedTransport {
+    /// Replaces the timeout set through `Pipelined::timeout`.
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    /// Writes never block, so only the read timeout means anything.
+    fn timeouts(&self) -> io::Result<(Option<Duration>, Option<Duration>)> {
+        Ok((self.timeout, self.timeout))
+    }
+}
+
+impl<C: Client> Drop for Pipelined<C> {
+    fn drop(&mut self) {
+        self.shared.fail(String::from("the pipelined client was dropped"));
+        if let Some((connection, dispatcher)) = self.connection.take() {
+            // Wakes the reading thread up, which then stops.
+            let _ = connection.shutdown(Shutdown::Both);
+            let _ = dispatcher.join();
+        }
+    }
+}
+
+impl Drop for PipelinedTransport {
+    fn drop(&mut self) {
+        // Drop the reply if it comes after all.
+        if self.waiting.is_some() {
+            self.shared.waiting.lock().unwrap().replies.remove(&self.seqid);
+        }
+    }
+}
+
+impl Shared {
+    /// Fail every waiting call, and those made later.
+    fn fail(&self, failure: String) {
+        let mut waiting = self.waiting.lock().unwrap();
+        for (_, sender) in waiting.replies.drain() {
+            let _ = sender.send(Err(io::Error::new(io::ErrorKind::ConnectionAborted, failure.clone())));
+        }
+        waiting.failure = Some(failure);
+    }
+}
+
+/// Read replies until the connection fails, handing each one to the caller
+/// waiting for it.
+fn dispatch<P: Protocol, R: Read>(mut protocol: P, mut reader: Recorder<R>, shared: Arc<Shared>) {
+    loop {
+        let seqid = match read_message(&mut protocol, &mut reader) {
+            Ok(seqid) => seqid,
+            Err(e) => {
+                debug!("pipelined connection closed: {}", e);
+                return shared.fail(format!("reading a reply failed: {}", e));
+            }
+        };
+
+        let reply = mem::replace(&mut reader.recorded, Vec::new());
+        match shared.waiting.lock().unwrap().replies.remove(&seqid) {
+            Some(sender) => { let _ = sender.send(Ok(reply)); }
+            None => debug!("dropping a reply to call {}, which is no longer waited for", seqid),
+        }
+    }
+}
+
+fn read_message<P: Protocol, R: Read>(protocol: &mut P, reader: &mut Recorder<R>) -> Result<i32> {
+    let (_, _, seqid) = try!(protocol.read_message_begin(reader));
+    try!(protocol.skip(reader, Type::Struct));
+    try!(protocol.read_message_end(reader));
+    Ok(seqid)
+}
+
+/// Keeps a copy of everything read, so that a message can be handed on
+/// after it has been skipped.
+struct Recorder<R> {
+    reader: R,
+    recorded: Vec<u8>,
+}
+
+impl<R: Read> Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = try!(self.reader.read(buf));
+        self.recorded.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<R> Write for Recorder<R> {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::Other, "the reading end cannot be written to"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+impl<R: Read> Transport for Recorder<R> {}
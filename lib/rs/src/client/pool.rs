@@ -0,0 +1,411 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use protocol::ProtocolFactory;
+use transport::server::TransportServer;
+use {BuildError, Result};
+use super::{connector, Client, Connect, Interceptors};
+
+/// How long the pool waits before trying again when it cannot open the
+/// connections it needs for its minimum size.
+const REFILL_RETRY: Duration = Duration::from_secs(1);
+
+/// A pool of clients, each with its own connection. Clients are created on
+/// demand from a protocol factory and a `TransportServer`, which can be any
+/// `Fn() -> io::Result<T>` opening a connection.
+///
+/// A client is returned to the pool when the `Pooled` handing it out is
+/// dropped, unless a call broke its connection. The pool can be shared
+/// between threads.
+///
+/// With a minimum size or an idle timeout, a thread of the pool's own keeps
+/// the minimum number of connections open and closes those left idle for
+/// too long, until the pool is dropped.
+pub struct Pool<C> {
+    inner: Arc<Inner<C>>,
+    maintainer: Option<thread::JoinHandle<()>>,
+}
+
+/// Configures a `Pool`, see `Pool::builder`.
+pub struct PoolBuilder<C> {
+    connect: Connect<C>,
+    health_check: Option<Box<Fn(&mut C) -> bool + Send + Sync>>,
+    interceptors: Interceptors,
+    min_size: usize,
+    max_size: usize,
+    idle_timeout: Option<Duration>,
+}
+
+/// What the pool shares with its maintaining thread.
+struct Inner<C> {
+    connect: Connect<C>,
+    health_check: Option<Box<Fn(&mut C) -> bool + Send + Sync>>,
+    interceptors: Interceptors,
+    min_size: usize,
+    max_size: usize,
+    idle_timeout: Option<Duration>,
+    state: Mutex<State<C>>,
+    returned: Condvar,
+    /// Wakes the maintaining thread up when a connection is returned or
+    /// closed, or the pool is dropped.
+    changed: Condvar,
+}
+
+struct State<C> {
+    /// The most recently returned client first.
+    idle: VecDeque<Idle<C>>,
+    /// The number of open connections, idle or not, including those being
+    /// opened.
+    size: usize,
+    dropped: bool,
+}
+
+struct Idle<C> {
+    client: C,
+    since: Instant,
+}
+
+impl<C: Client + Send + 'static> Pool<C> {
+    /// A pool of up to 8 clients, closing none for being idle.
+    pub fn new<PF, TS>(protocols: PF, transports: TS) -> Self
+    where PF: ProtocolFactory<Protocol = C::Protocol> + Send + Sync + 'static,
+          TS: TransportServer<Transport = C::Transport> + Send + Sync + 'static {
+        Pool::builder(protocols, transports).start()
+    }
+
+    /// Configure a pool, which starts out like the one made by `new`.
+    pub fn builder<PF, TS>(protocols: PF, transports: TS) -> PoolBuilder<C>
+    where PF: ProtocolFactory<Protocol = C::Protocol> + Send + Sync + 'static,
+          TS: TransportServer<Transport = C::Transport> + Send + Sync + 'static {
+        PoolBuilder {
+            connect: connector(protocols, transports),
+            health_check: None,
+            interceptors: Interceptors::new(),
+            min_size: 0,
+            max_size: 8,
+            idle_timeout: None,
+        }
+    }
+}
+
+impl<C: Client + Send + 'static> PoolBuilder<C> {
+    /// Keep at least this many connections open, even when they are idle.
+    pub fn min_size(mut self, size: usize) -> Self {
+        self.min_size = size;
+        self
+    }
+
+    /// Open at most this many connections; `get` waits for a client to be
+    /// returned when they are all in use. Must be at least one, and at least
+    /// the minimum size.
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    /// Close connections left idle for this long, down to the minimum size.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Check idle clients before handing them out, discarding those for
+    /// which `check` returns false, e.g. because a cheap call failed.
+    pub fn health_check<F>(mut self, check: F) -> Self
+    where F: Fn(&mut C) -> bool + Send + Sync + 'static {
+        self.health_check = Some(Box::new(check));
+        self
+    }
+
+    /// Have `interceptors` see the calls of every client.
+    pub fn interceptors(mut self, interceptors: Interceptors) -> Self {
+        self.interceptors = interceptors;
+        self
+    }
+
+    pub fn build(self) -> ::std::result::Result<Pool<C>, BuildError> {
+        if self.max_size == 0 {
+            return Err(BuildError::InvalidValue {
+                strukt: "Pool",
+                field: "max_size",
+                reason: "a pool needs at least one connection",
+            });
+        }
+        if self.min_size > self.max_size {
+            return Err(BuildError::InvalidValue {
+                strukt: "Pool",
+                field: "min_size",
+                reason: "the minimum size cannot exceed the maximum size",
+            });
+        }
+        Ok(self.start())
+    }
+
+    fn start(self) -> Pool<C> {
+        let maintained = self.min_size > 0 || self.idle_timeout.is_some();
+        let inner = Arc::new(Inner {
+            connect: self.connect,
+            health_check: self.health_check,
+            interceptors: self.interceptors,
+            min_size: self.min_size,
+            max_size: self.max_size,
+            idle_timeout: self.idle_timeout,
+            state: Mutex::new(State { idle: VecDeque::new(), size: 0, dropped: false }),
+            returned: Condvar::new(),
+            changed: Condvar::new(),
+        });
+
+        let maintainer = if maintained {
+            let inner = inner.clone();
+            Some(thread::spawn(move || inner.maintain()))
+        } else {
+            None
+        };
+        Pool { inner: inner, maintainer: maintainer }
+    }
+}
+
+impl<C: Client> Pool<C> {
+    /// Open connections until the pool has its minimum size, which the pool
+    /// also does in the background.
+    pub fn fill(&self) -> Result<()> {
+        self.inner.fill()
+    }
+
+    /// Hand out an idle client, or one with a new connection, waiting for a
+    /// client to be returned if the pool is at its maximum size.
+    pub fn get(&self) -> Result<Pooled<'_, C>> {
+        let inner = &*self.inner;
+        loop {
+            let idle = {
+                let mut state = inner.lock();
+                loop {
+                    inner.close_expired(&mut state);
+                    if let Some(idle) = state.idle.pop_front() {
+                        break Some(idle.client);
+                    } else if state.size < inner.max_size {
+                        state.size += 1;
+                        break None;
+                    }
+                    state = inner.returned.wait(state).unwrap();
+                }
+            };
+
+            // The connection is counted in the size until it is handed out,
+            // even if checking or opening it panics.
+            let counted = Counted { inner: inner };
+            let client = match idle {
+                Some(mut client) => {
+                    let healthy = inner.health_check.as_ref().map_or(true, |check| check(&mut client));
+                    if !healthy || client.is_broken() {
+                        debug!("closing a pooled connection that failed its health check");
+                        continue;
+                    }
+                    client
+                }
+                None => try!(inner.open()),
+            };
+            counted.keep();
+
+            return Ok(Pooled { pool: inner, client: Some(client) });
+        }
+    }
+
+    /// The number of open connections, idle or not.
+    pub fn size(&self) -> usize {
+        self.inner.lock().size
+    }
+
+    /// The number of idle connections.
+    pub fn idle(&self) -> usize {
+        self.inner.lock().idle.len()
+    }
+}
+
+impl<C> Drop for Pool<C> {
+    fn drop(&mut self) {
+        if let Some(maintainer) = self.maintainer.take() {
+            self.inner.state.lock().unwrap().dropped = true;
+            self.inner.changed.notify_all();
+            let _ = maintainer.join();
+        }
+    }
+}
+
+impl<C: Client> Inner<C> {
+    fn fill(&self) -> Result<()> {
+        loop {
+            {
+                let mut state = self.lock();
+                if state.size >= self.min_size {
+                    return Ok(());
+                }
+                state.size += 1;
+            }
+
+            let counted = Counted { inner: self };
+            let client = try!(self.open());
+            counted.keep();
+            self.put(client);
+        }
+    }
+
+    /// Keep the pool at its minimum size and close expired connections,
+    /// until the pool is dropped.
+    fn maintain(&self) {
+        let mut state = self.lock();
+        while !state.dropped {
+            self.close_expired(&mut state);
+
+            let wait = if state.size < self.min_size {
+                drop(state);
+                let filled = self.fill();
+                state = self.lock();
+                match filled {
+                    Ok(()) => continue,
+                    Err(e) => {
+                        warn!("could not open the minimum number of pooled connections: {}", e);
+                        Some(REFILL_RETRY)
+                    }
+                }
+            } else {
+                // The least recently returned client expires first.
+                match (self.idle_timeout, state.idle.back()) {
+                    (Some(timeout), Some(idle)) if state.size > self.min_size => {
+                        Some((idle.since + timeout).saturating_duration_since(Instant::now()))
+                    }
+                    _ => None,
+                }
+            };
+
+            state = match wait {
+                Some(wait) => self.changed.wait_timeout(state, wait).unwrap().0,
+                None => self.changed.wait(state).unwrap(),
+            };
+        }
+    }
+
+    /// Open a connection that has already been counted in the size.
+    fn open(&self) -> Result<C> {
+        let mut client = try!((self.connect)());
+        client.set_interceptors(self.interceptors.clone());
+        Ok(client)
+    }
+
+    fn put(&self, client: C) {
+        let mut state = self.lock();
+        state.idle.push_front(Idle { client: client, since: Instant::now() });
+        self.returned.notify_one();
+        self.changed.notify_one();
+    }
+
+    /// Forget a connection that has been closed.
+    fn discard(&self) {
+        self.lock().size -= 1;
+        self.returned.notify_one();
+        self.changed.notify_one();
+    }
+
+    fn close_expired(&self, state: &mut State<C>) {
+        let timeout = match self.idle_timeout {
+            Some(timeout) => timeout,
+            None => return,
+        };
+
+        // The least recently returned clients are at the back.
+        while state.size > self.min_size {
+            match state.idle.back() {
+                Some(idle) if idle.since.elapsed() >= timeout => {}
+                _ => break,
+            }
+            state.idle.pop_back();
+            state.size -= 1;
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<C>> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// Takes a connection counted in the size of the pool out of it again when
+/// dropped, unless it is kept.
+struct Counted<'a, C: Client + 'a> {
+    inner: &'a Inner<C>,
+}
+
+impl<'a, C: Client> Counted<'a, C> {
+    fn keep(self) {
+        ::std::mem::forget(self);
+    }
+}
+
+impl<'a, C: Client> Drop for Counted<'a, C> {
+    fn drop(&mut self) {
+        self.inner.discard();
+    }
+}
+
+/// A client checked out of a `Pool`, returned to it when dropped.
+pub struct Pooled<'a, C: Client + 'a> {
+    pool: &'a Inner<C>,
+    client: Option<C>,
+}
+
+impl<'a, C: Client> Pooled<'a, C> {
+    /// Close the connection instead of returning the client to the pool,
+    /// for instance after a call took too long.
+    pub fn discard(mut self) {
+        self.client = None;
+        self.pool.discard();
+    }
+}
+
+impl<'a, C: Client> Deref for Pooled<'a, C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        self.client.as_ref().unwrap()
+    }
+}
+
+impl<'a, C: Client> DerefMut for Pooled<'a, C> {
+    fn deref_mut(&mut self) -> &mut C {
+        self.client.as_mut().unwrap()
+    }
+}
+
+impl<'a, C: Client> Drop for Pooled<'a, C> {
+    fn drop(&mut self) {
+        match self.client.take() {
+            Some(ref client) if client.is_broken() => {
+                debug!("closing a pooled connection broken by a failed call");
+                self.pool.discard();
+            }
+            Some(client) => self.pool.put(client),
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,153 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::cmp;
+use std::collections::HashSet;
+use std::thread;
+use std::time::Duration;
+
+use protocol::ProtocolFactory;
+use transport::server::TransportServer;
+use {Error, Result};
+use super::{connector, Client, Connect, Interceptors};
+
+/// A client that reconnects when a call breaks its connection, and retries
+/// calls that are safe to repeat.
+///
+/// A failed call is only retried when the server cannot have received it,
+/// because the request was not completely written, and when its method is
+/// idempotent: marked `(idempotent)` in the IDL, or listed with
+/// `idempotent`. Connecting is retried too. Other calls fail as
+/// usual, keeping their at-most-once semantics, but the next call uses a
+/// new connection.
+///
+/// ```ignore
+/// let mut client = Reconnecting::new(|| BinaryProtocol, move || TcpStream::connect(addr))
+///     .idempotent(&["getStruct"]);
+/// let found = try!(client.call(|client: &mut SharedServiceClient<_, _>| client.getStruct(1)));
+/// ```
+pub struct Reconnecting<C> {
+    connect: Connect<C>,
+    client: Option<C>,
+    idempotent: HashSet<String>,
+    retries: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    interceptors: Interceptors,
+}
+
+impl<C: Client> Reconnecting<C> {
+    /// Connect on the first call, and retry up to twice, waiting 50ms, then
+    /// twice as long each time, up to a second. Only the methods the IDL
+    /// marks `(idempotent)` are retried.
+    pub fn new<PF, TS>(protocols: PF, transports: TS) -> Self
+    where PF: ProtocolFactory<Protocol = C::Protocol> + Send + Sync + 'static,
+          TS: TransportServer<Transport = C::Transport> + Send + Sync + 'static {
+        Reconnecting {
+            connect: connector(protocols, transports),
+            client: None,
+            idempotent: HashSet::new(),
+            retries: 2,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(1),
+            interceptors: Interceptors::new(),
+        }
+    }
+
+    /// Mark methods as safe to call more than once, by their IDL names, in
+    /// addition to those the IDL marks `(idempotent)`.
+    pub fn idempotent(mut self, methods: &[&str]) -> Self {
+        self.idempotent.extend(methods.iter().map(|method| String::from(*method)));
+        self
+    }
+
+    /// Retry a call at most this many times.
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Wait `initial` before the first retry, then twice as long before
+    /// every other one, but never more than `max`.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Have `interceptors` see every call, retries included.
+    pub fn interceptors(mut self, interceptors: Interceptors) -> Self {
+        self.interceptors = interceptors;
+        self
+    }
+
+    /// Make a call with the client, connecting first if needed.
+    pub fn call<R, F>(&mut self, mut call: F) -> Result<R>
+    where F: FnMut(&mut C) -> Result<R> {
+        let mut backoff = self.initial_backoff;
+        let mut retries = 0;
+
+        loop {
+            let error = match self.attempt(&mut call) {
+                Ok(result) => return Ok(result),
+                Err(error) => error,
+            };
+
+            if !self.can_retry(&error) || retries == self.retries {
+                return Err(error);
+            }
+
+            debug!("retrying a call after {:?}: {}", backoff, error);
+            thread::sleep(backoff);
+            backoff = cmp::min(backoff * 2, self.max_backoff);
+            retries += 1;
+        }
+    }
+
+    /// The current client, which may be broken, if connected.
+    pub fn client(&mut self) -> Option<&mut C> {
+        self.client.as_mut()
+    }
+
+    fn attempt<R, F>(&mut self, call: &mut F) -> Result<R>
+    where F: FnMut(&mut C) -> Result<R> {
+        if self.client.as_ref().map_or(true, C::is_broken) {
+            self.client = None;
+            let mut client = try!((self.connect)());
+            client.set_interceptors(self.interceptors.clone());
+            self.client = Some(client);
+        }
+
+        call(self.client.as_mut().unwrap())
+    }
+
+    fn can_retry(&self, error: &Error) -> bool {
+        match self.client {
+            // Nothing was sent without a connection.
+            None => true,
+            Some(ref client) => match client.last_call() {
+                Some(last) => {
+                    !last.sent && error.breaks_connection()
+                        && (last.idempotent || self.idempotent.contains(last.method))
+                }
+                None => false,
+            }
+        }
+    }
+}
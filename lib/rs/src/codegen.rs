@@ -3,40 +3,89 @@ macro_rules! service {
     (trait_name = $name:ident,
      processor_name = $processor_name:ident,
      client_name = $client_name:ident,
-     service_methods = [$($siname:ident -> $soname:ident = $smfname:ident.$smname:ident($($saname:ident: $saty:ty => $said:expr,)*) -> $srty:ty => $senname:ident = [$($sevname:ident($sename:ident: $sety:ty => $seid:expr),)*] ($srrty:ty),)*],
-     parent_methods = [$($piname:ident -> $poname:ident = $pmfname:ident.$pmname:ident($($paname:ident: $paty:ty => $paid:expr,)*) -> $prty:ty => $penname:ident = [$($pevname:ident($pename:ident: $pety:ty => $peid:expr),)*] ($prrty:ty),)*],
+     async_client_name = $async_client_name:ident,
+     service_methods = [$($siname:ident -> $soname:ident = $smfname:ident.$smname:ident($($saname:ident: $saty:ty $(= $sadef:expr)* => $said:expr,)*) -> $srty:ty => $senname:ident = [$($sevname:ident($sename:ident: $sety:ty => $seid:expr),)*] ($srrty:ty) oneway = $soneway:tt, idempotent = $sidempotent:tt, context = $scontext:tt,)*],
+     parent_methods = [$($piname:ident -> $poname:ident = $pmfname:ident.$pmname:ident($($paname:ident: $paty:ty $(= $padef:expr)* => $paid:expr,)*) -> $prty:ty => $penname:ident = [$($pevname:ident($pename:ident: $pety:ty => $peid:expr),)*] ($prrty:ty) oneway = $poneway:tt, idempotent = $pidempotent:tt, context = $pcontext:tt,)*],
+     extends = [$($parent:ident)?],
      bounds = [$($boundty:ident: $bound:ident,)*],
      fields = [$($fname:ident: $fty:ty,)*]) => {
         pub trait $name {
-            $(fn $smname(&self, $($saname: $saty),*) -> $srrty;)*
+            $(service_trait_method! { context = $scontext, fn $smname($($saname: $saty),*) -> $srrty })*
         }
 
         service_processor! {
             processor_name = $processor_name,
-            service_methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty),)*],
-            parent_methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty),)*],
+            service_methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty $(= $sadef)* => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty) oneway = $soneway, idempotent = $sidempotent, context = $scontext,)*],
+            parent_methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty $(= $padef)* => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty) oneway = $poneway, idempotent = $pidempotent, context = $pcontext,)*],
             bounds = [$($boundty: $bound,)*],
             fields = [$($fname: $fty,)*]
         }
 
         service_client! {
             client_name = $client_name,
-            service_methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty),)*],
-            parent_methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty),)*]
+            service_methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty $(= $sadef)* => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty) oneway = $soneway, idempotent = $sidempotent, context = $scontext,)*],
+            parent_methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty $(= $padef)* => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty) oneway = $poneway, idempotent = $pidempotent, context = $pcontext,)*]
+        }
+        service_async_client! {
+            trait_name = $name,
+            client_name = $async_client_name,
+            service_methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty $(= $sadef)* => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty) oneway = $soneway, idempotent = $sidempotent, context = $scontext,)*],
+            parent_methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty $(= $padef)* => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty) oneway = $poneway, idempotent = $pidempotent, context = $pcontext,)*]
+        }
+
+        impl $crate::reflect::ThriftService for dyn $name {
+            fn descriptor() -> &'static $crate::reflect::ServiceDescriptor {
+                static DESCRIPTOR: $crate::reflect::ServiceDescriptor = $crate::reflect::ServiceDescriptor {
+                    name: stringify!($name),
+                    parent: service_parent!($($parent)?),
+                    methods: &[$($crate::reflect::MethodDescriptor {
+                        name: stringify!($smname),
+                        args: &[$(reflect_field!($saname: $saty => $said),)*],
+                        returns: <$srty as $crate::reflect::Reflect>::TYPE,
+                        exceptions: &[$(reflect_field!($sename: $sety => $seid),)*],
+                        oneway: $soneway,
+                        idempotent: $sidempotent,
+                    },)*],
+                };
+
+                &DESCRIPTOR
+            }
+        }
+
+        impl<$($boundty: $bound),*> $crate::reflect::ThriftService for $processor_name<$($boundty),*> {
+            fn descriptor() -> &'static $crate::reflect::ServiceDescriptor {
+                <dyn $name as $crate::reflect::ThriftService>::descriptor()
+            }
+        }
+
+        impl<P: $crate::Protocol, T: $crate::Transport> $crate::reflect::ThriftService for $client_name<P, T> {
+            fn descriptor() -> &'static $crate::reflect::ServiceDescriptor {
+                <dyn $name as $crate::reflect::ThriftService>::descriptor()
+            }
         }
     }
 }
 
+#[macro_export]
+macro_rules! service_parent {
+    () => { None };
+    ($parent:ident) => {
+        Some(<dyn $parent as $crate::reflect::ThriftService>::descriptor)
+    };
+}
+
 #[macro_export]
 macro_rules! service_processor {
     (processor_name = $name:ident,
-     service_methods = [$($siname:ident -> $soname:ident = $smfname:ident.$smname:ident($($saname:ident: $saty:ty => $said:expr,)*) -> $srty:ty => $senname:ident = [$($sevname:ident($sename:ident: $sety:ty => $seid:expr),)*] ($srrty:ty),)*],
-     parent_methods = [$($piname:ident -> $poname:ident = $pmfname:ident.$pmname:ident($($paname:ident: $paty:ty => $paid:expr,)*) -> $prty:ty => $penname:ident = [$($pevname:ident($pename:ident: $pety:ty => $peid:expr),)*] ($prrty:ty),)*],
+     service_methods = [$($siname:ident -> $soname:ident = $smfname:ident.$smname:ident($($saname:ident: $saty:ty $(= $sadef:expr)* => $said:expr,)*) -> $srty:ty => $senname:ident = [$($sevname:ident($sename:ident: $sety:ty => $seid:expr),)*] ($srrty:ty) oneway = $soneway:tt, idempotent = $sidempotent:tt, context = $scontext:tt,)*],
+     parent_methods = [$($piname:ident -> $poname:ident = $pmfname:ident.$pmname:ident($($paname:ident: $paty:ty $(= $padef:expr)* => $paid:expr,)*) -> $prty:ty => $penname:ident = [$($pevname:ident($pename:ident: $pety:ty => $peid:expr),)*] ($prrty:ty) oneway = $poneway:tt, idempotent = $pidempotent:tt, context = $pcontext:tt,)*],
      bounds = [$($boundty:ident: $bound:ident,)*],
      fields = [$($fname:ident: $fty:ty,)*]) => {
         pub struct $name<$($boundty: $bound),*> {
             $($fname: $fty,)*
-            proxies: $crate::proxy::Proxies
+            proxies: $crate::proxy::Proxies,
+            catch_panics: bool,
+            missing_arguments: $crate::processor::MissingArguments
         }
 
         $(strukt! { name = $siname, fields = { $($saname: Option<$saty> => $said,)* } }
@@ -45,7 +94,24 @@ macro_rules! service_processor {
 
         impl<$($boundty: $bound),*> $name<$($boundty),*> {
             pub fn new($($fname: $fty),*) -> Self {
-                $name { $($fname: $fname,)* proxies: Default::default() }
+                $name {
+                    $($fname: $fname,)*
+                    proxies: Default::default(),
+                    catch_panics: false,
+                    missing_arguments: Default::default()
+                }
+            }
+
+            /// Catch panics in handler methods and reply to the caller with an
+            /// `InternalError` application exception instead of unwinding
+            /// through the server.
+            pub fn catch_panics(&mut self, catch: bool) {
+                self.catch_panics = catch;
+            }
+
+            /// Choose how calls that leave out arguments are handled.
+            pub fn missing_arguments(&mut self, policy: $crate::processor::MissingArguments) {
+                self.missing_arguments = policy;
             }
 
             /// Add a `Proxy` to be used for all incoming messages.
@@ -59,12 +125,12 @@ macro_rules! service_processor {
                 match name {
                     $(stringify!($smname) => self.$smname(prot, transport, ty, id),)*
                     $(stringify!($pmname) => self.$pmname(prot, transport, ty, id),)*
-                    _ => Err($crate::Error::from($crate::protocol::Error::ProtocolViolation))
+                    _ => $crate::protocol::helpers::reply_unknown_method(prot, transport, name, ty, id)
                 }
             }
 
-            service_processor_methods! { methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty),)*] }
-            service_processor_methods! { methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty),)*] }
+            service_processor_methods! { methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty $(= $sadef)* => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty) oneway = $soneway, idempotent = $sidempotent, context = $scontext,)*] }
+            service_processor_methods! { methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty $(= $padef)* => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty) oneway = $poneway, idempotent = $pidempotent, context = $pcontext,)*] }
         }
 
         impl<P: $crate::Protocol, T: $crate::Transport, $($boundty: $bound),*> $crate::Processor<P, T> for $name<$($boundty),*> {
@@ -97,36 +163,128 @@ macro_rules! service_processor_error_enum {
                 }
             }
         )+
+
+        impl ::std::fmt::Display for $senname {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                match *self {
+                    $($senname::$sevname(ref e) => ::std::fmt::Display::fmt(e, f),)+
+                }
+            }
+        }
+
+        impl ::std::error::Error for $senname {
+            fn source(&self) -> Option<&(::std::error::Error + 'static)> {
+                match *self {
+                    $($senname::$sevname(ref e) => Some(e),)+
+                }
+            }
+        }
+
+        impl From<$senname> for $crate::Error {
+            fn from(e: $senname) -> $crate::Error {
+                $crate::Error::UserException(Box::new(e))
+            }
+        }
     }
 }
 
 #[macro_export]
 macro_rules! service_processor_methods {
-    (methods = [$($iname:ident -> $oname:ident = $fname:ident.$mname:ident($($aname:ident: $aty:ty => $aid:expr,)*) -> $rty:ty => $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*] ($rrty:ty),)*]) => {
+    (methods = [$($iname:ident -> $oname:ident = $fname:ident.$mname:ident($($aname:ident: $aty:ty $(= $adef:expr)* => $aid:expr,)*) -> $rty:ty => $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*] ($rrty:ty) oneway = $oneway:tt, idempotent = $idempotent:tt, context = $context:tt,)*]) => {
         $(fn $mname<P: $crate::Protocol, T: $crate::Transport>(&self, prot: &mut P, transport: &mut T,
                                                                ty: $crate::protocol::MessageType, id: i32) -> $crate::Result<()> {
             use $crate::proxy::Proxy;
 
             static MNAME: &'static str = stringify!($mname);
 
+            // A call to a oneway method would wait forever for its reply.
+            let expected = service_message_type!(oneway = $oneway);
+            if ty != expected && (ty == $crate::protocol::MessageType::Call || ty == $crate::protocol::MessageType::Oneway) {
+                return $crate::protocol::helpers::reply_invalid_message_type(prot, transport, MNAME, expected, ty, id)
+            }
+
             let mut args = $iname::default();
             try!($crate::protocol::helpers::receive_body(prot, transport, MNAME,
-                                                         &mut args, MNAME, ty, id));
+                                                         &mut args, MNAME, ty));
 
             self.proxies.proxy(ty, MNAME, id, &args);
 
-            // TODO: Further investigate this unwrap.
-            let result = self.$fname.$mname($(args.$aname.unwrap()),*);
-            let result = service_processor_methods_translate_return!(
-                result, $oname, $enname = [$($evname($ename: $ety => $eid),)*]);
-            try!($crate::protocol::helpers::send(prot, transport, MNAME,
-                                                 $crate::protocol::MessageType::Reply, &result, id));
+            $(let $aname = match args.$aname {
+                Some(value) => value,
+                None => match self.missing_arguments {
+                    $crate::processor::MissingArguments::UseDefault => service_processor_default_argument!($aty $(, $adef)*),
+                    $crate::processor::MissingArguments::Reject => {
+                        return $crate::protocol::helpers::reply_missing_argument(
+                            prot, transport, MNAME, stringify!($aname), ty, id)
+                    }
+                }
+            };)*
 
-            Ok(())
+            let result = match ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+                service_processor_call!(context = $context, self.$fname, $mname($($aname),*), transport, MNAME, ty, id)
+            })) {
+                Ok(result) => result,
+                Err(payload) => if self.catch_panics {
+                    return $crate::protocol::helpers::reply_handler_panic(prot, transport, MNAME, ty, id, payload)
+                } else {
+                    ::std::panic::resume_unwind(payload)
+                }
+            };
+            service_processor_methods_reply! {
+                oneway = $oneway, prot, transport, MNAME, id,
+                result, $oname, $enname = [$($evname($ename: $ety => $eid),)*]
+            }
         })*
     }
 }
 
+/// The IDL default of an argument, or its type's default when it has none.
+#[macro_export]
+macro_rules! service_processor_default_argument {
+    ($aty:ty) => { <$aty as Default>::default() };
+    ($aty:ty, $adef:expr) => { $adef };
+}
+
+#[macro_export]
+macro_rules! service_trait_method {
+    (context = false, fn $mname:ident($($aname:ident: $aty:ty),*) -> $rrty:ty) => {
+        fn $mname(&self, $($aname: $aty),*) -> $rrty;
+    };
+    (context = true, fn $mname:ident($($aname:ident: $aty:ty),*) -> $rrty:ty) => {
+        fn $mname(&self, context: &$crate::processor::RequestContext, $($aname: $aty),*) -> $rrty;
+    }
+}
+
+#[macro_export]
+macro_rules! service_processor_call {
+    (context = false, $handler:expr, $mname:ident($($arg:expr),*), $transport:expr, $name:expr, $ty:expr, $id:expr) => {
+        $handler.$mname($($arg),*)
+    };
+    (context = true, $handler:expr, $mname:ident($($arg:expr),*), $transport:expr, $name:expr, $ty:expr, $id:expr) => {{
+        let context = $crate::processor::RequestContext::new(&*$transport, $name, $ty, $id);
+        $handler.$mname(&context, $($arg),*)
+    }}
+}
+
+#[macro_export]
+macro_rules! service_processor_methods_reply {
+    (oneway = true, $prot:expr, $transport:expr, $mname:expr, $id:expr,
+     $result:expr, $oname:ident, $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {{
+        // Oneway callers never read a reply.
+        let () = $result;
+        Ok(())
+    }};
+    (oneway = false, $prot:expr, $transport:expr, $mname:expr, $id:expr,
+     $result:expr, $oname:ident, $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {{
+        let result = service_processor_methods_translate_return!(
+            $result, $oname, $enname = [$($evname($ename: $ety => $eid),)*]);
+        try!($crate::protocol::helpers::send($prot, $transport, $mname,
+                                             $crate::protocol::MessageType::Reply, &result, $id));
+
+        Ok(())
+    }}
+}
+
 #[macro_export]
 macro_rules! service_processor_methods_translate_return {
     ($result:expr, $oname:ident, $enname:ident = []) => {{
@@ -149,88 +307,398 @@ macro_rules! service_processor_methods_translate_return {
 #[macro_export]
 macro_rules! service_client {
     (client_name = $client_name:ident,
-     service_methods = [$($siname:ident -> $soname:ident = $smfname:ident.$smname:ident($($saname:ident: $saty:ty => $said:expr,)*) -> $srty:ty => $senname:ident = [$($sevname:ident($sename:ident: $sety:ty => $seid:expr),)*] ($srrty:ty),)*],
-     parent_methods = [$($piname:ident -> $poname:ident = $pmfname:ident.$pmname:ident($($paname:ident: $paty:ty => $paid:expr,)*) -> $prty:ty => $penname:ident = [$($pevname:ident($pename:ident: $pety:ty => $peid:expr),)*] ($prrty:ty),)*]) => {
+     service_methods = [$($siname:ident -> $soname:ident = $smfname:ident.$smname:ident($($saname:ident: $saty:ty $(= $sadef:expr)* => $said:expr,)*) -> $srty:ty => $senname:ident = [$($sevname:ident($sename:ident: $sety:ty => $seid:expr),)*] ($srrty:ty) oneway = $soneway:tt, idempotent = $sidempotent:tt, context = $scontext:tt,)*],
+     parent_methods = [$($piname:ident -> $poname:ident = $pmfname:ident.$pmname:ident($($paname:ident: $paty:ty $(= $padef:expr)* => $paid:expr,)*) -> $prty:ty => $penname:ident = [$($pevname:ident($pename:ident: $pety:ty => $peid:expr),)*] ($prrty:ty) oneway = $poneway:tt, idempotent = $pidempotent:tt, context = $pcontext:tt,)*]) => {
         pub struct $client_name<P: $crate::Protocol, T: $crate::Transport> {
             pub protocol: P,
-            pub transport: T
+            pub transport: T,
+            seqid: i32,
+            broken: bool,
+            last_call: Option<$crate::client::LastCall>,
+            timeout: Option<::std::time::Duration>,
+            interceptors: $crate::client::Interceptors
         }
 
         impl<P: $crate::Protocol, T: $crate::Transport> $client_name<P, T> {
             pub fn new(protocol: P, transport: T) -> Self {
                 $client_name {
                     protocol: protocol,
-                    transport: transport
+                    transport: transport,
+                    seqid: 0,
+                    broken: false,
+                    last_call: None,
+                    timeout: None,
+                    interceptors: $crate::client::Interceptors::new()
                 }
             }
 
-            service_client_methods! { methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty),)*] }
-            service_client_methods! { methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty),)*] }
+            /// Fail calls taking longer than `timeout` with `Error::Timeout`,
+            /// which breaks the connection. The transport must support
+            /// timeouts, and its own are replaced.
+            pub fn set_timeout(&mut self, timeout: Option<::std::time::Duration>) {
+                self.timeout = timeout;
+            }
+
+            service_client_methods! { methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty $(= $sadef)* => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty) oneway = $soneway, idempotent = $sidempotent, context = $scontext,)*] }
+            service_client_methods! { methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty $(= $padef)* => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty) oneway = $poneway, idempotent = $pidempotent, context = $pcontext,)*] }
+        }
+
+        impl<P: $crate::Protocol, T: $crate::Transport> $crate::client::Client for $client_name<P, T> {
+            type Protocol = P;
+            type Transport = T;
+
+            fn from_parts(protocol: P, transport: T) -> Self {
+                $client_name::new(protocol, transport)
+            }
+
+            fn is_broken(&self) -> bool { self.broken }
+
+            fn last_call(&self) -> Option<$crate::client::LastCall> { self.last_call }
+
+            fn set_next_seqid(&mut self, seqid: i32) {
+                self.seqid = seqid.wrapping_sub(1);
+            }
+
+            fn set_interceptors(&mut self, interceptors: $crate::client::Interceptors) {
+                self.interceptors = interceptors;
+            }
         }
     }
 }
 
 #[macro_export]
 macro_rules! service_client_methods {
-    (methods = [$($iname:ident -> $oname:ident = $fname:ident.$mname:ident($($aname:ident: $aty:ty => $aid:expr,)*) -> $rty:ty => $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*] ($rrty:ty),)*]) => {
+    (methods = [$($iname:ident -> $oname:ident = $fname:ident.$mname:ident($($aname:ident: $aty:ty $(= $adef:expr)* => $aid:expr,)*) -> $rty:ty => $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*] ($rrty:ty) oneway = $oneway:tt, idempotent = $idempotent:tt, context = $context:tt,)*]) => {
         $(pub fn $mname(&mut self, $($aname: $aty,)*) -> $crate::Result<$rrty> {
             static MNAME: &'static str = stringify!($mname);
 
+            // A late reply to the call that broke the connection could be
+            // taken for the reply to this one.
+            if self.broken {
+                return Err($crate::Error::from(::std::io::Error::new(
+                    ::std::io::ErrorKind::NotConnected, "an earlier call broke the connection")));
+            }
+
+            self.seqid = self.seqid.wrapping_add(1);
+            let seqid = self.seqid;
+
+            let args = {
+                #[allow(unused_mut)]
+                let mut args = $iname::default();
+                $(args.$aname = Some($aname);)*
+                args
+            };
+            let mut call = $crate::client::Call::new(MNAME, service_message_type!(oneway = $oneway), seqid, &args);
+
+            let reply = match self.interceptors.before(&mut call) {
+                Some(reply) => service_client_methods_intercepted! {
+                    oneway = $oneway, reply, $oname
+                },
+                None => {
+                    self.last_call = Some($crate::client::LastCall { method: MNAME, sent: false, idempotent: $idempotent });
+                    let reply = {
+                        let last_call = &mut self.last_call;
+                        let protocol = &mut self.protocol;
+                        let mut transport = $crate::transport::Deadline::new(&mut self.transport, self.timeout);
+
+                        $crate::client::intercept::set_headers(&mut transport, &call.headers, self.timeout)
+                            .and_then(|()| $crate::protocol::helpers::send(&mut *protocol, &mut transport, MNAME,
+                                                                           call.message_type, &args, seqid))
+                            .and_then(|()| {
+                                *last_call = Some($crate::client::LastCall { method: MNAME, sent: true, idempotent: $idempotent });
+                                service_client_methods_receive_reply! {
+                                    oneway = $oneway, &mut *protocol, &mut transport, MNAME, seqid, $oname -> $rty, $enname = [$($evname($ename: $ety => $eid),)*]
+                                }
+                            })
+                    };
+
+                    if let Err(ref e) = reply {
+                        self.broken |= e.breaks_connection();
+                    }
+                    reply
+                }
+            };
+
+            if !self.interceptors.is_empty() {
+                self.interceptors.after(&call, match reply {
+                    Ok((Some(ref result), _)) => Ok(Some(result)),
+                    Ok((None, _)) => Ok(None),
+                    Err(ref e) => Err(e),
+                });
+            }
+
+            reply.and_then(|(result, unknown)| service_client_methods_translate_reply! {
+                oneway = $oneway, result, unknown, MNAME, $rty, $enname = [$($evname($ename: $ety => $eid),)*]
+            })
+        })*
+    }
+}
+
+/// The async client, which only exists with the `nonblocking` feature.
+#[cfg(feature = "nonblocking")]
+#[macro_export]
+macro_rules! service_async_client {
+    (trait_name = $name:ident,
+     client_name = $client_name:ident,
+     service_methods = [$($siname:ident -> $soname:ident = $smfname:ident.$smname:ident($($saname:ident: $saty:ty $(= $sadef:expr)* => $said:expr,)*) -> $srty:ty => $senname:ident = [$($sevname:ident($sename:ident: $sety:ty => $seid:expr),)*] ($srrty:ty) oneway = $soneway:tt, idempotent = $sidempotent:tt, context = $scontext:tt,)*],
+     parent_methods = [$($piname:ident -> $poname:ident = $pmfname:ident.$pmname:ident($($paname:ident: $paty:ty $(= $padef:expr)* => $paid:expr,)*) -> $prty:ty => $penname:ident = [$($pevname:ident($pename:ident: $pety:ty => $peid:expr),)*] ($prrty:ty) oneway = $poneway:tt, idempotent = $pidempotent:tt, context = $pcontext:tt,)*]) => {
+        /// A client for tokio transports. The server must use framed
+        /// messages, like `nonblocking::Server` does.
+        ///
+        /// Dropping a call before it completes, for instance when it is
+        /// cancelled by a timeout, leaves the connection halfway through a
+        /// message. The client is then broken, and later calls fail with
+        /// `NotConnected`.
+        pub struct $client_name<P: $crate::Protocol, T: $crate::nonblocking::AsyncTransport> {
+            pub protocol: P,
+            pub transport: T,
+            seqid: i32,
+            broken: bool
+        }
+
+        impl<P: $crate::Protocol, T: $crate::nonblocking::AsyncTransport> $client_name<P, T> {
+            pub fn new(protocol: P, transport: T) -> Self {
+                $client_name {
+                    protocol: protocol,
+                    transport: transport,
+                    seqid: 0,
+                    broken: false
+                }
+            }
+
+            /// Whether an earlier call was dropped before completing, or
+            /// failed in a way described by `Error::breaks_connection`. The
+            /// client should then be discarded.
+            pub fn is_broken(&self) -> bool { self.broken }
+
+            service_async_client_methods! { methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty $(= $sadef)* => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty) oneway = $soneway, idempotent = $sidempotent, context = $scontext,)*] }
+            service_async_client_methods! { methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty $(= $padef)* => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty) oneway = $poneway, idempotent = $pidempotent, context = $pcontext,)*] }
+        }
+
+        impl<P: $crate::Protocol, T: $crate::nonblocking::AsyncTransport> $crate::reflect::ThriftService for $client_name<P, T> {
+            fn descriptor() -> &'static $crate::reflect::ServiceDescriptor {
+                <dyn $name as $crate::reflect::ThriftService>::descriptor()
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "nonblocking"))]
+#[macro_export]
+macro_rules! service_async_client {
+    ($($tokens:tt)*) => {};
+}
+
+#[macro_export]
+macro_rules! service_async_client_methods {
+    (methods = [$($iname:ident -> $oname:ident = $fname:ident.$mname:ident($($aname:ident: $aty:ty $(= $adef:expr)* => $aid:expr,)*) -> $rty:ty => $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*] ($rrty:ty) oneway = $oneway:tt, idempotent = $idempotent:tt, context = $context:tt,)*]) => {
+        $(pub fn $mname<'a>(&'a mut self, $($aname: $aty,)*) -> impl ::std::future::Future<Output = $crate::Result<$rrty>> + 'a {
+            static MNAME: &'static str = stringify!($mname);
+
+            self.seqid = self.seqid.wrapping_add(1);
+            let seqid = self.seqid;
+
             let mut args = $iname::default();
             $(args.$aname = Some($aname);)*
-            try!($crate::protocol::helpers::send(&mut self.protocol, &mut self.transport,
-                                                 MNAME, $crate::protocol::MessageType::Call, &mut args, 0));
-
-            let mut result = $oname::default();
-            try!($crate::protocol::helpers::receive(&mut self.protocol, &mut self.transport,
-                                                    MNAME, &mut result));
+            let mut request = $crate::transport::BufferTransport::default();
+            let sent = $crate::protocol::helpers::send(&mut self.protocol, &mut request,
+                                                       MNAME, service_message_type!(oneway = $oneway), &mut args, seqid);
 
-            let result = service_client_methods_translate_result!(
-                result, $enname = [$($evname($ename: $ety => $eid),)*]);
-            Ok(result)
+            $crate::nonblocking::Call::new(&mut self.protocol, &mut self.transport, &mut self.broken,
+                                           sent.map(|()| request.into_output()), $oneway,
+                                           move |_protocol: &mut P, _reply: &mut $crate::transport::BufferTransport| -> $crate::Result<$rrty> {
+                service_client_methods_receive! {
+                    oneway = $oneway, _protocol, _reply, MNAME, seqid, $oname -> $rty, $enname = [$($evname($ename: $ety => $eid),)*]
+                }
+            })
         })*
     }
 }
 
 #[macro_export]
-macro_rules! service_client_methods_translate_result {
-    ($result:expr, $enname:ident = []) => {{
-        use $crate::protocol::Encode;
+macro_rules! service_message_type {
+    (oneway = true) => { $crate::protocol::MessageType::Oneway };
+    (oneway = false) => { $crate::protocol::MessageType::Call };
+}
+
+#[macro_export]
+macro_rules! service_client_methods_receive {
+    (oneway = true, $protocol:expr, $transport:expr, $mname:expr, $seqid:expr, $oname:ident -> $rty:ty,
+     $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {
+        // The server does not reply to oneway calls.
+        Ok(())
+    };
+    (oneway = false, $protocol:expr, $transport:expr, $mname:expr, $seqid:expr, $oname:ident -> $rty:ty,
+     $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {{
+        let (result, unknown) = try!(service_client_methods_receive_reply! {
+            oneway = false, $protocol, $transport, $mname, $seqid, $oname -> $rty, $enname = [$($evname($ename: $ety => $eid),)*]
+        });
+        service_client_methods_translate_reply!(
+            oneway = false, result, unknown, $mname, $rty, $enname = [$($evname($ename: $ety => $eid),)*])
+    }}
+}
+
+/// Receive the result struct of a reply, if the call has one, and the ids of
+/// the fields in it that this version of the IDL does not know about.
+#[macro_export]
+macro_rules! service_client_methods_receive_reply {
+    (oneway = true, $protocol:expr, $transport:expr, $mname:expr, $seqid:expr, $oname:ident -> $rty:ty,
+     $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {{
+        let result: $crate::Result<(Option<$oname>, Vec<i16>)> = Ok((None, Vec::new()));
+        result
+    }};
+    (oneway = false, $protocol:expr, $transport:expr, $mname:expr, $seqid:expr, $oname:ident -> $rty:ty,
+     $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {{
+        let mut result = $oname::default();
 
+        // The result struct is decoded by hand to find out which fields the
+        // server sent that this version of the IDL does not know about.
+        $crate::protocol::helpers::receive_reply(
+            $protocol, $transport, $mname, $seqid, |protocol, transport, typ, id| {
+                if (typ, id) == ($crate::protocol::helpers::typ::<$rty>(), 0) {
+                    try!($crate::protocol::Decode::decode(&mut result.success, protocol, transport));
+                    Ok(true)
+                } $(else if (typ, id) == ($crate::protocol::helpers::typ::<$ety>(), $eid) {
+                    try!($crate::protocol::Decode::decode(&mut result.$ename, protocol, transport));
+                    Ok(true)
+                })* else {
+                    Ok(false)
+                }
+            }).map(|unknown| (Some(result), unknown))
+    }}
+}
+
+/// Decode a reply an interceptor answered a call with.
+#[macro_export]
+macro_rules! service_client_methods_intercepted {
+    (oneway = true, $reply:expr, $oname:ident) => {{
+        let reply: $crate::Result<$crate::protocol::Value> = $reply;
+        reply.map(|_| (None, Vec::new()))
+    }};
+    (oneway = false, $reply:expr, $oname:ident) => {{
+        let reply: $crate::Result<$crate::protocol::Value> = $reply;
+        reply.and_then(|value| value.decode::<$oname>()).map(|result| (Some(result), Vec::new()))
+    }}
+}
+
+#[macro_export]
+macro_rules! service_client_methods_translate_reply {
+    (oneway = true, $result:expr, $unknown:expr, $mname:expr, $rty:ty,
+     $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {{
+        let _ = ($result, $unknown);
+        Ok(())
+    }};
+    (oneway = false, $result:expr, $unknown:expr, $mname:expr, $rty:ty,
+     $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {
+        service_client_methods_translate_result!(
+            $result.unwrap_or_default(), $unknown, $mname, $rty, $enname = [$($evname($ename: $ety => $eid),)*])
+    }
+}
+
+#[macro_export]
+macro_rules! service_client_methods_translate_result {
+    ($result:expr, $unknown:expr, $mname:expr, $rty:ty, $enname:ident = []) => {{
         let result = $result;
 
-        if result.success.should_encode() {
-            result.success.unwrap()
-        } else {
-            result.success.unwrap_or_default()
+        match result.success {
+            Some(s) => Ok(s),
+            None => service_client_methods_missing_result!($unknown, $mname, $rty)
         }
     }};
-    ($result:expr, $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {{
+    ($result:expr, $unknown:expr, $mname:expr, $rty:ty, $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {{
         let result = $result;
         if let Some(s) = result.success {
-            Ok(s)
+            Ok(Ok(s))
         }
         $(
             else if let Some(e) = result.$ename {
-                Err($enname::$evname(e))
+                Ok(Err($enname::$evname(e)))
             }
         )*
         else {
-            // TODO investigate this
-            unreachable!()
+            service_client_methods_missing_result!($unknown, $mname, $rty).map(Ok)
         }
     }}
 }
 
+#[macro_export]
+macro_rules! service_client_methods_missing_result {
+    ($unknown:expr, $mname:expr, $rty:ty) => {{
+        let unknown: Vec<i16> = $unknown;
+        let result: $crate::Result<$rty>;
+
+        if !unknown.is_empty() {
+            result = Err($crate::Error::UnknownException { method: String::from($mname), field_ids: unknown });
+        } else if $crate::protocol::helpers::typ::<$rty>() == $crate::protocol::Type::Void {
+            // Void methods send back an empty result struct.
+            result = Ok(Default::default());
+        } else {
+            result = Err($crate::Error::MissingResult(String::from($mname)));
+        }
+
+        result
+    }}
+}
+
 #[macro_export]
 macro_rules! strukt {
-    (name = $name:ident,
-     fields = { $($fname:ident: $fty:ty => $id:expr,)+ }) => {
-        #[derive(Debug, Clone, Default, Eq, PartialEq, PartialOrd, Ord)]
-        pub struct $name {
-            $(pub $fname: $fty,)+
+    (name = $name:ident, builder = $builder:ident, $(unknown_fields = $unknown:tt,)? fields = { $($fields:tt)* }) => {
+        strukt! { name = $name, $(unknown_fields = $unknown,)? fields = { $($fields)* } }
+        strukt_builder! { @fields $name, $builder, [] $($fields)* }
+    };
+    (name = $name:ident, fields = { $($fields:tt)* }) => {
+        strukt! { @struct $name, false, { $($fields)* } }
+    };
+    (name = $name:ident, unknown_fields = $unknown:tt, fields = { $($fields:tt)* }) => {
+        strukt! { @struct $name, $unknown, { $($fields)* } }
+    };
+    (@struct $name:ident, false, {}) => {
+        #[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name;
+
+        impl $crate::protocol::ThriftTyped for $name {
+            fn typ(&self) -> $crate::protocol::Type { $crate::protocol::Type::Struct }
+        }
+
+        impl $crate::protocol::Encode for $name {
+            fn encode<P, T>(&self, protocol: &mut P, transport: &mut T) -> $crate::Result<()>
+            where P: $crate::Protocol, T: $crate::Transport {
+                #[allow(unused_imports)]
+                use $crate::Protocol;
+
+                try!(protocol.write_struct_begin(transport, stringify!($name)));
+                try!(protocol.write_field_stop(transport));
+                try!(protocol.write_struct_end(transport));
+
+                Ok(())
+            }
         }
 
+        impl $crate::protocol::Decode for $name {
+            fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> $crate::Result<()>
+            where P: $crate::Protocol, T: $crate::Transport {
+                #[allow(unused_imports)]
+                use $crate::Protocol;
+
+                try!(protocol.read_struct_begin(transport));
+
+                let (_, ty, _) = try!(protocol.read_field_begin(transport));
+                if ty != $crate::protocol::Type::Stop {
+                     return Err($crate::Error::from($crate::protocol::Error::ProtocolViolation))
+                }
+
+                try!(protocol.read_struct_end(transport));
+
+                Ok(())
+            }
+        }
+
+        strukt_reflect! { $name, {} }
+    };
+    (@struct $name:ident, $unknown:tt,
+     { $($(#[$fattr:ident])* $fname:ident: $fty:ty $(= $fdef:expr)? => $id:expr,)* }) => {
+        strukt_definition! { $unknown, $name, { $($fname: $fty $(= $fdef)?,)* } }
+
         impl $crate::protocol::ThriftTyped for $name {
             fn typ(&self) -> $crate::protocol::Type { $crate::protocol::Type::Struct }
         }
@@ -245,13 +713,18 @@ macro_rules! strukt {
 
                 try!(protocol.write_struct_begin(transport, stringify!($name)));
 
-                $(if $crate::protocol::Encode::should_encode(&self.$fname) {
+                strukt_encode_unknown!(@begin $unknown, written);
+
+                $(strukt_encode_unknown!($unknown, self, protocol, transport, written, Some($id));
+                if $crate::protocol::Encode::should_encode(&self.$fname) {
                     try!(protocol.write_field_begin(transport, stringify!($fname),
                                                     $crate::protocol::helpers::typ::<$fty>(), $id));
                     try!($crate::protocol::Encode::encode(&self.$fname, protocol, transport));
                     try!(protocol.write_field_end(transport));
                 })*
 
+                strukt_encode_unknown!($unknown, self, protocol, transport, written, None);
+
                 try!(protocol.write_field_stop(transport));
                 try!(protocol.write_struct_end(transport));
 
@@ -269,6 +742,8 @@ macro_rules! strukt {
 
                 try!(protocol.read_struct_begin(transport));
 
+                strukt_decode_unknown!(@begin $unknown, self);
+
                 loop {
                     let (_, typ, id) = try!(protocol.read_field_begin(transport));
 
@@ -277,7 +752,18 @@ macro_rules! strukt {
                     } $(else if (typ, id) == ($crate::protocol::helpers::typ::<$fty>(), $id) {
                         try!($crate::protocol::Decode::decode(&mut self.$fname, protocol, transport));
                     })* else {
-                        try!(protocol.skip(transport, typ));
+                        if protocol.strict_fields() {
+                            $(if id == $id {
+                                return Err($crate::Error::from($crate::protocol::Error::FieldTypeMismatch {
+                                    strukt: stringify!($name),
+                                    field: stringify!($fname),
+                                    expected: $crate::protocol::helpers::typ::<$fty>(),
+                                    actual: typ,
+                                }));
+                            })*
+                        }
+
+                        strukt_decode_unknown!($unknown, self, protocol, transport, typ, id);
                     }
 
                     try!(protocol.read_field_end(transport));
@@ -288,61 +774,283 @@ macro_rules! strukt {
                 Ok(())
             }
         }
+
+        strukt_reflect! { $name, { $($(#[$fattr])* $fname: $fty => $id,)* } }
     };
-    (name = $name:ident, fields = {}) => {
-        #[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
-        pub struct $name;
 
-        impl $crate::protocol::ThriftTyped for $name {
-            fn typ(&self) -> $crate::protocol::Type { $crate::protocol::Type::Struct }
+}
+
+#[macro_export]
+macro_rules! strukt_reflect {
+    ($name:ident, { $($(#[$fattr:ident])* $fname:ident: $fty:ty => $id:expr,)* }) => {
+        impl $crate::reflect::ThriftStruct for $name {
+            fn descriptor() -> &'static $crate::reflect::StructDescriptor {
+                static DESCRIPTOR: $crate::reflect::StructDescriptor = $crate::reflect::StructDescriptor {
+                    name: stringify!($name),
+                    fields: &[$(reflect_field!($(#[$fattr])* $fname: $fty => $id),)*],
+                };
+
+                &DESCRIPTOR
+            }
         }
 
-        impl $crate::protocol::Encode for $name {
-            fn encode<P, T>(&self, protocol: &mut P, transport: &mut T) -> $crate::Result<()>
-            where P: $crate::Protocol, T: $crate::Transport {
-                #[allow(unused_imports)]
-                use $crate::Protocol;
+        impl $crate::reflect::Reflect for $name {
+            const TYPE: $crate::reflect::TypeDescriptor =
+                $crate::reflect::TypeDescriptor::Struct(<$name as $crate::reflect::ThriftStruct>::descriptor);
+        }
+    }
+}
 
-                try!(protocol.write_struct_begin(transport, stringify!($name)));
-                try!(protocol.write_field_stop(transport));
-                try!(protocol.write_struct_end(transport));
+#[macro_export]
+macro_rules! reflect_field {
+    (#[required] $fname:ident: $fty:ty => $id:expr) => {
+        reflect_field!(@field $fname: $fty => $id, $crate::reflect::Requiredness::Required)
+    };
+    ($fname:ident: $fty:ty => $id:expr) => {
+        reflect_field!(@field $fname: $fty => $id, <$fty as $crate::reflect::Reflect>::REQUIREDNESS)
+    };
+    (@field $fname:ident: $fty:ty => $id:expr, $requiredness:expr) => {
+        $crate::reflect::FieldDescriptor {
+            id: $id,
+            name: stringify!($fname),
+            typ: <$fty as $crate::reflect::Reflect>::TYPE.typ(),
+            requiredness: $requiredness,
+            ty: <$fty as $crate::reflect::Reflect>::TYPE,
+        }
+    };
+}
 
-                Ok(())
+#[macro_export]
+macro_rules! strukt_definition {
+    (false, $name:ident, { $($fname:ident: $fty:ty $(= $fdef:expr)?,)* }) => {
+        #[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord)]
+        pub struct $name {
+            $(pub $fname: $fty,)*
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                $name {
+                    $($fname: strukt_field_default!($($fdef)?),)*
+                }
             }
         }
+    };
+    (true, $name:ident, { $($fname:ident: $fty:ty $(= $fdef:expr)?,)* }) => {
+        #[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord)]
+        pub struct $name {
+            $(pub $fname: $fty,)*
+            /// Fields that are not in this version of the IDL, written back
+            /// out between the known fields by field id.
+            #[doc(hidden)]
+            pub unknown_fields: $crate::protocol::UnknownFields,
+        }
 
-        impl $crate::protocol::Decode for $name {
-            fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> $crate::Result<()>
-            where P: $crate::Protocol, T: $crate::Transport {
-                #[allow(unused_imports)]
-                use $crate::Protocol;
+        impl Default for $name {
+            fn default() -> Self {
+                $name {
+                    $($fname: strukt_field_default!($($fdef)?),)*
+                    unknown_fields: Default::default(),
+                }
+            }
+        }
+    };
+}
 
-                try!(protocol.read_struct_begin(transport));
+/// The default value of a struct field: its default value from the IDL, if
+/// it has one, or that of its type.
+#[macro_export]
+macro_rules! strukt_field_default {
+    () => { Default::default() };
+    ($default:expr) => { $default };
+}
 
-                let (_, ty, _) = try!(protocol.read_field_begin(transport));
-                if ty != $crate::protocol::Type::Stop {
-                     return Err($crate::Error::from($crate::protocol::Error::ProtocolViolation))
-                }
+#[macro_export]
+macro_rules! strukt_encode_unknown {
+    (@begin false, $written:ident) => {};
+    (@begin true, $written:ident) => {
+        let mut $written = 0;
+    };
+    (false, $this:ident, $protocol:expr, $transport:expr, $written:ident, $id:expr) => {};
+    (true, $this:ident, $protocol:expr, $transport:expr, $written:ident, $id:expr) => {
+        try!($this.unknown_fields.write_before($protocol, $transport, &mut $written, $id));
+    };
+}
 
-                try!(protocol.read_struct_end(transport));
+#[macro_export]
+macro_rules! strukt_decode_unknown {
+    (@begin false, $this:ident) => {};
+    (@begin true, $this:ident) => {
+        $this.unknown_fields.clear();
+    };
+    (false, $this:ident, $protocol:expr, $transport:expr, $typ:expr, $id:expr) => {
+        try!($protocol.skip($transport, $typ));
+    };
+    (true, $this:ident, $protocol:expr, $transport:expr, $typ:expr, $id:expr) => {
+        try!($this.unknown_fields.read_field($protocol, $transport, $typ, $id));
+    };
+}
 
-                Ok(())
+/// Generates a builder for a struct declared with `strukt!`. Fields of type
+/// `Option<T>` are optional and fields marked `#[required]` are required by
+/// `build`. All others keep their default value, from the IDL if it declares
+/// one, unless they are set.
+#[macro_export]
+macro_rules! strukt_builder {
+    (@fields $name:ident, $builder:ident, [$($done:tt)*] #[required] $fname:ident: $fty:ty $(= $fdef:expr)? => $id:expr, $($rest:tt)*) => {
+        strukt_builder! { @fields $name, $builder, [$($done)* ($fname: $fty, required)] $($rest)* }
+    };
+    (@fields $name:ident, $builder:ident, [$($done:tt)*] $fname:ident: Option<$fty:ty> $(= $fdef:expr)? => $id:expr, $($rest:tt)*) => {
+        strukt_builder! { @fields $name, $builder, [$($done)* ($fname: $fty, optional)] $($rest)* }
+    };
+    (@fields $name:ident, $builder:ident, [$($done:tt)*] $fname:ident: $fty:ty $(= $fdef:expr)? => $id:expr, $($rest:tt)*) => {
+        strukt_builder! { @fields $name, $builder, [$($done)* ($fname: $fty, default)] $($rest)* }
+    };
+    (@fields $name:ident, $builder:ident, [$(($fname:ident: $fty:ty, $req:ident))*]) => {
+        #[derive(Debug, Clone, Default)]
+        pub struct $builder {
+            $($fname: Option<$fty>,)*
+        }
+
+        impl $builder {
+            $(pub fn $fname<V: Into<$fty>>(mut self, value: V) -> Self {
+                self.$fname = Some(value.into());
+                self
+            })*
+
+            #[allow(unused_mut)]
+            pub fn build(self) -> ::std::result::Result<$name, $crate::BuildError> {
+                let mut built = $name::default();
+                $(strukt_builder!(@set $req, built.$fname, self.$fname, $name, $fname);)*
+                Ok(built)
+            }
+        }
+
+        impl $name {
+            pub fn builder() -> $builder {
+                $builder::default()
+            }
+        }
+    };
+    (@set optional, $field:expr, $value:expr, $name:ident, $fname:ident) => { $field = $value; };
+    (@set default, $field:expr, $value:expr, $name:ident, $fname:ident) => {
+        if let Some(value) = $value {
+            $field = value;
+        }
+    };
+    (@set required, $field:expr, $value:expr, $name:ident, $fname:ident) => {
+        $field = match $value {
+            Some(value) => value,
+            None => return Err($crate::BuildError::MissingField {
+                strukt: stringify!($name),
+                field: stringify!($fname),
+            }),
+        };
+    };
+}
+
+/// Declares a Thrift exception: a `strukt!` that also implements `Display`
+/// and `std::error::Error`, so it can be used with `?` and boxed as a
+/// `thrift::Error::UserException`.
+#[macro_export]
+macro_rules! exception {
+    (name = $name:ident, builder = $builder:ident, $(unknown_fields = $unknown:tt,)? fields = { $($fields:tt)* }) => {
+        exception! { name = $name, $(unknown_fields = $unknown,)? fields = { $($fields)* } }
+        strukt_builder! { @fields $name, $builder, [] $($fields)* }
+    };
+    (name = $name:ident, $(unknown_fields = $unknown:tt,)? fields = { $($fields:tt)* }) => {
+        strukt! { name = $name, $(unknown_fields = $unknown,)? fields = { $($fields)* } }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                try!(write!(f, "{}(", stringify!($name)));
+                let mut first = true;
+                exception_display_fields! { self, f, first, $($fields)* }
+                write!(f, ")")
+            }
+        }
+
+        impl ::std::error::Error for $name {}
+
+        impl From<$name> for $crate::Error {
+            fn from(e: $name) -> $crate::Error {
+                $crate::Error::UserException(Box::new(e))
             }
         }
     }
 }
 
+/// Writes the fields of an exception that are set, like `name: value`.
+#[macro_export]
+macro_rules! exception_display_fields {
+    ($this:ident, $f:ident, $first:ident,) => {
+        let _ = $first;
+    };
+    ($this:ident, $f:ident, $first:ident, #[$fattr:ident] $($rest:tt)*) => {
+        exception_display_fields! { $this, $f, $first, $($rest)* }
+    };
+    ($this:ident, $f:ident, $first:ident, $fname:ident: Option<$fty:ty> $(= $fdef:expr)? => $id:expr, $($rest:tt)*) => {
+        if let Some(ref value) = $this.$fname {
+            try!(write!($f, "{}{}: {:?}", if $first { "" } else { ", " }, stringify!($fname), value));
+            $first = false;
+        }
+        exception_display_fields! { $this, $f, $first, $($rest)* }
+    };
+    ($this:ident, $f:ident, $first:ident, $fname:ident: $fty:ty $(= $fdef:expr)? => $id:expr, $($rest:tt)*) => {
+        try!(write!($f, "{}{}: {:?}", if $first { "" } else { ", " }, stringify!($fname), $this.$fname));
+        $first = false;
+        exception_display_fields! { $this, $f, $first, $($rest)* }
+    };
+}
+
+/// Declares IDL constants. Values that can be built in a constant expression
+/// are declared with `const`, all others (lists, sets, maps and structs) with
+/// `static` and are built lazily on first access.
+#[macro_export]
+macro_rules! consts {
+    () => {};
+    (const $name:ident: $ty:ty = $val:expr; $($rest:tt)*) => {
+        pub const $name: $ty = $val;
+
+        consts! { $($rest)* }
+    };
+    (static $name:ident: $ty:ty = $val:expr; $($rest:tt)*) => {
+        $crate::rt::lazy_static! {
+            pub static ref $name: $ty = $val;
+        }
+
+        consts! { $($rest)* }
+    }
+}
+
 #[macro_export]
 macro_rules! enom {
     (name = $name:ident,
-     values = [$($vname:ident = $val:expr,)*],
+     values = [$($vname:ident = $val:expr $(=> $idl:expr)?,)*],
      default = $dname:ident) => {
-        #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+        #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
         #[repr(i32)]
         pub enum $name {
             $($vname = $val),*
         }
 
+        enom_impls! {
+            name = $name,
+            values = [$($vname = $val => enom_name!($vname $(, $idl)?),)*],
+            unknown = []
+        }
+
+        impl ::std::convert::TryFrom<i32> for $name {
+            type Error = $crate::EnumError;
+
+            fn try_from(num: i32) -> ::std::result::Result<Self, $crate::EnumError> {
+                match num {
+                    $($val => Ok($name::$vname),)*
+                    _ => Err($crate::EnumError::UnknownValue { enom: stringify!($name), value: num })
+                }
+            }
+        }
+
         impl Default for $name {
             fn default() -> Self { $name::$dname }
         }
@@ -370,6 +1078,67 @@ macro_rules! enom {
             }
         }
 
+        impl $crate::protocol::Decode for $name {
+            fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> $crate::Result<()>
+            where P: $crate::Protocol, T: $crate::Transport {
+                *self = try!($crate::protocol::helpers::read_enum(protocol, transport));
+                Ok(())
+            }
+        }
+    };
+    (name = $name:ident,
+     values = [$($vname:ident = $val:expr $(=> $idl:expr)?,)*],
+     unknown = $uname:ident,
+     default = $dname:ident) => {
+        // `From<i32>` and decoding only make the unknown variant for values
+        // the IDL does not declare, so comparing variants compares values.
+        #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+        pub enum $name {
+            $($vname,)*
+            $uname(i32)
+        }
+
+        enom_impls! {
+            name = $name,
+            values = [$($vname = $val => enom_name!($vname $(, $idl)?),)*],
+            unknown = [$uname]
+        }
+
+        impl From<i32> for $name {
+            fn from(num: i32) -> Self {
+                match num {
+                    $($val => $name::$vname,)*
+                    _ => $name::$uname(num)
+                }
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self { $name::$dname }
+        }
+
+        impl $crate::protocol::FromNum for $name {
+            fn from_num(num: i32) -> Option<Self> {
+                // Keep values this version of the IDL does not know about so
+                // they can be encoded again.
+                Some($name::from(num))
+            }
+        }
+
+        impl $crate::protocol::ThriftTyped for $name {
+            fn typ(&self) -> $crate::protocol::Type { $crate::protocol::Type::I32 }
+        }
+
+        impl $crate::protocol::Encode for $name {
+            fn encode<P, T>(&self, protocol: &mut P, transport: &mut T) -> $crate::Result<()>
+            where P: $crate::Protocol, T: $crate::Transport {
+                #[allow(unused_imports)]
+                use $crate::Protocol;
+
+                protocol.write_i32(transport, self.as_i32())
+            }
+        }
+
         impl $crate::protocol::Decode for $name {
             fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> $crate::Result<()>
             where P: $crate::Protocol, T: $crate::Transport {
@@ -380,3 +1149,93 @@ macro_rules! enom {
     }
 }
 
+/// Conversions shared by both kinds of `enom!`. Names are the IDL names,
+/// which default to the variant names.
+#[macro_export]
+macro_rules! enom_impls {
+    (name = $name:ident,
+     values = [$($vname:ident = $val:expr => $idl:expr,)*],
+     unknown = [$($uname:ident)*]) => {
+        impl $name {
+            /// Every value the IDL declares, in declaration order.
+            pub const ALL: &'static [$name] = &[$($name::$vname),*];
+
+            pub fn values() -> &'static [$name] {
+                $name::ALL
+            }
+
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    $($name::$vname => $idl,)*
+                    $($name::$uname(_) => stringify!($uname),)*
+                }
+            }
+
+            pub fn as_i32(&self) -> i32 {
+                match *self {
+                    $($name::$vname => $val,)*
+                    $($name::$uname(num) => num,)*
+                }
+            }
+        }
+
+        impl From<$name> for i32 {
+            fn from(value: $name) -> i32 {
+                value.as_i32()
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                match *self {
+                    $($name::$uname(num) => write!(f, "{}({})", stringify!($uname), num),)*
+                    #[allow(unreachable_patterns)]
+                    _ => f.write_str(self.name()),
+                }
+            }
+        }
+
+        impl ::std::str::FromStr for $name {
+            type Err = $crate::EnumError;
+
+            fn from_str(name: &str) -> ::std::result::Result<Self, $crate::EnumError> {
+                $(if name == $idl {
+                    return Ok($name::$vname);
+                })*
+
+                // Accept the `Display` form of unknown values too.
+                $(let prefix = concat!(stringify!($uname), "(");
+                if name.starts_with(prefix) && name.ends_with(')') {
+                    if let Ok(num) = name[prefix.len()..name.len() - 1].parse::<i32>() {
+                        return Ok($name::from(num));
+                    }
+                })*
+
+                Err($crate::EnumError::UnknownName { enom: stringify!($name), name: name.to_owned() })
+            }
+        }
+
+        impl $crate::reflect::ThriftEnum for $name {
+            fn descriptor() -> &'static $crate::reflect::EnumDescriptor {
+                static DESCRIPTOR: $crate::reflect::EnumDescriptor = $crate::reflect::EnumDescriptor {
+                    name: stringify!($name),
+                    values: &[$(($idl, $val),)*],
+                };
+
+                &DESCRIPTOR
+            }
+        }
+
+        impl $crate::reflect::Reflect for $name {
+            const TYPE: $crate::reflect::TypeDescriptor =
+                $crate::reflect::TypeDescriptor::Enum(<$name as $crate::reflect::ThriftEnum>::descriptor);
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! enom_name {
+    ($vname:ident) => { stringify!($vname) };
+    ($vname:ident, $idl:expr) => { $idl };
+}
+
@@ -34,10 +34,12 @@ service! {
     trait_name = SharedService,
     processor_name = SharedServiceProcessor,
     client_name = SharedServiceClient,
+    async_client_name = SharedServiceAsyncClient,
     service_methods = [
-        SharedServiceGetStructArgs -> SharedServiceGetStructResult = shared.get_struct(key: i32 => 1,) -> DeeplyNested => SharedServiceGetStructError = [] (DeeplyNested),
+        SharedServiceGetStructArgs -> SharedServiceGetStructResult = shared.get_struct(key: i32 => 1,) -> DeeplyNested => SharedServiceGetStructError = [] (DeeplyNested) oneway = false, idempotent = false, context = false,
     ],
     parent_methods = [],
+    extends = [],
     bounds = [S: SharedService,],
     fields = [shared: S,]
 }
@@ -46,20 +48,22 @@ service! {
      trait_name = ChildService,
      processor_name = ChildServiceProcessor,
      client_name = ChildServiceClient,
+     async_client_name = ChildServiceAsyncClient,
      service_methods = [
          ChildServiceOperationArgs -> ChildServiceOperationResult = child.operation(
              one: String => 2,
              another: i32 => 3,
-         ) -> Operation => ChildServiceOperationError = [] (Operation),
+         ) -> Operation => ChildServiceOperationError = [] (Operation) oneway = false, idempotent = false, context = false,
      ],
      parent_methods = [
-        SharedServiceGetStructArgs -> SharedServiceGetStructResult = shared.get_struct(key: i32 => 1,) -> DeeplyNested => SharedServiceGetStructError = [] (DeeplyNested),
+        SharedServiceGetStructArgs -> SharedServiceGetStructResult = shared.get_struct(key: i32 => 1,) -> DeeplyNested => SharedServiceGetStructError = [] (DeeplyNested) oneway = false, idempotent = false, context = false,
      ],
+     extends = [SharedService],
      bounds = [S: SharedService, C: ChildService,],
      fields = [shared: S, child: C,]
 }
 
-strukt! {
+exception! {
      name = Exception,
      fields = {
           name: String => 0,
@@ -71,10 +75,12 @@ service! {
     trait_name = ServiceWithException,
     processor_name = ServiceWithExceptionProcessor,
     client_name = ServiceWithExceptionClient,
+    async_client_name = ServiceWithExceptionAsyncClient,
     service_methods = [
-        ServiceWithExceptionOperationArgs -> ServiceWithExceptionOperationResult = this.operation() -> i32 => ServiceWithExceptionOperationError = [Bad(bad: Exception => 1),] (Result<i32, ServiceWithExceptionOperationError>),
+        ServiceWithExceptionOperationArgs -> ServiceWithExceptionOperationResult = this.operation() -> i32 => ServiceWithExceptionOperationError = [Bad(bad: Exception => 1),] (Result<i32, ServiceWithExceptionOperationError>) oneway = false, idempotent = false, context = false,
     ],
     parent_methods = [],
+    extends = [],
     bounds = [S: ServiceWithException,],
     fields = [this: S,]
 }
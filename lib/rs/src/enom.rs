@@ -0,0 +1,42 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::fmt;
+use std::error::Error as StdError;
+
+/// Why a name or number could not be converted to an enum declared with
+/// `enom!`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EnumError {
+    /// The enum declares no value with this name.
+    UnknownName { enom: &'static str, name: String },
+    /// The enum declares no value with this number.
+    UnknownValue { enom: &'static str, value: i32 },
+}
+
+impl fmt::Display for EnumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EnumError::UnknownName { enom, ref name } => write!(f, "{} has no value named {:?}", enom, name),
+            EnumError::UnknownValue { enom, value } => write!(f, "{} has no value {}", enom, value),
+        }
+    }
+}
+
+impl StdError for EnumError {}
@@ -0,0 +1,69 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::fmt;
+use std::error::Error as StdError;
+
+enom! {
+    name = ApplicationExceptionKind,
+    values = [
+        Unknown = 0,
+        UnknownMethod = 1,
+        InvalidMessageType = 2,
+        WrongMethodName = 3,
+        BadSequenceId = 4,
+        MissingResult = 5,
+        InternalError = 6,
+        ProtocolError = 7,
+        InvalidTransform = 8,
+        InvalidProtocol = 9,
+        UnsupportedClientType = 10,
+    ],
+    default = Unknown
+}
+
+// The layout of `TApplicationException`, shared by all Thrift implementations.
+strukt! {
+    name = ApplicationException,
+    fields = {
+        message: Option<String> => 1,
+        kind: Option<ApplicationExceptionKind> => 2,
+    }
+}
+
+impl ApplicationException {
+    pub fn new<S: Into<String>>(kind: ApplicationExceptionKind, message: S) -> ApplicationException {
+        ApplicationException {
+            message: Some(message.into()),
+            kind: Some(kind)
+        }
+    }
+}
+
+impl fmt::Display for ApplicationException {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(fmt::Debug::fmt(&self.kind.unwrap_or_default(), f));
+        match self.message {
+            Some(ref message) => write!(f, ": {}", message),
+            None => Ok(())
+        }
+    }
+}
+
+impl StdError for ApplicationException {}
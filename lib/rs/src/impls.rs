@@ -3,6 +3,8 @@ pub use {Protocol, Transport, Result, Error};
 
 pub use std::collections::{BTreeSet, BTreeMap};
 
+use ordered_float::OrderedFloat;
+
 use protocol::helpers::typ;
 
 impl ThriftTyped for bool { fn typ(&self) -> Type { Type::Bool } }
@@ -11,6 +13,7 @@ impl ThriftTyped for i16 { fn typ(&self) -> Type { Type::I16 } }
 impl ThriftTyped for i32 { fn typ(&self) -> Type { Type::I32 } }
 impl ThriftTyped for i64 { fn typ(&self) -> Type { Type::I64 } }
 impl ThriftTyped for f64 { fn typ(&self) -> Type { Type::Double } }
+impl ThriftTyped for OrderedFloat<f64> { fn typ(&self) -> Type { Type::Double } }
 impl ThriftTyped for () { fn typ(&self) -> Type { Type::Void } }
 impl ThriftTyped for String { fn typ(&self) -> Type { Type::String } }
 impl ThriftTyped for Vec<u8> { fn typ(&self) -> Type { Type::String } }
@@ -121,6 +124,14 @@ prim_encode! {
     i32 => write_i32, i64 => write_i64, f64 => write_double
 }
 
+// The generated code stores doubles this way so that structs can be ordered.
+impl Encode for OrderedFloat<f64> {
+    fn encode<P, T>(&self, protocol: &mut P, transport: &mut T) -> Result<()>
+    where P: Protocol, T: Transport {
+        self.0.encode(protocol, transport)
+    }
+}
+
 fn decode<D, P, T>(protocol: &mut P, transport: &mut T) -> Result<D>
 where D: Decode, P: Protocol, T: Transport {
      let mut elem = D::default();
@@ -213,3 +224,10 @@ prim_decode! {
     Vec<u8> => read_binary
 }
 
+impl Decode for OrderedFloat<f64> {
+    fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> Result<()>
+    where P: Protocol, T: Transport {
+        self.0.decode(protocol, transport)
+    }
+}
+
@@ -1,6 +1,9 @@
 #![recursion_limit="1024"]
 extern crate podio;
 extern crate ordered_float;
+extern crate lazy_static;
+#[cfg(feature = "nonblocking")]
+extern crate tokio;
 
 #[macro_use]
 extern crate log;
@@ -11,20 +14,33 @@ use std::error::Error as StdError;
 pub use protocol::Protocol;
 pub use transport::Transport;
 pub use processor::Processor;
+pub use exception::{ApplicationException, ApplicationExceptionKind};
+pub use builder::BuildError;
+pub use enom::EnumError;
+pub use reflect::{ThriftStruct, ThriftEnum, ThriftService};
 
 pub mod rt {
     pub use ordered_float::OrderedFloat;
+    pub use lazy_static::lazy_static;
 }
 
+#[macro_use]
+mod codegen;
+
 pub mod protocol;
 pub mod transport;
 pub mod server;
+pub mod client;
 pub mod processor;
 pub mod proxy;
 pub mod virt;
+pub mod exception;
+pub mod builder;
+pub mod enom;
+pub mod reflect;
+#[cfg(feature = "nonblocking")]
+pub mod nonblocking;
 
-#[macro_use]
-mod codegen;
 mod impls;
 mod compiletest;
 
@@ -43,7 +59,35 @@ pub enum Error {
     ProtocolError(protocol::Error),
 
     /// The server code threw a user-defined exception
-    UserException,
+    UserException(Box<StdError + Send + Sync>),
+
+    /// The server failed to process the call, for instance, because the
+    /// method is unknown
+    ApplicationException(ApplicationException),
+
+    /// The server replied to the named method with neither a result nor an
+    /// exception
+    MissingResult(String),
+
+    /// The server replied to a method with an exception that is not declared
+    /// in this version of the IDL, stored in the given fields of the result
+    UnknownException { method: String, field_ids: Vec<i16> },
+
+    /// Connecting, or reading from/writing to the underlying transport, took
+    /// longer than allowed
+    Timeout,
+}
+
+impl Error {
+    /// Whether the error leaves the connection it happened on in an unknown
+    /// state, for instance halfway through a message, so that it should not
+    /// be used for further calls.
+    pub fn breaks_connection(&self) -> bool {
+        match *self {
+            Error::TransportError(_) | Error::ProtocolError(_) | Error::Timeout => true,
+            _ => false,
+        }
+    }
 }
 
 impl From<protocol::Error> for Error {
@@ -54,7 +98,12 @@ impl From<protocol::Error> for Error {
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
-        Error::TransportError(err)
+        match err.kind() {
+            // `Deadline` reports the timeouts of blocking sockets, which
+            // fail with `WouldBlock` on some platforms, as `TimedOut`.
+            io::ErrorKind::TimedOut => Error::Timeout,
+            _ => Error::TransportError(err),
+        }
     }
 }
 
@@ -67,7 +116,9 @@ impl StdError for Error {
         match *self {
             Error::TransportError(ref err) => Some(err),
             Error::ProtocolError(ref err) => Some(err),
-            _ => None
+            Error::UserException(ref err) => Some(&**err),
+            Error::ApplicationException(ref err) => Some(err),
+            Error::MissingResult(_) | Error::UnknownException { .. } | Error::Timeout => None,
         }
     }
 }
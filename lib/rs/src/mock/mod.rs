@@ -1,9 +1,14 @@
 use std::io;
 use std::io::Read as IoRead;
 use std::io::Write as IoWrite;
+use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use {Protocol, Transport, Result};
+use {Processor, Protocol, Transport, Result};
+use transport::BufferTransport;
 use protocol::{Type, MessageType};
+use protocol::binary_protocol::BinaryProtocol;
 
 pub use self::ProtocolAction::*;
 pub use self::Primitive::*;
@@ -42,6 +47,70 @@ impl io::Read for MockTransport {
 
 impl Transport for MockTransport {}
 
+/// A connection to an in-process server: every flush hands the request to a
+/// processor speaking the binary protocol, and its reply is read back.
+pub struct Loopback<Pr> {
+    processor: Arc<Pr>,
+    pub switches: Arc<Switches>,
+    request: Vec<u8>,
+    reply: io::Cursor<Vec<u8>>,
+}
+
+/// Faults to inject into a `Loopback` connection.
+#[derive(Default)]
+pub struct Switches {
+    /// Fail like a closed socket.
+    pub closed: AtomicBool,
+    /// Process requests but never reply.
+    pub silent: AtomicBool,
+}
+
+impl<Pr> Loopback<Pr> {
+    pub fn new(processor: Arc<Pr>) -> Self {
+        Loopback {
+            processor: processor,
+            switches: Arc::new(Switches::default()),
+            request: Vec::new(),
+            reply: io::Cursor::new(Vec::new()),
+        }
+    }
+
+    fn check_open(&self) -> io::Result<()> {
+        if self.switches.closed.load(Ordering::SeqCst) {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "connection closed"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<Pr> io::Write for Loopback<Pr> where Pr: Processor<BinaryProtocol, BufferTransport> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        try!(self.check_open());
+        self.request.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        try!(self.check_open());
+        let mut transport = BufferTransport::new(mem::replace(&mut self.request, Vec::new()));
+        try!(self.processor.process(&mut BinaryProtocol, &mut transport)
+             .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string())));
+        if !self.switches.silent.load(Ordering::SeqCst) {
+            self.reply = io::Cursor::new(transport.into_output());
+        }
+        Ok(())
+    }
+}
+
+impl<Pr> io::Read for Loopback<Pr> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        try!(self.check_open());
+        self.reply.read(buf)
+    }
+}
+
+impl<Pr> Transport for Loopback<Pr> where Pr: Processor<BinaryProtocol, BufferTransport> {}
+
 #[derive(Debug, Default, Clone)]
 pub struct MockProtocol {
     log: Vec<ProtocolAction>
@@ -222,9 +291,56 @@ impl Protocol for MockProtocol {
     fn read_string<T: Transport>(&mut self, _: &mut T) -> Result<String> { read!(self, Prim(PString(string)), string) }
     fn read_binary<T: Transport>(&mut self, _: &mut T) -> Result<Vec<u8>> { read!(self, Prim(Binary(val)), val) }
 
-    fn skip<T: Transport>(&mut self, _: &mut T, _: Type) -> Result<()> {
-        // TODO: Implement *checked* skipping
-        if self.log.len() != 0 { self.log.pop(); }
+    fn skip<T: Transport>(&mut self, transport: &mut T, type_: Type) -> Result<()> {
+        match type_ {
+            Type::Bool => { try!(self.read_bool(transport)); }
+            Type::Byte => { try!(self.read_byte(transport)); }
+            Type::I16 => { try!(self.read_i16(transport)); }
+            Type::I32 => { try!(self.read_i32(transport)); }
+            Type::I64 => { try!(self.read_i64(transport)); }
+            Type::Double => { try!(self.read_double(transport)); }
+            // Strings and binaries share a wire type but are logged differently.
+            Type::String => match self.log.first() {
+                Some(&Prim(Binary(_))) => { try!(self.read_binary(transport)); }
+                _ => { try!(self.read_string(transport)); }
+            },
+            Type::Struct => {
+                try!(self.read_struct_begin(transport));
+                loop {
+                    let (_, field_type, _) = try!(self.read_field_begin(transport));
+                    if field_type == Type::Stop {
+                        break;
+                    }
+                    try!(self.skip(transport, field_type));
+                    try!(self.read_field_end(transport));
+                }
+                try!(self.read_struct_end(transport));
+            }
+            Type::Map => {
+                let (key_type, value_type, size) = try!(self.read_map_begin(transport));
+                for _ in 0..size {
+                    try!(self.skip(transport, key_type));
+                    try!(self.skip(transport, value_type));
+                }
+                try!(self.read_map_end(transport));
+            }
+            Type::Set => {
+                let (elem_type, size) = try!(self.read_set_begin(transport));
+                for _ in 0..size {
+                    try!(self.skip(transport, elem_type));
+                }
+                try!(self.read_set_end(transport));
+            }
+            Type::List => {
+                let (elem_type, size) = try!(self.read_list_begin(transport));
+                for _ in 0..size {
+                    try!(self.skip(transport, elem_type));
+                }
+                try!(self.read_list_end(transport));
+            }
+            Type::Void | Type::Stop => { }
+        }
+
         Ok(())
     }
 }
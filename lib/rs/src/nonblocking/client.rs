@@ -0,0 +1,120 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use protocol::Protocol;
+use transport::framed::DEFAULT_MAX_FRAME_SIZE;
+use {Error, Result};
+use super::{AsyncTransport, BufferTransport, FrameReader, FrameWriter};
+
+/// A call made by a generated async client.
+///
+/// Sends the encoded request and, unless the method is oneway, reads the
+/// reply frame and hands it to `decode`.
+///
+/// `broken` is set once the request starts being written, and only cleared
+/// when the call completes without breaking the connection. A call dropped
+/// halfway, for instance by a timeout, thus leaves it set, and later calls
+/// fail with `NotConnected` instead of reading the wrong reply.
+pub struct Call<'a, P: 'a, T: 'a, F> {
+    protocol: &'a mut P,
+    transport: &'a mut T,
+    broken: &'a mut bool,
+    state: State,
+    oneway: bool,
+    decode: Option<F>,
+}
+
+enum State {
+    Failed(Option<Error>),
+    Writing(FrameWriter),
+    Reading(FrameReader),
+    Done,
+}
+
+impl<'a, P, T, F, R> Call<'a, P, T, F>
+where P: Protocol, T: AsyncTransport, F: FnOnce(&mut P, &mut BufferTransport) -> Result<R> {
+    pub fn new(protocol: &'a mut P, transport: &'a mut T, broken: &'a mut bool, request: Result<Vec<u8>>,
+               oneway: bool, decode: F) -> Call<'a, P, T, F> {
+        let state = if *broken {
+            State::Failed(Some(Error::from(io::Error::new(
+                io::ErrorKind::NotConnected, "an earlier call broke the connection"))))
+        } else {
+            match request {
+                Ok(frame) => State::Writing(FrameWriter::new(frame)),
+                Err(e) => State::Failed(Some(e)),
+            }
+        };
+        Call {
+            protocol: protocol,
+            transport: transport,
+            broken: broken,
+            state: state,
+            oneway: oneway,
+            decode: Some(decode),
+        }
+    }
+
+    fn finish(&mut self, reply: Vec<u8>) -> Result<R> {
+        self.state = State::Done;
+        let decode = self.decode.take().expect("Call polled after completion");
+        let result = decode(self.protocol, &mut BufferTransport::new(reply));
+        *self.broken = result.as_ref().err().map_or(false, Error::breaks_connection);
+        result
+    }
+}
+
+// Nothing in a `Call` is pinned structurally.
+impl<'a, P, T, F> Unpin for Call<'a, P, T, F> {}
+
+impl<'a, P, T, F, R> Future for Call<'a, P, T, F>
+where P: Protocol, T: AsyncTransport, F: FnOnce(&mut P, &mut BufferTransport) -> Result<R> {
+    type Output = Result<R>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<R>> {
+        let this = &mut *self;
+        loop {
+            match this.state {
+                State::Failed(ref mut err) => {
+                    return Poll::Ready(Err(err.take().expect("Call polled after completion")));
+                }
+                State::Writing(ref mut writer) => {
+                    *this.broken = true;
+                    try_ready!(writer.poll_write(this.transport, cx));
+                    if this.oneway {
+                        // The server does not reply to oneway calls.
+                        return Poll::Ready(this.finish(Vec::new()));
+                    }
+                    this.state = State::Reading(FrameReader::new(DEFAULT_MAX_FRAME_SIZE));
+                }
+                State::Reading(ref mut reader) => {
+                    return Poll::Ready(match try_ready!(reader.poll_read(this.transport, cx)) {
+                        Some(reply) => this.finish(reply),
+                        None => Err(Error::TransportError(io::ErrorKind::UnexpectedEof.into())),
+                    });
+                }
+                State::Done => panic!("Call polled after completion"),
+            }
+        }
+    }
+}
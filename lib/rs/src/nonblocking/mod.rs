@@ -0,0 +1,224 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+//! Clients and servers for tokio, with the `nonblocking` feature.
+//!
+//! Messages travel in frames, like `transport::framed::FramedTransport`.
+//! Frames are read and written without blocking, but each one is encoded
+//! and decoded in memory with the same `Encode`/`Decode` implementations as
+//! the blocking stack.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::io;
+use std::mem;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf};
+use tokio::net::TcpStream;
+
+use transport::framed::{decode_headers, frame_size, FrameKind, DEFAULT_MAX_FRAME_SIZE};
+
+macro_rules! try_ready {
+    ($e:expr) => {
+        match $e {
+            Poll::Ready(Ok(v)) => v,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(From::from(e))),
+            Poll::Pending => return Poll::Pending,
+        }
+    }
+}
+
+pub mod client;
+pub mod server;
+
+pub use self::client::Call;
+pub use self::server::{Server, Serve, ServeConnection};
+pub use transport::BufferTransport;
+
+pub trait AsyncTransport: AsyncRead + AsyncWrite + Unpin {
+    /// The address of the peer, for socket transports.
+    fn peer_addr(&self) -> Option<SocketAddr> { None }
+}
+
+impl AsyncTransport for TcpStream {
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        TcpStream::peer_addr(self).ok()
+    }
+}
+
+impl AsyncTransport for DuplexStream {}
+
+impl<'t, T> AsyncTransport for &'t mut T where T: AsyncTransport {
+    fn peer_addr(&self) -> Option<SocketAddr> { (**self).peer_addr() }
+}
+
+impl<T> AsyncTransport for Box<T> where T: AsyncTransport + ?Sized {
+    fn peer_addr(&self) -> Option<SocketAddr> { (**self).peer_addr() }
+}
+
+/// Read one frame from `transport`, skipping the header frames of
+/// `FramedTransport`.
+///
+/// Resolves to `None` if the peer closes the connection between frames.
+pub fn read_frame<'a, T: AsyncTransport>(transport: &'a mut T) -> ReadFrame<'a, T> {
+    ReadFrame { transport: transport, reader: FrameReader::new(DEFAULT_MAX_FRAME_SIZE) }
+}
+
+/// Write `frame` to `transport`, prefixed with its length, and flush it.
+pub fn write_frame<'a, T: AsyncTransport>(transport: &'a mut T, frame: Vec<u8>) -> WriteFrame<'a, T> {
+    WriteFrame { transport: transport, writer: FrameWriter::new(frame) }
+}
+
+pub struct ReadFrame<'a, T: 'a> {
+    transport: &'a mut T,
+    reader: FrameReader,
+}
+
+impl<'a, T: AsyncTransport> Future for ReadFrame<'a, T> {
+    type Output = io::Result<Option<Vec<u8>>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        this.reader.poll_read(&mut *this.transport, cx)
+    }
+}
+
+pub struct WriteFrame<'a, T: 'a> {
+    transport: &'a mut T,
+    writer: FrameWriter,
+}
+
+impl<'a, T: AsyncTransport> Future for WriteFrame<'a, T> {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        this.writer.poll_write(&mut *this.transport, cx)
+    }
+}
+
+/// The state of a frame being read, kept apart from the transport so that
+/// clients and servers can alternate between reading and writing on a
+/// transport they own.
+struct FrameReader {
+    header: [u8; 4],
+    header_read: usize,
+    kind: FrameKind,
+    body: Vec<u8>,
+    body_read: usize,
+    max_frame_size: usize,
+    /// The header frame read before the message, if any.
+    headers: Option<Vec<u8>>,
+}
+
+impl FrameReader {
+    fn new(max_frame_size: usize) -> FrameReader {
+        FrameReader {
+            header: [0; 4],
+            header_read: 0,
+            kind: FrameKind::Message,
+            body: Vec::new(),
+            body_read: 0,
+            max_frame_size: max_frame_size,
+            headers: None,
+        }
+    }
+
+    fn poll_read<T: AsyncTransport>(&mut self, transport: &mut T, cx: &mut Context) -> Poll<io::Result<Option<Vec<u8>>>> {
+        loop {
+            while self.header_read < self.header.len() {
+                let n = try_ready!(poll_read_some(transport, cx, &mut self.header[self.header_read..]));
+                if n == 0 {
+                    return Poll::Ready(if self.header_read == 0 && self.headers.is_none() {
+                        Ok(None)
+                    } else {
+                        Err(io::ErrorKind::UnexpectedEof.into())
+                    });
+                }
+
+                self.header_read += n;
+                if self.header_read == self.header.len() {
+                    let (kind, size) = try_ready!(Poll::Ready(frame_size(self.header, self.max_frame_size)));
+                    self.kind = kind;
+                    self.body = vec![0; size];
+                }
+            }
+
+            while self.body_read < self.body.len() {
+                let n = try_ready!(poll_read_some(transport, cx, &mut self.body[self.body_read..]));
+                if n == 0 {
+                    return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+                }
+                self.body_read += n;
+            }
+
+            let frame = mem::replace(&mut self.body, Vec::new());
+            self.header_read = 0;
+            self.body_read = 0;
+            match self.kind {
+                FrameKind::Headers => self.headers = Some(frame),
+                FrameKind::Message => return Poll::Ready(Ok(Some(frame))),
+            }
+        }
+    }
+
+    /// The headers sent with the message last read.
+    fn take_headers(&mut self) -> io::Result<BTreeMap<String, String>> {
+        match self.headers.take() {
+            Some(frame) => decode_headers(&frame),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+}
+
+fn poll_read_some<T: AsyncTransport>(transport: &mut T, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+    let mut buf = ReadBuf::new(buf);
+    try_ready!(Pin::new(transport).poll_read(cx, &mut buf));
+    Poll::Ready(Ok(buf.filled().len()))
+}
+
+/// The state of a frame being written, see `FrameReader`.
+struct FrameWriter {
+    buf: Vec<u8>,
+    written: usize,
+}
+
+impl FrameWriter {
+    fn new(frame: Vec<u8>) -> FrameWriter {
+        let mut buf = Vec::with_capacity(frame.len() + 4);
+        buf.extend_from_slice(&(frame.len() as i32).to_be_bytes());
+        buf.extend_from_slice(&frame);
+        FrameWriter { buf: buf, written: 0 }
+    }
+
+    fn poll_write<T: AsyncTransport>(&mut self, transport: &mut T, cx: &mut Context) -> Poll<io::Result<()>> {
+        while self.written < self.buf.len() {
+            let n = try_ready!(Pin::new(&mut *transport).poll_write(cx, &self.buf[self.written..]));
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+        }
+
+        Pin::new(transport).poll_flush(cx)
+    }
+}
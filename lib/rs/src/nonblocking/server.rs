@@ -0,0 +1,216 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+
+use tokio;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use processor::Processor;
+use protocol::ProtocolFactory;
+use transport::framed::DEFAULT_MAX_FRAME_SIZE;
+use {Error, Result};
+use super::{AsyncTransport, BufferTransport, FrameReader, FrameWriter};
+
+/// Serves a processor over framed connections on a tokio runtime.
+///
+/// Connections wait for requests without holding a thread. The processor
+/// and the handlers behind it are synchronous, so each request is handled on
+/// tokio's blocking thread pool through `spawn_blocking`, where handlers may
+/// block without stalling the runtime. The runtime must therefore allow
+/// blocking tasks, as the ones built by `tokio::runtime::Builder` do.
+pub struct Server<PR, PF> {
+    inner: Arc<ServerInner<PR, PF>>
+}
+
+struct ServerInner<PR, PF> {
+    processor: PR,
+    protocol_factory: PF,
+    next_connection_id: AtomicUsize,
+}
+
+impl<PR, PF> Clone for Server<PR, PF> {
+    fn clone(&self) -> Self {
+        Server { inner: self.inner.clone() }
+    }
+}
+
+impl<PR, PF> Server<PR, PF>
+where PF: ProtocolFactory, PR: Processor<PF::Protocol, BufferTransport> {
+    pub fn new(processor: PR, factory: PF) -> Self {
+        Server {
+            inner: Arc::new(ServerInner {
+                processor: processor,
+                protocol_factory: factory,
+                next_connection_id: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    /// Handle the requests arriving on one connection until the peer closes
+    /// it.
+    pub fn serve_connection<T: AsyncTransport>(&self, transport: T) -> ServeConnection<PR, PF, T> {
+        let id = self.inner.next_connection_id.fetch_add(1, Ordering::SeqCst) as u64;
+
+        ServeConnection {
+            server: self.clone(),
+            protocol: Some(self.inner.protocol_factory.new_protocol()),
+            peer_addr: transport.peer_addr(),
+            connection_id: id,
+            transport: transport,
+            state: State::Reading(FrameReader::new(DEFAULT_MAX_FRAME_SIZE)),
+        }
+    }
+}
+
+impl<PR, PF> Server<PR, PF>
+where PF: ProtocolFactory + Send + Sync + 'static,
+      PF::Protocol: Send + 'static,
+      PR: Processor<PF::Protocol, BufferTransport> + Send + Sync + 'static {
+    /// Accept connections from `listener` and serve each of them on its own
+    /// task. Must be polled from within a tokio runtime.
+    pub fn serve(&self, listener: TcpListener) -> Serve<PR, PF> {
+        Serve { server: self.clone(), listener: listener }
+    }
+}
+
+/// The future returned by `Server::serve`. Only completes if accepting a
+/// connection fails.
+pub struct Serve<PR, PF> {
+    server: Server<PR, PF>,
+    listener: TcpListener,
+}
+
+impl<PR, PF> Future for Serve<PR, PF>
+where PF: ProtocolFactory + Send + Sync + 'static,
+      PF::Protocol: Send + 'static,
+      PR: Processor<PF::Protocol, BufferTransport> + Send + Sync + 'static {
+    type Output = io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        loop {
+            let (stream, _) = try_ready!(self.listener.poll_accept(cx));
+            let connection = self.server.serve_connection(stream);
+            tokio::spawn(LogErrors(connection));
+        }
+    }
+}
+
+/// The future returned by `Server::serve_connection`.
+pub struct ServeConnection<PR, PF: ProtocolFactory, T> {
+    server: Server<PR, PF>,
+    /// Handed to the blocking task processing a request, and back.
+    protocol: Option<PF::Protocol>,
+    transport: T,
+    peer_addr: Option<SocketAddr>,
+    connection_id: u64,
+    state: State<PF::Protocol>,
+}
+
+enum State<P> {
+    Reading(FrameReader),
+    /// Resolves to the protocol and the reply, if there is one.
+    Processing(JoinHandle<(P, Result<Vec<u8>>)>),
+    Writing(FrameWriter),
+}
+
+impl<PR, PF: ProtocolFactory, T> ServeConnection<PR, PF, T> {
+    pub fn connection_id(&self) -> u64 { self.connection_id }
+}
+
+// Nothing in a `ServeConnection` is pinned structurally.
+impl<PR, PF: ProtocolFactory, T> Unpin for ServeConnection<PR, PF, T> {}
+
+impl<PR, PF, T> Future for ServeConnection<PR, PF, T>
+where PF: ProtocolFactory + Send + Sync + 'static,
+      PF::Protocol: Send + 'static,
+      PR: Processor<PF::Protocol, BufferTransport> + Send + Sync + 'static,
+      T: AsyncTransport {
+    type Output = Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        let this = &mut *self;
+        loop {
+            let next = match this.state {
+                State::Reading(ref mut reader) => match try_ready!(reader.poll_read(&mut this.transport, cx)) {
+                    None => return Poll::Ready(Ok(())),
+                    Some(request) => {
+                        let headers = try_ready!(Poll::Ready(reader.take_headers()));
+                        let mut buffer = BufferTransport::new(request)
+                            .connection(this.peer_addr, Some(this.connection_id))
+                            .headers(headers);
+                        let mut protocol = this.protocol.take().expect("protocol handed back by the last request");
+                        let server = this.server.clone();
+                        State::Processing(tokio::task::spawn_blocking(move || {
+                            let result = server.inner.processor.process(&mut protocol, &mut buffer);
+                            (protocol, result.map(|()| buffer.into_output()))
+                        }))
+                    }
+                },
+                State::Processing(ref mut task) => {
+                    let (protocol, result) = try_ready!(Pin::new(task).poll(cx).map_err(|e| {
+                        Error::from(io::Error::new(io::ErrorKind::Other, e))
+                    }));
+                    this.protocol = Some(protocol);
+
+                    // Oneway calls have nothing to send back.
+                    let output = try_ready!(Poll::Ready(result));
+                    if output.is_empty() {
+                        State::Reading(FrameReader::new(DEFAULT_MAX_FRAME_SIZE))
+                    } else {
+                        State::Writing(FrameWriter::new(output))
+                    }
+                }
+                State::Writing(ref mut writer) => {
+                    try_ready!(writer.poll_write(&mut this.transport, cx));
+                    State::Reading(FrameReader::new(DEFAULT_MAX_FRAME_SIZE))
+                }
+            };
+            this.state = next;
+        }
+    }
+}
+
+struct LogErrors<PR, PF: ProtocolFactory, T>(ServeConnection<PR, PF, T>);
+
+impl<PR, PF, T> Future for LogErrors<PR, PF, T>
+where PF: ProtocolFactory + Send + Sync + 'static,
+      PF::Protocol: Send + 'static,
+      PR: Processor<PF::Protocol, BufferTransport> + Send + Sync + 'static,
+      T: AsyncTransport {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        match Pin::new(&mut self.0).poll(cx) {
+            Poll::Ready(Err(e)) => {
+                error!("Closing connection {} after error: {}", self.0.connection_id, e);
+                Poll::Ready(())
+            }
+            Poll::Ready(Ok(())) => Poll::Ready(()),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
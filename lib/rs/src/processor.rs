@@ -1,4 +1,8 @@
-use protocol::Protocol;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use protocol::{MessageType, Protocol};
 use transport::Transport;
 use Result;
 
@@ -6,3 +10,62 @@ pub trait Processor<P: Protocol, T: Transport> {
     fn process(&self, prot: &mut P, transport: &mut T) -> Result<()>;
 }
 
+
+/// What a generated processor does when a call leaves out one of the
+/// method's arguments, as clients built against an older IDL do.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MissingArguments {
+    /// Call the handler with the argument's default value from the IDL, or
+    /// its type's default value if the IDL declares none.
+    UseDefault,
+    /// Reply with a `ProtocolError` application exception without calling
+    /// the handler.
+    Reject,
+}
+
+impl Default for MissingArguments {
+    fn default() -> MissingArguments { MissingArguments::UseDefault }
+}
+
+/// Everything a generated processor knows about the call it is handling.
+///
+/// Handler methods declared with `context = true` receive one of these as
+/// their first argument. The envelope fields are always filled in; the rest
+/// come from `Transport::fill_context` and are only present when the
+/// transport the request arrived on can supply them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RequestContext {
+    /// The method name from the message envelope.
+    pub method: String,
+    /// `Call` or `Oneway`.
+    pub message_type: MessageType,
+    /// The sequence id from the message envelope.
+    pub sequence_id: i32,
+    /// The address of the client, for socket transports.
+    pub peer_addr: Option<SocketAddr>,
+    /// Identifies the connection the request arrived on, when the transport
+    /// was accepted through `transport::server::Connections`.
+    pub connection_id: Option<u64>,
+    /// The headers the client sent with the request, for transports that
+    /// carry them, such as `FramedTransport`.
+    pub headers: BTreeMap<String, String>,
+    /// When the client stops waiting for a reply, if it sent its timeout in
+    /// `transport::DEADLINE_HEADER`.
+    pub deadline: Option<Instant>,
+}
+
+impl RequestContext {
+    pub fn new<T: Transport + ?Sized>(transport: &T, method: &str, message_type: MessageType, sequence_id: i32) -> RequestContext {
+        let mut context = RequestContext {
+            method: method.to_owned(),
+            message_type: message_type,
+            sequence_id: sequence_id,
+            peer_addr: None,
+            connection_id: None,
+            headers: BTreeMap::new(),
+            deadline: None,
+        };
+        transport.fill_context(&mut context);
+        context
+    }
+}
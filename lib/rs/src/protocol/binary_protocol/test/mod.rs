@@ -162,6 +162,20 @@ fn read_message_begin() {
     );
 }
 
+#[test]
+fn read_message_begin_oneway() {
+    let transport = &mut MockTransport::new(vec!(
+        0x80, 0x01, 0x00, 0x04,
+        0x00, 0x00, 0x00, 0x03, 0x66, 0x6f, 0x6f,
+        0x00, 0x00, 0x00, 0x01
+    ));
+    let mut protocol = BinaryProtocol;
+    assert_eq!(
+        protocol.read_message_begin(transport).unwrap(),
+        ("foo".to_string(), protocol::MessageType::Oneway, 1)
+    );
+}
+
 #[test]
 fn read_message_begin_bad_version() {
     let transport = &mut MockTransport::new(vec!(
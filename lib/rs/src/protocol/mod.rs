@@ -23,6 +23,9 @@ use transport::Transport;
 use Result;
 
 pub mod binary_protocol;
+pub mod value;
+
+pub use self::value::{UnknownFields, Value};
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
@@ -32,6 +35,20 @@ pub enum Error {
     ProtocolViolation,
     /// Received string cannot be converted to a UTF8 string
     InvalidUtf8(str::Utf8Error),
+    /// Received a message of a type that is not valid at this point, for
+    /// instance, a client receiving a `Call`
+    InvalidMessageType(MessageType),
+    /// Received a reply for another method than the one that was called
+    WrongMethodName { expected: String, received: String },
+    /// Received a reply whose sequence id does not match the call's
+    BadSequenceId { expected: i32, received: i32 },
+    /// Received a known field with another type than the IDL declares, while
+    /// decoding with `StrictFields`
+    FieldTypeMismatch { strukt: &'static str, field: &'static str, expected: Type, actual: Type },
+    /// A struct ended without a field marked `#[thrift(required)]`
+    MissingRequiredField { strukt: &'static str, field: &'static str },
+    /// Received a value nested deeper than `value::MAX_DEPTH`
+    DepthLimitExceeded,
 }
 
 impl StdError for Error {
@@ -73,7 +90,7 @@ impl<F, P: Protocol> ProtocolFactory for F where F: Fn() -> P {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub enum Type {
     Stop = 0x00,
     Void = 0x01,
@@ -159,6 +176,7 @@ pub enum MessageType {
     Call = 0x01,
     Reply = 0x02,
     Exception = 0x03,
+    Oneway = 0x04,
 }
 
 impl fmt::Display for MessageType {
@@ -166,7 +184,8 @@ impl fmt::Display for MessageType {
         f.write_str(match *self {
             MessageType::Call => "Call",
             MessageType::Reply => "Reply",
-            MessageType::Exception => "Exception"
+            MessageType::Exception => "Exception",
+            MessageType::Oneway => "Oneway"
         })
     }
 }
@@ -179,6 +198,7 @@ impl ::std::str::FromStr for MessageType {
             "Call" => MessageType::Call,
             "Reply" => MessageType::Reply,
             "Exception" => MessageType::Exception,
+            "Oneway" => MessageType::Oneway,
             _ => return Err(())
         })
     }
@@ -190,6 +210,7 @@ impl MessageType {
             0x01 => Some(MessageType::Call),
             0x02 => Some(MessageType::Reply),
             0x03 => Some(MessageType::Exception),
+            0x04 => Some(MessageType::Oneway),
             _ => None,
         }
     }
@@ -287,6 +308,10 @@ pub trait Protocol {
     fn read_binary<T: Transport>(&mut self, transport: &mut T) -> Result<Vec<u8>>;
 
     fn skip<T: Transport>(&mut self, transport: &mut T, type_: Type) -> Result<()>;
+
+    /// Whether `strukt!` decoding should fail when a known field arrives
+    /// with the wrong type, rather than skip it. See `StrictFields`.
+    fn strict_fields(&self) -> bool { false }
 }
 
 impl<'a, T: ?Sized> ThriftTyped for &'a T where T: ThriftTyped {
@@ -477,6 +502,199 @@ impl<'a, P: ?Sized> Protocol for &'a mut P where P: Protocol {
     fn skip<T: Transport>(&mut self, transport: &mut T, type_: Type) -> Result<()> {
         <P as Protocol>::skip(self, transport, type_)
     }
+
+    fn strict_fields(&self) -> bool {
+        <P as Protocol>::strict_fields(self)
+    }
+}
+
+/// Wraps a protocol so that decoding a struct fails with
+/// `Error::FieldTypeMismatch` when one of its fields arrives with another
+/// type than the IDL declares. Plain protocols skip such fields, leaving the
+/// field at its default value.
+///
+/// Wrap a borrowed protocol, as in `StrictFields(&mut protocol)`, to decode
+/// a single value strictly.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StrictFields<P>(pub P);
+
+impl<P: Protocol> Protocol for StrictFields<P> {
+    fn write_message_begin<T: Transport>(&mut self, transport: &mut T, name: &str,
+                           message_type: MessageType, sequence_id: i32) -> Result<()> {
+        self.0.write_message_begin(transport, name, message_type, sequence_id)
+    }
+
+    fn write_message_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.0.write_message_end(transport)
+    }
+
+    fn write_struct_begin<T: Transport>(&mut self, transport: &mut T, name: &str) -> Result<()> {
+        self.0.write_struct_begin(transport, name)
+    }
+
+    fn write_struct_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.0.write_struct_end(transport)
+    }
+
+    fn write_field_begin<T: Transport>(&mut self, transport: &mut T, name: &str,
+                         field_type: Type, field_id: i16) -> Result<()> {
+        self.0.write_field_begin(transport, name, field_type, field_id)
+    }
+
+    fn write_field_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.0.write_field_end(transport)
+    }
+
+    fn write_field_stop<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.0.write_field_stop(transport)
+    }
+
+    fn write_map_begin<T: Transport>(&mut self, transport: &mut T, key_type: Type,
+                       value_type: Type, size: usize) -> Result<()> {
+        self.0.write_map_begin(transport, key_type, value_type, size)
+    }
+
+    fn write_map_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.0.write_map_end(transport)
+    }
+
+    fn write_list_begin<T: Transport>(&mut self, transport: &mut T, elem_type: Type, size: usize) -> Result<()> {
+        self.0.write_list_begin(transport, elem_type, size)
+    }
+
+    fn write_list_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.0.write_list_end(transport)
+    }
+
+    fn write_set_begin<T: Transport>(&mut self, transport: &mut T, elem_type: Type, size: usize) -> Result<()> {
+        self.0.write_set_begin(transport, elem_type, size)
+    }
+
+    fn write_set_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.0.write_set_end(transport)
+    }
+
+    fn write_bool<T: Transport>(&mut self, transport: &mut T, value: bool) -> Result<()> {
+        self.0.write_bool(transport, value)
+    }
+
+    fn write_byte<T: Transport>(&mut self, transport: &mut T, value: i8) -> Result<()> {
+         self.0.write_byte(transport, value)
+    }
+
+    fn write_i16<T: Transport>(&mut self, transport: &mut T, value: i16) -> Result<()> {
+        self.0.write_i16(transport, value)
+    }
+
+    fn write_i32<T: Transport>(&mut self, transport: &mut T, value: i32) -> Result<()> {
+        self.0.write_i32(transport, value)
+    }
+
+    fn write_i64<T: Transport>(&mut self, transport: &mut T, value: i64) -> Result<()> {
+        self.0.write_i64(transport, value)
+    }
+
+    fn write_double<T: Transport>(&mut self, transport: &mut T, value: f64) -> Result<()> {
+        self.0.write_double(transport, value)
+    }
+
+    fn write_str<T: Transport>(&mut self, transport: &mut T, value: &str) -> Result<()> {
+        self.0.write_str(transport, value)
+    }
+
+    fn write_string<T: Transport>(&mut self, transport: &mut T, value: &String) -> Result<()> {
+        self.0.write_string(transport, value)
+    }
+
+    fn write_binary<T: Transport>(&mut self, transport: &mut T, value: &[u8]) -> Result<()> {
+        self.0.write_binary(transport, value)
+    }
+
+    fn read_message_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(String, MessageType, i32)> {
+        self.0.read_message_begin(transport)
+    }
+
+    fn read_message_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.0.read_message_end(transport)
+    }
+
+    fn read_struct_begin<T: Transport>(&mut self, transport: &mut T) -> Result<String> {
+        self.0.read_struct_begin(transport)
+    }
+
+    fn read_struct_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.0.read_struct_end(transport)
+    }
+
+    fn read_field_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(String, Type, i16)> {
+        self.0.read_field_begin(transport)
+    }
+
+    fn read_field_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.0.read_field_end(transport)
+    }
+
+    fn read_map_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, Type, i32)> {
+        self.0.read_map_begin(transport)
+    }
+
+    fn read_map_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.0.read_map_end(transport)
+    }
+
+    fn read_list_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, i32)> {
+        self.0.read_list_begin(transport)
+    }
+
+    fn read_list_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.0.read_list_end(transport)
+    }
+
+    fn read_set_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, i32)> {
+        self.0.read_set_begin(transport)
+    }
+
+    fn read_set_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.0.read_set_end(transport)
+    }
+
+    fn read_bool<T: Transport>(&mut self, transport: &mut T) -> Result<bool> {
+        self.0.read_bool(transport)
+    }
+
+    fn read_byte<T: Transport>(&mut self, transport: &mut T) -> Result<i8> {
+        self.0.read_byte(transport)
+    }
+
+    fn read_i16<T: Transport>(&mut self, transport: &mut T) -> Result<i16> {
+        self.0.read_i16(transport)
+    }
+
+    fn read_i32<T: Transport>(&mut self, transport: &mut T) -> Result<i32> {
+        self.0.read_i32(transport)
+    }
+
+    fn read_i64<T: Transport>(&mut self, transport: &mut T) -> Result<i64> {
+        self.0.read_i64(transport)
+    }
+
+    fn read_double<T: Transport>(&mut self, transport: &mut T) -> Result<f64> {
+        self.0.read_double(transport)
+    }
+
+    fn read_string<T: Transport>(&mut self, transport: &mut T) -> Result<String> {
+        self.0.read_string(transport)
+    }
+
+    fn read_binary<T: Transport>(&mut self, transport: &mut T) -> Result<Vec<u8>> {
+        self.0.read_binary(transport)
+    }
+
+    fn skip<T: Transport>(&mut self, transport: &mut T, type_: Type) -> Result<()> {
+        self.0.skip(transport, type_)
+    }
+
+    fn strict_fields(&self) -> bool { true }
 }
 
 pub trait FromNum: Sized {
@@ -484,8 +702,11 @@ pub trait FromNum: Sized {
 }
 
 pub mod helpers {
+    use std::any::Any;
+
     use protocol::{ThriftTyped, Protocol, Type, MessageType, FromNum, Decode, Encode, Error};
     use transport::Transport;
+    use exception::{ApplicationException, ApplicationExceptionKind};
     use Result;
 
     pub fn typ<T: ThriftTyped + Default>() -> Type {
@@ -512,44 +733,163 @@ pub mod helpers {
         Ok(())
     }
 
+    /// Skip the arguments of a call to a method the processor does not
+    /// implement and, unless the call is oneway, reply with an
+    /// `UnknownMethod` application exception.
+    pub fn reply_unknown_method<T, P>(protocol: &mut P, transport: &mut T,
+                                      name: &str, ty: MessageType, id: i32) -> Result<()>
+    where T: Transport, P: Protocol {
+        try!(protocol.skip(transport, Type::Struct));
+        try!(protocol.read_message_end(transport));
+
+        let exception = ApplicationException::new(ApplicationExceptionKind::UnknownMethod,
+                                                  format!("Unknown method {}", name));
+        reply_exception(protocol, transport, name, ty, id, &exception)
+    }
+
+    /// Skip the arguments of a call sent as `ty` to a method declared as
+    /// `expected`, `Call` or `Oneway`, and unless the call is oneway, reply
+    /// with an `InvalidMessageType` application exception.
+    pub fn reply_invalid_message_type<T, P>(protocol: &mut P, transport: &mut T, name: &str,
+                                            expected: MessageType, ty: MessageType, id: i32) -> Result<()>
+    where T: Transport, P: Protocol {
+        try!(protocol.skip(transport, Type::Struct));
+        try!(protocol.read_message_end(transport));
+
+        let exception = ApplicationException::new(ApplicationExceptionKind::InvalidMessageType,
+                                                  format!("{} expects {:?} messages, not {:?}", name, expected, ty));
+        reply_exception(protocol, transport, name, ty, id, &exception)
+    }
+
+    /// Reply to a call that left out one of its arguments with a
+    /// `ProtocolError` application exception, unless the call is oneway.
+    pub fn reply_missing_argument<T, P>(protocol: &mut P, transport: &mut T, name: &str,
+                                        argument: &str, ty: MessageType, id: i32) -> Result<()>
+    where T: Transport, P: Protocol {
+        let exception = ApplicationException::new(ApplicationExceptionKind::ProtocolError,
+                                                  format!("Missing argument {} to {}", argument, name));
+        reply_exception(protocol, transport, name, ty, id, &exception)
+    }
+
+    /// Log a panic caught in a handler method and, unless the call is
+    /// oneway, reply with an `InternalError` application exception carrying
+    /// the panic message.
+    pub fn reply_handler_panic<T, P>(protocol: &mut P, transport: &mut T, name: &str,
+                                     ty: MessageType, id: i32, payload: Box<Any + Send>) -> Result<()>
+    where T: Transport, P: Protocol {
+        let message = match payload.downcast_ref::<&'static str>() {
+            Some(message) => String::from(*message),
+            None => match payload.downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => String::from("Box<Any>")
+            }
+        };
+
+        error!("handler for {} panicked: {}", name, message);
+
+        let exception = ApplicationException::new(ApplicationExceptionKind::InternalError, message);
+        reply_exception(protocol, transport, name, ty, id, &exception)
+    }
+
+    fn reply_exception<T, P>(protocol: &mut P, transport: &mut T, name: &str, ty: MessageType,
+                             id: i32, exception: &ApplicationException) -> Result<()>
+    where T: Transport, P: Protocol {
+        // Oneway callers never read a reply.
+        if ty == MessageType::Oneway {
+            return Ok(())
+        }
+
+        send(protocol, transport, name, MessageType::Exception, exception, id)
+    }
+
     pub fn receive<R, T, P>(protocol: &mut P, transport: &mut T,
-                            op: &str, result: &mut R) -> Result<()>
+                            op: &str, seqid: i32, result: &mut R) -> Result<()>
     where R: Decode, T: Transport, P: Protocol {
+        receive_with(protocol, transport, op, seqid, |protocol, transport| {
+            result.decode(protocol, transport)
+        })
+    }
+
+    /// Receive the reply to a call, handing each field of the result struct
+    /// to `field`, which returns whether it knows the field. Returns the ids
+    /// of the fields it did not know, which were skipped.
+    pub fn receive_reply<F, T, P>(protocol: &mut P, transport: &mut T,
+                                  op: &str, seqid: i32, mut field: F) -> Result<Vec<i16>>
+    where F: FnMut(&mut P, &mut T, Type, i16) -> Result<bool>, T: Transport, P: Protocol {
+        let mut unknown = Vec::new();
+
+        try!(receive_with(protocol, transport, op, seqid, |protocol, transport| {
+            try!(protocol.read_struct_begin(transport));
+
+            loop {
+                let (_, typ, id) = try!(protocol.read_field_begin(transport));
+
+                if typ == Type::Stop {
+                    break;
+                } else if !try!(field(protocol, transport, typ, id)) {
+                    unknown.push(id);
+                    try!(protocol.skip(transport, typ));
+                }
+
+                try!(protocol.read_field_end(transport));
+            }
+
+            protocol.read_struct_end(transport)
+        }));
+
+        Ok(unknown)
+    }
+
+    fn receive_with<F, T, P>(protocol: &mut P, transport: &mut T,
+                             op: &str, seqid: i32, decode: F) -> Result<()>
+    where F: FnOnce(&mut P, &mut T) -> Result<()>, T: Transport, P: Protocol {
         let (name, ty, id) = try!(protocol.read_message_begin(transport));
-        receive_body(protocol, transport, op, result, &name, ty, id)
+
+        // An exception meant for another call must not fail this one.
+        let err = match ty {
+            MessageType::Reply | MessageType::Exception if name != op => {
+                Error::WrongMethodName { expected: op.to_owned(), received: name }
+            }
+            MessageType::Reply | MessageType::Exception if id != seqid => {
+                Error::BadSequenceId { expected: seqid, received: id }
+            }
+            MessageType::Exception => {
+                let mut exception = ApplicationException::default();
+                try!(exception.decode(protocol, transport));
+                try!(protocol.read_message_end(transport));
+                return Err(::Error::ApplicationException(exception))
+            }
+            MessageType::Reply => {
+                try!(decode(protocol, transport));
+                try!(protocol.read_message_end(transport));
+                return Ok(())
+            }
+            _ => Error::InvalidMessageType(ty)
+        };
+
+        try!(protocol.skip(transport, Type::Struct));
+        try!(protocol.read_message_end(transport));
+        Err(::Error::from(err))
     }
 
     pub fn receive_body<R, T, P>(protocol: &mut P, transport: &mut T, op: &str,
-                                 result: &mut R, name: &str, ty: MessageType,
-                                 id: i32) -> Result<()>
+                                 result: &mut R, name: &str, ty: MessageType) -> Result<()>
     where R: Decode, T: Transport, P: Protocol {
-        match (name, ty, id) {
-            (_, MessageType::Exception, _) => {
-                println!("got exception");
-                // TODO
-                //let x = ApplicationException;
-                //x.read(&mut protocol)
-                //protocol.read_message_end();
-                //transport.read_end();
-                //throw x
-                Err(::Error::UserException)
+        let err = match ty {
+            MessageType::Call | MessageType::Oneway if name != op => {
+                Error::WrongMethodName { expected: op.to_owned(), received: name.to_owned() }
             }
-            // TODO: Make sure the client doesn't receive Call messages and that the server
-            // doesn't receive Reply messages
-            (fname, _, _) => {
-                if &fname[..] == op {
-                    try!(result.decode(protocol, transport));
-                    try!(protocol.read_message_end(transport));
-                    Ok(())
-                 }
-                else {
-                    // FIXME: shall we err in this case?
-                    try!(protocol.skip(transport, Type::Struct));
-                    try!(protocol.read_message_end(transport));
-                    Err(::Error::from(Error::ProtocolViolation))
-                }
+            MessageType::Call | MessageType::Oneway => {
+                try!(result.decode(protocol, transport));
+                try!(protocol.read_message_end(transport));
+                return Ok(())
             }
-        }
+            _ => Error::InvalidMessageType(ty)
+        };
+
+        try!(protocol.skip(transport, Type::Struct));
+        try!(protocol.read_message_end(transport));
+        Err(::Error::from(err))
     }
 }
 
@@ -0,0 +1,259 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::slice;
+
+use ordered_float::OrderedFloat;
+
+use transport::BufferTransport;
+use transport::Transport;
+use Result;
+use super::{Decode, Encode, Error, Protocol, Type};
+use super::binary_protocol::BinaryProtocol;
+
+/// How deeply structs and containers may nest inside a `Value` being read,
+/// so that hostile input cannot exhaust the stack.
+pub const MAX_DEPTH: usize = 64;
+
+/// A value read off the wire without knowing its IDL type.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Value {
+    Bool(bool),
+    Byte(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Double(OrderedFloat<f64>),
+    /// Strings and binaries, which look the same on the wire.
+    String(Vec<u8>),
+    Struct(UnknownFields),
+    Map(Type, Type, Vec<(Value, Value)>),
+    Set(Type, Vec<Value>),
+    List(Type, Vec<Value>),
+}
+
+impl Value {
+    pub fn typ(&self) -> Type {
+        match *self {
+            Value::Bool(_) => Type::Bool,
+            Value::Byte(_) => Type::Byte,
+            Value::I16(_) => Type::I16,
+            Value::I32(_) => Type::I32,
+            Value::I64(_) => Type::I64,
+            Value::Double(_) => Type::Double,
+            Value::String(_) => Type::String,
+            Value::Struct(_) => Type::Struct,
+            Value::Map(..) => Type::Map,
+            Value::Set(..) => Type::Set,
+            Value::List(..) => Type::List,
+        }
+    }
+
+    /// Read a value of the given type.
+    ///
+    /// Fails with `Error::DepthLimitExceeded` when structs and containers
+    /// nest more than `MAX_DEPTH` levels deep.
+    pub fn read<P, T>(protocol: &mut P, transport: &mut T, typ: Type) -> Result<Value>
+    where P: Protocol, T: Transport {
+        Value::read_nested(protocol, transport, typ, 0)
+    }
+
+    fn read_nested<P, T>(protocol: &mut P, transport: &mut T, typ: Type, depth: usize) -> Result<Value>
+    where P: Protocol, T: Transport {
+        if depth > MAX_DEPTH {
+            return Err(Error::DepthLimitExceeded.into());
+        }
+        let depth = depth + 1;
+        Ok(match typ {
+            Type::Bool => Value::Bool(try!(protocol.read_bool(transport))),
+            Type::Byte => Value::Byte(try!(protocol.read_byte(transport))),
+            Type::I16 => Value::I16(try!(protocol.read_i16(transport))),
+            Type::I32 => Value::I32(try!(protocol.read_i32(transport))),
+            Type::I64 => Value::I64(try!(protocol.read_i64(transport))),
+            Type::Double => Value::Double(OrderedFloat(try!(protocol.read_double(transport)))),
+            Type::String => Value::String(try!(protocol.read_binary(transport))),
+            Type::Struct => {
+                let mut fields = UnknownFields::default();
+                try!(protocol.read_struct_begin(transport));
+                loop {
+                    let (_, typ, id) = try!(protocol.read_field_begin(transport));
+                    if typ == Type::Stop {
+                        break;
+                    }
+                    let value = try!(Value::read_nested(protocol, transport, typ, depth));
+                    fields.fields.push((id, value));
+                    try!(protocol.read_field_end(transport));
+                }
+                try!(protocol.read_struct_end(transport));
+                Value::Struct(fields)
+            }
+            Type::Map => {
+                let (key_type, value_type, size) = try!(protocol.read_map_begin(transport));
+                let mut entries = Vec::new();
+                for _ in 0..size {
+                    let key = try!(Value::read_nested(protocol, transport, key_type, depth));
+                    let value = try!(Value::read_nested(protocol, transport, value_type, depth));
+                    entries.push((key, value));
+                }
+                try!(protocol.read_map_end(transport));
+                Value::Map(key_type, value_type, entries)
+            }
+            Type::Set => {
+                let (elem_type, size) = try!(protocol.read_set_begin(transport));
+                let mut elems = Vec::new();
+                for _ in 0..size {
+                    elems.push(try!(Value::read_nested(protocol, transport, elem_type, depth)));
+                }
+                try!(protocol.read_set_end(transport));
+                Value::Set(elem_type, elems)
+            }
+            Type::List => {
+                let (elem_type, size) = try!(protocol.read_list_begin(transport));
+                let mut elems = Vec::new();
+                for _ in 0..size {
+                    elems.push(try!(Value::read_nested(protocol, transport, elem_type, depth)));
+                }
+                try!(protocol.read_list_end(transport));
+                Value::List(elem_type, elems)
+            }
+            Type::Stop | Type::Void => return Err(Error::ProtocolViolation.into()),
+        })
+    }
+
+    /// Capture an encodable value, such as a reply seen by a client
+    /// interceptor, so that it can be kept after the value is gone.
+    pub fn from_encode<E: Encode>(value: &E) -> Result<Value> {
+        let mut transport = BufferTransport::default();
+        try!(value.encode(&mut BinaryProtocol, &mut transport));
+        let mut transport = BufferTransport::new(transport.into_output());
+        Value::read(&mut BinaryProtocol, &mut transport, value.typ())
+    }
+
+    /// Decode the value as `D`, which must have been declared with a
+    /// compatible type.
+    pub fn decode<D: Decode>(&self) -> Result<D> {
+        let mut transport = BufferTransport::default();
+        try!(self.write(&mut BinaryProtocol, &mut transport));
+        let mut transport = BufferTransport::new(transport.into_output());
+        let mut decoded = D::default();
+        try!(decoded.decode(&mut BinaryProtocol, &mut transport));
+        Ok(decoded)
+    }
+
+    pub fn write<P, T>(&self, protocol: &mut P, transport: &mut T) -> Result<()>
+    where P: Protocol, T: Transport {
+        match *self {
+            Value::Bool(v) => protocol.write_bool(transport, v),
+            Value::Byte(v) => protocol.write_byte(transport, v),
+            Value::I16(v) => protocol.write_i16(transport, v),
+            Value::I32(v) => protocol.write_i32(transport, v),
+            Value::I64(v) => protocol.write_i64(transport, v),
+            Value::Double(v) => protocol.write_double(transport, v.into_inner()),
+            Value::String(ref v) => protocol.write_binary(transport, v),
+            Value::Struct(ref fields) => {
+                try!(protocol.write_struct_begin(transport, ""));
+                try!(fields.write(protocol, transport));
+                try!(protocol.write_field_stop(transport));
+                protocol.write_struct_end(transport)
+            }
+            Value::Map(key_type, value_type, ref entries) => {
+                try!(protocol.write_map_begin(transport, key_type, value_type, entries.len()));
+                for &(ref key, ref value) in entries {
+                    try!(key.write(protocol, transport));
+                    try!(value.write(protocol, transport));
+                }
+                protocol.write_map_end(transport)
+            }
+            Value::Set(elem_type, ref elems) => {
+                try!(protocol.write_set_begin(transport, elem_type, elems.len()));
+                for elem in elems {
+                    try!(elem.write(protocol, transport));
+                }
+                protocol.write_set_end(transport)
+            }
+            Value::List(elem_type, ref elems) => {
+                try!(protocol.write_list_begin(transport, elem_type, elems.len()));
+                for elem in elems {
+                    try!(elem.write(protocol, transport));
+                }
+                protocol.write_list_end(transport)
+            }
+        }
+    }
+}
+
+/// The fields of a struct that its IDL does not declare, kept so they can
+/// be written back out unchanged.
+///
+/// Structs declared with `unknown_fields = true` collect them in a hidden
+/// `unknown_fields` member. They are kept in field id order and written back
+/// between the known fields by field id, so a struct whose fields were
+/// written in field id order, as generated code does, is written back
+/// byte for byte.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnknownFields {
+    fields: Vec<(i16, Value)>,
+}
+
+impl UnknownFields {
+    pub fn is_empty(&self) -> bool { self.fields.is_empty() }
+
+    pub fn len(&self) -> usize { self.fields.len() }
+
+    pub fn iter(&self) -> slice::Iter<'_, (i16, Value)> { self.fields.iter() }
+
+    pub fn get(&self, id: i16) -> Option<&Value> {
+        self.fields.iter().find(|&&(i, _)| i == id).map(|&(_, ref value)| value)
+    }
+
+    pub fn clear(&mut self) { self.fields.clear() }
+
+    /// Read the value of a field whose header has already been read.
+    pub fn read_field<P, T>(&mut self, protocol: &mut P, transport: &mut T, typ: Type, id: i16) -> Result<()>
+    where P: Protocol, T: Transport {
+        let value = try!(Value::read(protocol, transport, typ));
+        let at = self.fields.iter().rposition(|&(i, _)| i <= id).map_or(0, |at| at + 1);
+        self.fields.insert(at, (id, value));
+        Ok(())
+    }
+
+    /// Write every field, headers included, in field id order.
+    pub fn write<P, T>(&self, protocol: &mut P, transport: &mut T) -> Result<()>
+    where P: Protocol, T: Transport {
+        let mut written = 0;
+        self.write_before(protocol, transport, &mut written, None)
+    }
+
+    /// Write the fields from the `written`th on with an id below `id`, or all
+    /// of them without an `id`, to put them before the known field `id`.
+    pub fn write_before<P, T>(&self, protocol: &mut P, transport: &mut T,
+                              written: &mut usize, id: Option<i16>) -> Result<()>
+    where P: Protocol, T: Transport {
+        for &(i, ref value) in &self.fields[*written..] {
+            if id.map_or(false, |id| i >= id) {
+                break;
+            }
+            try!(protocol.write_field_begin(transport, "", value.typ(), i));
+            try!(value.write(protocol, transport));
+            try!(protocol.write_field_end(transport));
+            *written += 1;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+//! Static descriptions of the types declared with `strukt!` and `enom!` and
+//! the services declared with `service!`, for tools that walk values or make
+//! calls without knowing their types in advance.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use ordered_float::OrderedFloat;
+
+use protocol::Type;
+
+/// Implemented by every struct declared with `strukt!` or `exception!`.
+pub trait ThriftStruct {
+    fn descriptor() -> &'static StructDescriptor;
+}
+
+/// Implemented by every enum declared with `enom!`.
+pub trait ThriftEnum {
+    fn descriptor() -> &'static EnumDescriptor;
+}
+
+/// Implemented by the trait object, processor and clients of every service
+/// declared with `service!`.
+pub trait ThriftService {
+    fn descriptor() -> &'static ServiceDescriptor;
+}
+
+/// Maps a Rust type to the IDL type it stands for.
+pub trait Reflect {
+    const TYPE: TypeDescriptor;
+
+    /// The requiredness of a field of this type that the IDL does not mark
+    /// `required`: `Optional` for `Option<T>`, `Default` for all others.
+    const REQUIREDNESS: Requiredness = Requiredness::Default;
+}
+
+#[derive(Debug)]
+pub struct StructDescriptor {
+    pub name: &'static str,
+    pub fields: &'static [FieldDescriptor],
+}
+
+impl StructDescriptor {
+    pub fn field(&self, id: i16) -> Option<&'static FieldDescriptor> {
+        self.fields.iter().find(|field| field.id == id)
+    }
+
+    pub fn field_by_name(&self, name: &str) -> Option<&'static FieldDescriptor> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+#[derive(Debug)]
+pub struct FieldDescriptor {
+    pub id: i16,
+    pub name: &'static str,
+    pub typ: Type,
+    pub requiredness: Requiredness,
+    pub ty: TypeDescriptor,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Requiredness {
+    /// Marked `required` in the IDL, or `#[thrift(required)]`.
+    Required,
+    Optional,
+    /// Neither `required` nor `optional`: always written, but may be missing
+    /// when read.
+    Default,
+}
+
+#[derive(Debug)]
+pub struct EnumDescriptor {
+    pub name: &'static str,
+    /// The IDL name and number of every value, in declaration order.
+    pub values: &'static [(&'static str, i32)],
+}
+
+#[derive(Debug)]
+pub struct ServiceDescriptor {
+    pub name: &'static str,
+    /// The service this one extends.
+    pub parent: Option<fn() -> &'static ServiceDescriptor>,
+    /// The methods declared by this service, without inherited ones.
+    pub methods: &'static [MethodDescriptor],
+}
+
+impl ServiceDescriptor {
+    /// The services this one extends, nearest first.
+    pub fn parents(&'static self) -> Vec<&'static ServiceDescriptor> {
+        let mut parents = Vec::new();
+        let mut service = self;
+        while let Some(parent) = service.parent {
+            service = parent();
+            parents.push(service);
+        }
+        parents
+    }
+
+    /// Every method a processor for this service handles, inherited ones
+    /// last.
+    pub fn all_methods(&'static self) -> Vec<&'static MethodDescriptor> {
+        let mut methods: Vec<_> = self.methods.iter().collect();
+        for parent in self.parents() {
+            methods.extend(parent.methods);
+        }
+        methods
+    }
+
+    /// Look up a method by its IDL name, including inherited methods.
+    pub fn method(&'static self, name: &str) -> Option<&'static MethodDescriptor> {
+        self.all_methods().into_iter().find(|method| method.name == name)
+    }
+}
+
+#[derive(Debug)]
+pub struct MethodDescriptor {
+    pub name: &'static str,
+    pub args: &'static [FieldDescriptor],
+    pub returns: TypeDescriptor,
+    pub exceptions: &'static [FieldDescriptor],
+    pub oneway: bool,
+    /// Whether the IDL marks the method `(idempotent)`.
+    pub idempotent: bool,
+}
+
+#[derive(Copy, Clone)]
+pub enum TypeDescriptor {
+    Void,
+    Bool,
+    Byte,
+    I16,
+    I32,
+    I64,
+    Double,
+    String,
+    Binary,
+    Struct(fn() -> &'static StructDescriptor),
+    Enum(fn() -> &'static EnumDescriptor),
+    List(&'static TypeDescriptor),
+    Set(&'static TypeDescriptor),
+    Map(&'static TypeDescriptor, &'static TypeDescriptor),
+}
+
+impl TypeDescriptor {
+    /// The type used on the wire.
+    pub const fn typ(&self) -> Type {
+        match *self {
+            TypeDescriptor::Void => Type::Void,
+            TypeDescriptor::Bool => Type::Bool,
+            TypeDescriptor::Byte => Type::Byte,
+            TypeDescriptor::I16 => Type::I16,
+            TypeDescriptor::I32 | TypeDescriptor::Enum(_) => Type::I32,
+            TypeDescriptor::I64 => Type::I64,
+            TypeDescriptor::Double => Type::Double,
+            TypeDescriptor::String | TypeDescriptor::Binary => Type::String,
+            TypeDescriptor::Struct(_) => Type::Struct,
+            TypeDescriptor::List(_) => Type::List,
+            TypeDescriptor::Set(_) => Type::Set,
+            TypeDescriptor::Map(..) => Type::Map,
+        }
+    }
+}
+
+// Structs and enums are printed by name so that recursive types terminate.
+impl fmt::Debug for TypeDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TypeDescriptor::Struct(descriptor) => write!(f, "Struct({})", descriptor().name),
+            TypeDescriptor::Enum(descriptor) => write!(f, "Enum({})", descriptor().name),
+            TypeDescriptor::List(elem) => write!(f, "List({:?})", elem),
+            TypeDescriptor::Set(elem) => write!(f, "Set({:?})", elem),
+            TypeDescriptor::Map(key, value) => write!(f, "Map({:?}, {:?})", key, value),
+            TypeDescriptor::Void => f.write_str("Void"),
+            TypeDescriptor::Bool => f.write_str("Bool"),
+            TypeDescriptor::Byte => f.write_str("Byte"),
+            TypeDescriptor::I16 => f.write_str("I16"),
+            TypeDescriptor::I32 => f.write_str("I32"),
+            TypeDescriptor::I64 => f.write_str("I64"),
+            TypeDescriptor::Double => f.write_str("Double"),
+            TypeDescriptor::String => f.write_str("String"),
+            TypeDescriptor::Binary => f.write_str("Binary"),
+        }
+    }
+}
+
+impl Reflect for () { const TYPE: TypeDescriptor = TypeDescriptor::Void; }
+impl Reflect for bool { const TYPE: TypeDescriptor = TypeDescriptor::Bool; }
+impl Reflect for i8 { const TYPE: TypeDescriptor = TypeDescriptor::Byte; }
+impl Reflect for i16 { const TYPE: TypeDescriptor = TypeDescriptor::I16; }
+impl Reflect for i32 { const TYPE: TypeDescriptor = TypeDescriptor::I32; }
+impl Reflect for i64 { const TYPE: TypeDescriptor = TypeDescriptor::I64; }
+impl Reflect for f64 { const TYPE: TypeDescriptor = TypeDescriptor::Double; }
+impl Reflect for OrderedFloat<f64> { const TYPE: TypeDescriptor = TypeDescriptor::Double; }
+impl Reflect for String { const TYPE: TypeDescriptor = TypeDescriptor::String; }
+impl Reflect for Vec<u8> { const TYPE: TypeDescriptor = TypeDescriptor::Binary; }
+
+impl<T: Reflect> Reflect for Vec<T> {
+    const TYPE: TypeDescriptor = TypeDescriptor::List(&T::TYPE);
+}
+
+impl<T: Reflect> Reflect for BTreeSet<T> {
+    const TYPE: TypeDescriptor = TypeDescriptor::Set(&T::TYPE);
+}
+
+impl<K: Reflect, V: Reflect> Reflect for BTreeMap<K, V> {
+    const TYPE: TypeDescriptor = TypeDescriptor::Map(&K::TYPE, &V::TYPE);
+}
+
+impl<T: Reflect> Reflect for Option<T> {
+    const TYPE: TypeDescriptor = T::TYPE;
+    const REQUIREDNESS: Requiredness = Requiredness::Optional;
+}
@@ -0,0 +1,45 @@
+#![allow(non_upper_case_globals)]
+use std::collections::{BTreeMap, BTreeSet};
+
+use test::generated::*;
+
+consts! {
+    const answer: i32 = 42;
+    const greeting: &'static str = "hello";
+    const op: Operation = Operation::Clear;
+    static primes: Vec<i32> = vec![2, 3, 5, 7];
+    static names: BTreeMap<i32, String> = {
+        let mut m = BTreeMap::new();
+        m.insert(1, String::from("one"));
+        m.insert(2, String::from("two"));
+        m
+    };
+    static many: Many = {
+        let mut s = Many::default();
+        s.one = answer;
+        s.two = String::from(greeting);
+        s.three = vec![Simple { key: String::from("key") }];
+        s
+    };
+    static colors: BTreeSet<String> = {
+        let mut s = BTreeSet::new();
+        s.insert(String::from("red"));
+        s
+    };
+}
+
+#[test]
+fn test_scalar_consts() {
+    assert_eq!(answer, 42);
+    assert_eq!(greeting, "hello");
+    assert_eq!(op, Operation::Clear);
+}
+
+#[test]
+fn test_composite_consts() {
+    assert_eq!(*primes, vec![2, 3, 5, 7]);
+    assert_eq!(names[&2], "two");
+    assert_eq!(many.one, 42);
+    assert_eq!(many.three[0].key, "key");
+    assert!(colors.contains("red"));
+}
@@ -2,6 +2,11 @@ use test::*;
 use mock::*;
 use test::generated::*;
 
+use std::convert::TryFrom;
+
+use protocol::Decode;
+use EnumError;
+
 #[test]
 fn test_enum() {
     for op in vec![Operation::Add, Operation::Sub, Operation::Clear] {
@@ -18,3 +23,74 @@ fn test_enum() {
     assert_eq!(Operation::default(), Operation::Sub);
 }
 
+
+#[test]
+fn test_unknown_enum_value_is_an_error_by_default() {
+    let mut protocol = encode(&7i32);
+    let mut op = Operation::default();
+    assert!(op.decode(&mut protocol, &mut MockTransport::new(vec![])).is_err());
+}
+
+#[test]
+fn test_enum_with_unknown_variant() {
+    assert_eq!(decode::<Color>(&mut encode(&2i32)), Color::Green);
+
+    let mut protocol = encode(&7i32);
+    let color = decode::<Color>(&mut protocol);
+    assert_eq!(color, Color::Unknown(7));
+    assert_eq!(encode(&color).log(), &[Prim(I32(7))]);
+
+    let palette = Palette { colors: vec![Color::Red, Color::Unknown(-3)] };
+    assert_eq!(decode::<Palette>(&mut encode(&palette)), palette);
+}
+
+#[test]
+fn test_enum_names_and_values() {
+    assert_eq!(Operation::ALL, &[Operation::Add, Operation::Sub, Operation::Clear]);
+    assert_eq!(Operation::values().iter().map(Operation::as_i32).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert_eq!(Operation::Clear.name(), "Clear");
+    assert_eq!(Operation::Clear.to_string(), "Clear");
+    assert_eq!("Sub".parse::<Operation>(), Ok(Operation::Sub));
+    assert_eq!("sub".parse::<Operation>(),
+               Err(EnumError::UnknownName { enom: "Operation", name: String::from("sub") }));
+    assert_eq!(i32::from(Operation::Sub), 2);
+    assert_eq!(Operation::try_from(3), Ok(Operation::Clear));
+    assert_eq!(Operation::try_from(4), Err(EnumError::UnknownValue { enom: "Operation", value: 4 }));
+}
+
+#[test]
+fn test_enum_with_unknown_variant_names_and_values() {
+    assert_eq!(Color::ALL, &[Color::Red, Color::Green]);
+    assert_eq!(Color::Green.name(), "green");
+    assert_eq!("green".parse::<Color>(), Ok(Color::Green));
+    assert!("Green".parse::<Color>().is_err());
+    assert_eq!(Color::Unknown(9).to_string(), "Unknown(9)");
+    assert_eq!(Color::Unknown(9).as_i32(), 9);
+    assert_eq!(Color::from(1), Color::Red);
+    assert_eq!(Color::try_from(5), Ok(Color::Unknown(5)));
+}
+
+#[test]
+fn test_enum_with_unknown_variant_round_trips_through_strings() {
+    for color in vec![Color::Red, Color::Green, Color::Unknown(9), Color::Unknown(-3)] {
+        assert_eq!(color.to_string().parse::<Color>(), Ok(color));
+    }
+    assert!("Unknown(x)".parse::<Color>().is_err());
+    assert!("Unknown(9".parse::<Color>().is_err());
+}
+
+#[test]
+fn test_enum_with_unknown_variant_normalizes_known_values() {
+    use std::collections::HashSet;
+
+    assert_eq!(Color::from(1), Color::Red);
+    assert_eq!(decode::<Color>(&mut encode(&1i32)), Color::Red);
+    assert_eq!("Unknown(2)".parse::<Color>(), Ok(Color::Green));
+    assert_eq!("Unknown(2)".parse::<Color>().unwrap().to_string(), "green");
+
+    let set: HashSet<Color> = vec![Color::Red, Color::from(1), "Unknown(1)".parse().unwrap()].into_iter().collect();
+    assert_eq!(set.len(), 1);
+
+    let ops: HashSet<Operation> = Operation::ALL.iter().cloned().collect();
+    assert_eq!(ops.len(), 3);
+}
@@ -0,0 +1,47 @@
+use std::error::Error as StdError;
+
+use compiletest::*;
+use test::generated::Failure;
+use Error;
+
+fn operation() -> Result<i32, ServiceWithExceptionOperationError> {
+    Err(Exception { name: String::from("bad"), message: String::from("worse") })?
+}
+
+fn call() -> ::Result<i32> {
+    Ok(operation()?)
+}
+
+#[test]
+fn test_exception_is_error() {
+    let exception = Exception { name: String::from("bad"), message: String::from("worse") };
+    assert_eq!(exception.to_string(), r#"Exception(name: "bad", message: "worse")"#);
+    assert!(exception.source().is_none());
+}
+
+#[test]
+fn test_exception_displays_set_fields() {
+    let mut failure = Failure { code: 3, reason: None };
+    assert_eq!(failure.to_string(), "Failure(code: 3)");
+    failure.reason = Some(String::from("busy"));
+    assert_eq!(failure.to_string(), r#"Failure(code: 3, reason: "busy")"#);
+}
+
+#[test]
+fn test_error_enum_displays_variant() {
+    let err = operation().unwrap_err();
+    let ServiceWithExceptionOperationError::Bad(ref exception) = err;
+    assert_eq!(err.to_string(), exception.to_string());
+    assert_eq!(err.source().unwrap().to_string(), exception.to_string());
+}
+
+#[test]
+fn test_error_enum_into_thrift_error() {
+    match call() {
+        Err(Error::UserException(e)) => {
+            assert!(e.is::<ServiceWithExceptionOperationError>());
+            assert_eq!(e.source().unwrap().to_string(), r#"Exception(name: "bad", message: "worse")"#);
+        }
+        other => panic!("unexpected result {:?}", other)
+    }
+}
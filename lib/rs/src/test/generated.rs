@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 strukt! {
     name = Simple,
     fields = {
@@ -40,6 +42,74 @@ strukt! {
     }
 }
 
+strukt! {
+    name = Request,
+    builder = RequestBuilder,
+    fields = {
+        #[required] id: i64 => 1,
+        name: Option<String> => 2,
+        tags: Option<Vec<String>> => 3,
+        priority: i32 = 5 => 4,
+        attempts: i32 => 5,
+    }
+}
+
+strukt! {
+    name = Nothing,
+    builder = NothingBuilder,
+    fields = {}
+}
+
+exception! {
+    name = Failure,
+    builder = FailureBuilder,
+    fields = {
+        code: i32 => 1,
+        reason: Option<String> => 2,
+    }
+}
+
+strukt! {
+    name = Misfit,
+    fields = {
+        key: i64 => 16,
+    }
+}
+
+strukt! {
+    name = Record,
+    fields = {
+        #[required] id: i32 => 1,
+        name: String => 2,
+        tags: BTreeMap<String, Vec<i64>> => 3,
+        nested: Simple => 4,
+        flag: Option<bool> => 5,
+    }
+}
+
+strukt! {
+    name = OldRecord,
+    unknown_fields = true,
+    fields = {
+        id: i32 => 1,
+    }
+}
+
+strukt! {
+    name = SparseRecord,
+    unknown_fields = true,
+    fields = {
+        id: i32 => 1,
+        flag: Option<bool> => 5,
+    }
+}
+
+strukt! {
+    name = Opaque,
+    unknown_fields = true,
+    fields = {}
+}
+
 enom! {
     name = Operation,
     values = [
@@ -50,3 +120,52 @@ enom! {
     default = Sub
 }
 
+
+enom! {
+    name = Color,
+    values = [
+        Red = 1,
+        Green = 2 => "green",
+    ],
+    unknown = Unknown,
+    default = Red
+}
+
+strukt! {
+    name = Palette,
+    fields = {
+        colors: Vec<Color> => 1,
+    }
+}
+
+service! {
+    trait_name = Notifier,
+    processor_name = NotifierProcessor,
+    client_name = NotifierClient,
+    async_client_name = NotifierAsyncClient,
+    service_methods = [
+        NotifierNotifyArgs -> NotifierNotifyResult = notifier.notify(message: String => 1,) -> () => NotifierNotifyError = [] (()) oneway = true, idempotent = false, context = false,
+        NotifierPingArgs -> NotifierPingResult = notifier.ping() -> i32 => NotifierPingError = [] (i32) oneway = false, idempotent = false, context = false,
+        NotifierClearArgs -> NotifierClearResult = notifier.clear() -> () => NotifierClearError = [] (()) oneway = false, idempotent = false, context = false,
+        NotifierEchoArgs -> NotifierEchoResult = notifier.echo(message: String => 1, times: i32 = 2 => 2,) -> String => NotifierEchoError = [] (String) oneway = false, idempotent = true, context = false,
+    ],
+    parent_methods = [],
+    extends = [],
+    bounds = [N: Notifier,],
+    fields = [notifier: N,]
+}
+
+service! {
+    trait_name = Tracer,
+    processor_name = TracerProcessor,
+    client_name = TracerClient,
+    async_client_name = TracerAsyncClient,
+    service_methods = [
+        TracerWhoamiArgs -> TracerWhoamiResult = tracer.whoami() -> String => TracerWhoamiError = [] (String) oneway = false, idempotent = false, context = true,
+        TracerRecordArgs -> TracerRecordResult = tracer.record(event: String => 1,) -> () => TracerRecordError = [] (()) oneway = true, idempotent = false, context = true,
+    ],
+    parent_methods = [],
+    extends = [],
+    bounds = [R: Tracer,],
+    fields = [tracer: R,]
+}
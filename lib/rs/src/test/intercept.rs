@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use test::*;
+use test::generated::*;
+
+use client::{Call, Client, Interceptor, Interceptors, Pool};
+use client::intercept::Reply;
+use protocol::Value;
+use protocol::binary_protocol::BinaryProtocol;
+use transport::{Transport, DEADLINE_HEADER};
+use transport::framed::FramedTransport;
+use transport::server::TransportServer;
+use {Error, Processor, Result};
+
+/// Records the calls it sees.
+#[derive(Clone, Default)]
+struct Recorder {
+    calls: Arc<Mutex<Vec<(&'static str, i32, Value)>>>,
+    replies: Arc<Mutex<Vec<::std::result::Result<Option<Value>, String>>>>,
+}
+
+impl Interceptor for Recorder {
+    fn before(&self, call: &mut Call) -> Option<Result<Value>> {
+        let args = Value::from_encode(&call.args).unwrap();
+        self.calls.lock().unwrap().push((call.method, call.sequence_id, args));
+        None
+    }
+
+    fn after(&self, _: &Call, reply: Reply) {
+        let reply = match reply {
+            Ok(result) => Ok(result.map(|result| Value::from_encode(&result).unwrap())),
+            Err(e) => Err(e.to_string()),
+        };
+        self.replies.lock().unwrap().push(reply);
+    }
+}
+
+/// Answers pings itself.
+struct Fake(fn() -> Result<Value>);
+
+impl Interceptor for Fake {
+    fn before(&self, call: &mut Call) -> Option<Result<Value>> {
+        if call.method == "ping" { Some((self.0)()) } else { None }
+    }
+}
+
+fn client(interceptors: Interceptors) -> (PingerClient, PingerServer) {
+    let server = PingerServer::new();
+    let mut client = PingerClient::new(BinaryProtocol, server.accept().unwrap());
+    client.set_interceptors(interceptors);
+    (client, server)
+}
+
+fn ping_result(success: i32) -> Result<Value> {
+    let mut result = NotifierPingResult::default();
+    result.success = Some(success);
+    Value::from_encode(&result)
+}
+
+#[test]
+fn test_interceptor_sees_calls() {
+    let recorder = Recorder::default();
+    let mut interceptors = Interceptors::new();
+    interceptors.add(recorder.clone());
+    let (mut client, server) = client(interceptors);
+
+    assert_eq!(client.echo("hi".to_string(), 2).unwrap(), "hi");
+    client.notify("hello".to_string()).unwrap();
+    server.close(0);
+    assert!(client.ping().is_err());
+
+    let mut echo = NotifierEchoArgs::default();
+    echo.message = Some("hi".to_string());
+    echo.times = Some(2);
+    let mut notify = NotifierNotifyArgs::default();
+    notify.message = Some("hello".to_string());
+    assert_eq!(*recorder.calls.lock().unwrap(), vec![
+        ("echo", 1, Value::from_encode(&echo).unwrap()),
+        ("notify", 2, Value::from_encode(&notify).unwrap()),
+        ("ping", 3, Value::from_encode(&NotifierPingArgs::default()).unwrap()),
+    ]);
+
+    let mut echoed = NotifierEchoResult::default();
+    echoed.success = Some("hi".to_string());
+    let replies = recorder.replies.lock().unwrap();
+    assert_eq!(replies[0], Ok(Some(Value::from_encode(&echoed).unwrap())));
+    assert_eq!(replies[1], Ok(None));
+    assert!(replies[2].is_err());
+}
+
+#[test]
+fn test_interceptor_answers_call() {
+    let recorder = Recorder::default();
+    let mut interceptors = Interceptors::new();
+    interceptors.add(Fake(|| ping_result(3)));
+    interceptors.add(recorder.clone());
+    let (mut client, server) = client(interceptors);
+
+    assert_eq!(client.ping().unwrap(), 3);
+    assert_eq!(server.pings(), 0);
+    assert_eq!(client.last_call(), None);
+
+    // Only the interceptors before the one answering the call are asked.
+    assert!(recorder.calls.lock().unwrap().is_empty());
+    assert_eq!(*recorder.replies.lock().unwrap(), vec![Ok(Some(ping_result(3).unwrap()))]);
+}
+
+#[test]
+fn test_interceptor_fails_call() {
+    let mut interceptors = Interceptors::new();
+    interceptors.add(Fake(|| Err(Error::Timeout)));
+    let (mut client, server) = client(interceptors);
+
+    match client.ping() {
+        Err(Error::Timeout) => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+    assert!(!client.is_broken());
+    assert_eq!(client.echo("hi".to_string(), 1).unwrap(), "hi");
+    assert_eq!(server.pings(), 0);
+}
+
+/// A connection recording the headers sent with each request.
+struct WithHeaders {
+    connection: PingerConnection,
+    headers: Arc<Mutex<Vec<BTreeMap<String, String>>>>,
+}
+
+impl Read for WithHeaders {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> { self.connection.read(buf) }
+}
+
+impl Write for WithHeaders {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.connection.write(buf) }
+    fn flush(&mut self) -> io::Result<()> { self.connection.flush() }
+}
+
+impl Transport for WithHeaders {
+    fn set_headers(&mut self, headers: &BTreeMap<String, String>) -> io::Result<()> {
+        self.headers.lock().unwrap().push(headers.clone());
+        Ok(())
+    }
+}
+
+struct Tracing;
+
+impl Interceptor for Tracing {
+    fn before(&self, call: &mut Call) -> Option<Result<Value>> {
+        call.headers.insert("trace".to_string(), call.sequence_id.to_string());
+        None
+    }
+}
+
+#[test]
+fn test_interceptor_headers() {
+    let headers = Arc::new(Mutex::new(Vec::new()));
+    let transport = WithHeaders { connection: PingerServer::new().accept().unwrap(), headers: headers.clone() };
+    let mut client = NotifierClient::new(BinaryProtocol, transport);
+    let mut interceptors = Interceptors::new();
+    interceptors.add(Tracing);
+    client.set_interceptors(interceptors);
+
+    client.ping().unwrap();
+    client.ping().unwrap();
+    let headers = headers.lock().unwrap();
+    assert_eq!(headers.iter().map(|headers| headers["trace"].as_str()).collect::<Vec<_>>(), ["1", "2"]);
+}
+
+#[test]
+fn test_headers_dropped_by_transports_without_them() {
+    let server = PingerServer::new();
+    let mut client = NotifierClient::new(BinaryProtocol, server.accept().unwrap());
+    let mut interceptors = Interceptors::new();
+    interceptors.add(Tracing);
+    client.set_interceptors(interceptors);
+
+    assert_eq!(client.ping().unwrap(), 7);
+    assert!(!client.is_broken());
+    assert_eq!(client.ping().unwrap(), 7);
+    assert_eq!(server.pings(), 2);
+}
+
+#[test]
+fn test_framed_transport_carries_headers() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = PingerServer::new();
+    let processor = server.processor.clone();
+    let (sender, received) = mpsc::channel();
+    thread::spawn(move || {
+        let mut transport = FramedTransport::new(listener.accept().unwrap().0);
+        while processor.process(&mut BinaryProtocol, &mut transport).is_ok() {
+            sender.send(transport.headers().clone()).unwrap();
+        }
+    });
+
+    let transport = FramedTransport::new(TcpStream::connect(addr).unwrap());
+    let mut client = NotifierClient::new(BinaryProtocol, transport);
+    let mut interceptors = Interceptors::new();
+    interceptors.add(Tracing);
+    client.set_interceptors(interceptors);
+
+    assert_eq!(client.ping().unwrap(), 7);
+    assert_eq!(received.recv().unwrap()["trace"], "1");
+
+    client.set_timeout(Some(Duration::from_secs(5)));
+    assert_eq!(client.ping().unwrap(), 7);
+    let headers = received.recv().unwrap();
+    assert_eq!(headers["trace"], "2");
+    assert_eq!(headers[DEADLINE_HEADER], "5000");
+
+    // Headers only go with the message they were set for.
+    client.set_interceptors(Interceptors::new());
+    client.set_timeout(None);
+    assert_eq!(client.ping().unwrap(), 7);
+    assert!(received.recv().unwrap().is_empty());
+    assert_eq!(server.pings(), 3);
+}
+
+#[test]
+fn test_pool_interceptors() {
+    let recorder = Recorder::default();
+    let mut interceptors = Interceptors::new();
+    interceptors.add(recorder.clone());
+    let pool: Pool<PingerClient> = Pool::builder(|| BinaryProtocol, PingerServer::new())
+        .interceptors(interceptors)
+        .build()
+        .unwrap();
+
+    pool.get().unwrap().ping().unwrap();
+    assert_eq!(recorder.calls.lock().unwrap().len(), 1);
+}
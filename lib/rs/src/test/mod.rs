@@ -1,10 +1,27 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use protocol::{Type, Encode, Decode};
+use protocol::binary_protocol::BinaryProtocol;
+use transport::server::TransportServer;
 use mock::*;
+use self::generated::{Notifier, NotifierClient, NotifierProcessor};
 
 mod prim;
 mod strukt;
 mod enom;
 mod generated;
+mod exception;
+mod consts;
+mod service;
+#[cfg(feature = "nonblocking")]
+mod nonblocking;
+mod pool;
+mod reconnect;
+mod timeout;
+mod pipeline;
+mod intercept;
 
 pub fn encode<T: Encode>(x: &T) -> MockProtocol {
     let mut protocol = MockProtocol::new();
@@ -23,3 +40,61 @@ pub fn field_end() -> ProtocolAction {
     Field(Begin((String::new(), Type::Stop, 0)))
 }
 
+
+/// A `Notifier` counting the pings it answers.
+pub struct Pinger {
+    pings: Arc<AtomicUsize>,
+}
+
+impl Notifier for Pinger {
+    fn notify(&self, _: String) {}
+    fn ping(&self) -> i32 { self.pings.fetch_add(1, Ordering::SeqCst); 7 }
+    fn clear(&self) {}
+    fn echo(&self, message: String, _: i32) -> String { message }
+}
+
+pub type PingerConnection = Loopback<NotifierProcessor<Pinger>>;
+pub type PingerClient = NotifierClient<BinaryProtocol, PingerConnection>;
+
+/// Opens loopback connections to a `Pinger`, keeping their switches.
+#[derive(Clone)]
+pub struct PingerServer {
+    processor: Arc<NotifierProcessor<Pinger>>,
+    pings: Arc<AtomicUsize>,
+    connections: Arc<Mutex<Vec<Arc<Switches>>>>,
+}
+
+impl PingerServer {
+    pub fn new() -> Self {
+        let pings = Arc::new(AtomicUsize::new(0));
+        PingerServer {
+            processor: Arc::new(NotifierProcessor::new(Pinger { pings: pings.clone() })),
+            pings: pings,
+            connections: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn pings(&self) -> usize { self.pings.load(Ordering::SeqCst) }
+
+    pub fn connections(&self) -> usize { self.connections.lock().unwrap().len() }
+
+    /// Make a connection fail like a closed socket.
+    pub fn close(&self, connection: usize) {
+        self.connections.lock().unwrap()[connection].closed.store(true, Ordering::SeqCst);
+    }
+
+    /// Stop replying on a connection.
+    pub fn silence(&self, connection: usize) {
+        self.connections.lock().unwrap()[connection].silent.store(true, Ordering::SeqCst);
+    }
+}
+
+impl TransportServer for PingerServer {
+    type Transport = PingerConnection;
+
+    fn accept(&self) -> io::Result<PingerConnection> {
+        let connection = Loopback::new(self.processor.clone());
+        self.connections.lock().unwrap().push(connection.switches.clone());
+        Ok(connection)
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::future::{self, Future};
+use std::io;
+use std::net::{self, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::Poll;
+use std::thread;
+use std::time::Duration;
+
+use tokio::io::{duplex, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::runtime::{Builder, Runtime};
+
+use nonblocking::{self, Server};
+use processor::RequestContext;
+use protocol::binary_protocol::BinaryProtocol;
+use test::generated::*;
+use transport::Transport;
+use transport::framed::FramedTransport;
+use Error;
+
+fn runtime() -> Runtime {
+    Builder::new_current_thread().enable_io().build().unwrap()
+}
+
+#[derive(Clone, Default)]
+struct Shared {
+    notified: Arc<Mutex<Vec<String>>>,
+    contexts: Arc<Mutex<Vec<RequestContext>>>,
+}
+
+impl Notifier for Shared {
+    fn notify(&self, message: String) {
+        self.notified.lock().unwrap().push(message);
+    }
+
+    fn ping(&self) -> i32 { 7 }
+
+    fn clear(&self) {
+        self.notified.lock().unwrap().clear();
+    }
+
+    fn echo(&self, message: String, times: i32) -> String {
+        (0..times).map(|_| &message[..]).collect()
+    }
+}
+
+impl Tracer for Shared {
+    fn whoami(&self, context: &RequestContext) -> String {
+        self.contexts.lock().unwrap().push(context.clone());
+        format!("{}#{}", context.method, context.sequence_id)
+    }
+
+    fn record(&self, context: &RequestContext, _event: String) {
+        self.contexts.lock().unwrap().push(context.clone());
+    }
+}
+
+#[test]
+fn test_frames_round_trip() {
+    let runtime = runtime();
+    let (mut writer, mut reader) = duplex(1024);
+    runtime.block_on(nonblocking::write_frame(&mut writer, vec![1, 2, 3])).unwrap();
+    runtime.block_on(nonblocking::write_frame(&mut writer, vec![])).unwrap();
+    runtime.block_on(nonblocking::write_frame(&mut writer, vec![4; 20])).unwrap();
+    drop(writer);
+
+    assert_eq!(runtime.block_on(nonblocking::read_frame(&mut reader)).unwrap(), Some(vec![1, 2, 3]));
+    assert_eq!(runtime.block_on(nonblocking::read_frame(&mut reader)).unwrap(), Some(vec![]));
+    assert_eq!(runtime.block_on(nonblocking::read_frame(&mut reader)).unwrap(), Some(vec![4; 20]));
+    assert_eq!(runtime.block_on(nonblocking::read_frame(&mut reader)).unwrap(), None);
+}
+
+#[test]
+fn test_read_frame_rejects_bad_sizes() {
+    let runtime = runtime();
+    let (mut writer, mut reader) = duplex(64);
+    runtime.block_on(writer.write_all(&[0xff, 0xff, 0xff, 0xff])).unwrap();
+    let err = runtime.block_on(nonblocking::read_frame(&mut reader)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let (mut writer, mut reader) = duplex(64);
+    runtime.block_on(writer.write_all(&[0, 0, 0, 4, 1])).unwrap();
+    drop(writer);
+    let err = runtime.block_on(nonblocking::read_frame(&mut reader)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_async_client_and_server() {
+    let handler = Shared::default();
+    let server = Server::new(NotifierProcessor::new(handler.clone()), || BinaryProtocol);
+    let runtime = runtime();
+    let (client_side, server_side) = duplex(1024);
+    let connection = runtime.spawn(server.serve_connection(server_side));
+
+    let mut client = NotifierAsyncClient::new(BinaryProtocol, client_side);
+    assert_eq!(runtime.block_on(client.ping()).unwrap(), 7);
+    runtime.block_on(client.notify(String::from("hello"))).unwrap();
+    assert_eq!(runtime.block_on(client.echo(String::from("ab"), 3)).unwrap(), "ababab");
+    drop(client);
+
+    runtime.block_on(connection).unwrap().unwrap();
+    assert_eq!(*handler.notified.lock().unwrap(), vec![String::from("hello")]);
+}
+
+/// Answers pings once two of them are waiting, or gives up after a while.
+#[derive(Clone, Default)]
+struct Rendezvous {
+    waiting: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl Notifier for Rendezvous {
+    fn notify(&self, _: String) {}
+
+    fn ping(&self) -> i32 {
+        let (ref waiting, ref arrived) = *self.waiting;
+        let mut waiting = waiting.lock().unwrap();
+        *waiting += 1;
+        arrived.notify_all();
+        let (waiting, _) = arrived.wait_timeout_while(waiting, Duration::from_secs(5), |waiting| *waiting < 2).unwrap();
+        *waiting as i32
+    }
+
+    fn clear(&self) {}
+
+    fn echo(&self, message: String, _: i32) -> String { message }
+}
+
+#[test]
+fn test_async_server_handlers_may_block() {
+    let server = Server::new(NotifierProcessor::new(Rendezvous::default()), || BinaryProtocol);
+    let runtime = runtime();
+    let mut clients = (0..2).map(|_| {
+        let (client_side, server_side) = duplex(1024);
+        runtime.spawn(server.serve_connection(server_side));
+        NotifierAsyncClient::new(BinaryProtocol, client_side)
+    }).collect::<Vec<_>>();
+
+    // Both pings are only answered if the first one does not hold up the
+    // only thread of the runtime while it waits for the second.
+    let mut calls = clients.iter_mut().map(|client| Box::pin(client.ping())).collect::<Vec<_>>();
+    let mut pongs = vec![None, None];
+    runtime.block_on(future::poll_fn(|cx| {
+        for (call, pong) in calls.iter_mut().zip(pongs.iter_mut()) {
+            if pong.is_none() {
+                if let Poll::Ready(result) = call.as_mut().poll(cx) {
+                    *pong = Some(result.unwrap());
+                }
+            }
+        }
+        if pongs.iter().all(Option::is_some) { Poll::Ready(()) } else { Poll::Pending }
+    }));
+    assert_eq!(pongs, vec![Some(2), Some(2)]);
+}
+
+#[test]
+fn test_dropped_async_call_breaks_client() {
+    let runtime = runtime();
+    // Nothing answers on the other side.
+    let (client_side, _server_side) = duplex(1024);
+    let mut client = NotifierAsyncClient::new(BinaryProtocol, client_side);
+
+    {
+        // Give up on the call after writing it, like a timeout would.
+        let mut call = Box::pin(client.ping());
+        let pending = runtime.block_on(future::poll_fn(|cx| Poll::Ready(call.as_mut().poll(cx).is_pending())));
+        assert!(pending);
+    }
+    assert!(client.is_broken());
+
+    match runtime.block_on(client.ping()) {
+        Err(Error::TransportError(ref e)) if e.kind() == io::ErrorKind::NotConnected => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_completed_async_calls_keep_client() {
+    let server = Server::new(NotifierProcessor::new(Shared::default()), || BinaryProtocol);
+    let runtime = runtime();
+    let (client_side, server_side) = duplex(1024);
+    runtime.spawn(server.serve_connection(server_side));
+
+    let mut client = NotifierAsyncClient::new(BinaryProtocol, client_side);
+    assert_eq!(runtime.block_on(client.ping()).unwrap(), 7);
+    runtime.block_on(client.clear()).unwrap();
+    assert!(!client.is_broken());
+}
+
+#[test]
+fn test_blocking_framed_client_against_async_server() {
+    let handler = Shared::default();
+    let server = Server::new(TracerProcessor::new(handler.clone()), || BinaryProtocol);
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    listener.set_nonblocking(true).unwrap();
+
+    thread::spawn(move || {
+        let runtime = runtime();
+        let listener = {
+            let _entered = runtime.enter();
+            TcpListener::from_std(listener).unwrap()
+        };
+        runtime.block_on(server.serve(listener)).unwrap();
+    });
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let local = stream.local_addr().unwrap();
+    let mut client = TracerClient::new(BinaryProtocol, FramedTransport::new(stream));
+    client.record(String::from("login")).unwrap();
+    let mut headers = BTreeMap::new();
+    headers.insert(String::from("user"), String::from("alice"));
+    client.transport.set_headers(&headers).unwrap();
+    assert_eq!(client.whoami().unwrap(), "whoami#2");
+
+    let contexts = handler.contexts.lock().unwrap();
+    assert_eq!(contexts.len(), 2);
+    assert_eq!(contexts[1].peer_addr, Some(local));
+    assert_eq!(contexts[1].connection_id, contexts[0].connection_id);
+    assert!(contexts[0].headers.is_empty());
+    assert_eq!(contexts[1].headers, headers);
+}
@@ -0,0 +1,144 @@
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use test::PingerServer;
+use test::generated::*;
+
+use client::{Pipelined, PipelinedTransport};
+use protocol::binary_protocol::BinaryProtocol;
+use transport::Transport;
+use {Error, Processor};
+
+type PipelinedClient = Pipelined<NotifierClient<BinaryProtocol, PipelinedTransport>>;
+
+/// The server end of a connection, collecting replies instead of sending
+/// them.
+struct Replies {
+    stream: TcpStream,
+    output: Vec<u8>,
+}
+
+impl Read for Replies {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> { self.stream.read(buf) }
+}
+
+impl Write for Replies {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.output.write(buf) }
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+impl Transport for Replies {}
+
+/// A server taking one connection, which answers calls `batch` at a time in
+/// reverse order.
+fn reversing_server(server: PingerServer, batch: usize) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut transport = Replies { stream: stream, output: Vec::new() };
+        let mut replies = Vec::new();
+        while server.processor.process(&mut BinaryProtocol, &mut transport).is_ok() {
+            // Oneway calls have no reply.
+            if !transport.output.is_empty() {
+                replies.push(::std::mem::replace(&mut transport.output, Vec::new()));
+            }
+            if replies.len() == batch {
+                for reply in replies.drain(..).rev() {
+                    transport.stream.write_all(&reply).unwrap();
+                }
+            }
+        }
+    });
+
+    addr
+}
+
+fn connect(addr: SocketAddr) -> PipelinedClient {
+    Pipelined::tcp(|| BinaryProtocol, TcpStream::connect(addr).unwrap()).unwrap()
+}
+
+#[test]
+fn test_replies_matched_to_callers() {
+    let client = Arc::new(connect(reversing_server(PingerServer::new(), 4)));
+
+    let callers: Vec<_> = (0..4).map(|i| {
+        let client = client.clone();
+        thread::spawn(move || client.call(|client| client.echo(format!("call {}", i), 1)).unwrap())
+    }).collect();
+
+    for (i, caller) in callers.into_iter().enumerate() {
+        assert_eq!(caller.join().unwrap(), format!("call {}", i));
+    }
+}
+
+#[test]
+fn test_oneway_call() {
+    let server = PingerServer::new();
+    let client = connect(reversing_server(server.clone(), 1));
+
+    client.call(|client| client.notify("hello".to_string())).unwrap();
+    assert_eq!(client.call(|client| client.ping()).unwrap(), 7);
+    assert_eq!(server.pings(), 1);
+}
+
+#[test]
+fn test_one_call_per_client() {
+    let server = PingerServer::new();
+    let client = connect(reversing_server(server.clone(), 1));
+
+    match client.call(|client| { try!(client.ping()); client.ping() }) {
+        Err(Error::TransportError(ref e)) if e.kind() == io::ErrorKind::InvalidInput => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+    assert_eq!(server.pings(), 1);
+}
+
+#[test]
+fn test_late_reply_dropped_after_timeout() {
+    let client = connect(reversing_server(PingerServer::new(), 2))
+        .timeout(Duration::from_millis(100));
+
+    // The server holds the first reply back until it has a second call.
+    match client.call(|client| client.echo("first".to_string(), 1)) {
+        Err(Error::Timeout) => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+    assert_eq!(client.call(|client| client.echo("second".to_string(), 1)).unwrap(), "second");
+}
+
+#[test]
+fn test_closed_connection_fails_calls() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = connect(listener.local_addr().unwrap());
+    drop(listener.accept().unwrap());
+
+    for _ in 0..2 {
+        match client.call(|client| client.ping()) {
+            Err(Error::TransportError(_)) => {}
+            other => panic!("unexpected result {:?}", other)
+        }
+    }
+}
+
+#[test]
+fn test_drop_closes_connection_and_stops_thread() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = connect(listener.local_addr().unwrap());
+    let (mut accepted, _) = listener.accept().unwrap();
+
+    // Dropping waits for the reading thread to stop.
+    let (dropped, done) = mpsc::channel();
+    thread::spawn(move || {
+        drop(client);
+        dropped.send(()).unwrap();
+    });
+    done.recv_timeout(Duration::from_secs(5)).unwrap();
+
+    accepted.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    assert_eq!(accepted.read(&mut [0; 16]).unwrap(), 0);
+}
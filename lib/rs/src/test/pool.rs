@@ -0,0 +1,164 @@
+use std::io;
+use std::panic;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use test::*;
+
+use client::{Client, Pool, PoolBuilder};
+use protocol::binary_protocol::BinaryProtocol;
+use BuildError;
+
+fn pool() -> (Pool<PingerClient>, PingerServer) {
+    let server = PingerServer::new();
+    (Pool::new(|| BinaryProtocol, server.clone()), server)
+}
+
+fn builder() -> (PoolBuilder<PingerClient>, PingerServer) {
+    let server = PingerServer::new();
+    (Pool::builder(|| BinaryProtocol, server.clone()), server)
+}
+
+#[test]
+fn test_pool_reuses_idle_clients() {
+    let (pool, server) = pool();
+
+    assert_eq!(pool.get().unwrap().ping().unwrap(), 7);
+    assert_eq!(pool.get().unwrap().ping().unwrap(), 7);
+    assert_eq!(server.connections(), 1);
+    assert_eq!((pool.size(), pool.idle()), (1, 1));
+
+    let first = pool.get().unwrap();
+    let second = pool.get().unwrap();
+    assert_eq!((pool.size(), pool.idle()), (2, 0));
+    drop((first, second));
+    assert_eq!((pool.size(), pool.idle()), (2, 2));
+}
+
+#[test]
+fn test_pool_waits_at_max_size() {
+    let (builder, server) = builder();
+    let pool = Arc::new(builder.max_size(1).build().unwrap());
+
+    let mut client = pool.get().unwrap();
+    let waiting = {
+        let pool = pool.clone();
+        thread::spawn(move || pool.get().unwrap().ping().unwrap())
+    };
+
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(client.ping().unwrap(), 7);
+    drop(client);
+
+    assert_eq!(waiting.join().unwrap(), 7);
+    assert_eq!(server.connections(), 1);
+}
+
+#[test]
+fn test_pool_evicts_broken_clients() {
+    let (pool, server) = pool();
+
+    let mut client = pool.get().unwrap();
+    server.close(0);
+    assert!(client.ping().unwrap_err().breaks_connection());
+    assert!(client.is_broken());
+    drop(client);
+    assert_eq!(pool.size(), 0);
+
+    assert_eq!(pool.get().unwrap().ping().unwrap(), 7);
+    assert_eq!(server.connections(), 2);
+
+    pool.get().unwrap().discard();
+    assert_eq!(pool.size(), 0);
+}
+
+#[test]
+fn test_pool_health_check() {
+    let (builder, server) = builder();
+    let pool = builder.health_check(|client: &mut PingerClient| client.ping().is_ok()).build().unwrap();
+
+    drop(pool.get().unwrap());
+    server.close(0);
+
+    assert_eq!(pool.get().unwrap().ping().unwrap(), 7);
+    assert_eq!(server.connections(), 2);
+    assert_eq!(pool.size(), 1);
+}
+
+#[test]
+fn test_pool_panicking_health_check() {
+    let (builder, _server) = builder();
+    let pool = builder.health_check(|_: &mut PingerClient| panic!("unhealthy")).build().unwrap();
+
+    drop(pool.get().unwrap());
+    assert_eq!(pool.size(), 1);
+    assert!(panic::catch_unwind(panic::AssertUnwindSafe(|| pool.get())).is_err());
+    assert_eq!((pool.size(), pool.idle()), (0, 0));
+}
+
+#[test]
+fn test_pool_min_size_and_idle_timeout() {
+    let (builder, server) = builder();
+    let pool = builder.min_size(2).idle_timeout(Duration::from_millis(10)).build().unwrap();
+
+    pool.fill().unwrap();
+    assert_eq!((pool.size(), pool.idle()), (2, 2));
+
+    let extra = (pool.get().unwrap(), pool.get().unwrap(), pool.get().unwrap());
+    drop(extra);
+    assert_eq!((pool.size(), pool.idle()), (3, 3));
+
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(pool.get().unwrap().ping().unwrap(), 7);
+    assert_eq!((pool.size(), pool.idle()), (2, 2));
+    assert_eq!(server.connections(), 3);
+}
+
+#[test]
+fn test_pool_reports_connect_errors() {
+    let pool: Pool<PingerClient> = Pool::new(|| BinaryProtocol, || -> io::Result<_> {
+        Err(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"))
+    });
+
+    match pool.get() {
+        Err(::Error::TransportError(ref e)) if e.kind() == io::ErrorKind::ConnectionRefused => {}
+        Err(e) => panic!("unexpected error {:?}", e),
+        Ok(_) => panic!("connected"),
+    }
+    assert_eq!(pool.size(), 0);
+}
+
+#[test]
+fn test_pool_maintains_itself() {
+    let (builder, server) = builder();
+    let pool = builder.min_size(1).idle_timeout(Duration::from_millis(10)).build().unwrap();
+
+    // Filled without a call to `fill`...
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!((pool.size(), pool.idle()), (1, 1));
+
+    // ...closed when idle without a call to `get`...
+    drop((pool.get().unwrap(), pool.get().unwrap()));
+    assert_eq!(pool.size(), 2);
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!((pool.size(), pool.idle()), (1, 1));
+
+    // ...and refilled after a connection is discarded.
+    pool.get().unwrap().discard();
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!((pool.size(), pool.idle()), (1, 1));
+    assert_eq!(server.connections(), 3);
+}
+
+#[test]
+fn test_pool_rejects_invalid_sizes() {
+    match builder().0.max_size(0).build() {
+        Err(BuildError::InvalidValue { strukt: "Pool", field: "max_size", .. }) => {}
+        _ => panic!("built a pool without connections"),
+    }
+    match builder().0.min_size(3).max_size(2).build() {
+        Err(BuildError::InvalidValue { strukt: "Pool", field: "min_size", .. }) => {}
+        _ => panic!("built a pool with a minimum above its maximum"),
+    }
+}
@@ -0,0 +1,117 @@
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use test::*;
+
+use client::{Client, LastCall, Reconnecting};
+use protocol::binary_protocol::BinaryProtocol;
+
+fn reconnecting() -> (Reconnecting<PingerClient>, PingerServer) {
+    let server = PingerServer::new();
+    let client = Reconnecting::new(|| BinaryProtocol, server.clone())
+        .backoff(Duration::from_millis(1), Duration::from_millis(2));
+    (client, server)
+}
+
+fn ping(client: &mut PingerClient) -> ::Result<i32> {
+    client.ping()
+}
+
+#[test]
+fn test_reconnects_after_broken_connection() {
+    let (mut client, server) = reconnecting();
+    assert!(client.client().is_none());
+    assert_eq!(client.call(ping).unwrap(), 7);
+
+    server.close(0);
+    assert!(client.call(ping).unwrap_err().breaks_connection());
+    assert_eq!(server.connections(), 1);
+
+    assert_eq!(client.call(ping).unwrap(), 7);
+    assert_eq!(server.connections(), 2);
+    assert_eq!(server.pings(), 2);
+}
+
+#[test]
+fn test_retries_unsent_idempotent_calls() {
+    let (client, server) = reconnecting();
+    let mut client = client.idempotent(&["ping"]);
+    assert_eq!(client.call(ping).unwrap(), 7);
+
+    server.close(0);
+    assert_eq!(client.call(ping).unwrap(), 7);
+    assert_eq!(server.connections(), 2);
+    assert_eq!(server.pings(), 2);
+
+    // Other methods are not retried.
+    server.close(1);
+    assert!(client.call(|client: &mut PingerClient| client.clear()).is_err());
+    assert_eq!(server.connections(), 2);
+}
+
+#[test]
+fn test_retries_methods_marked_idempotent() {
+    let (mut client, server) = reconnecting();
+    let mut echo = |client: &mut PingerClient| client.echo(String::from("again"), 1);
+    assert_eq!(client.call(&mut echo).unwrap(), "again");
+
+    server.close(0);
+    assert_eq!(client.call(&mut echo).unwrap(), "again");
+    assert_eq!(client.client().unwrap().last_call(), Some(LastCall { method: "echo", sent: true, idempotent: true }));
+    assert_eq!(server.connections(), 2);
+
+    // Methods without the annotation are not.
+    server.close(1);
+    assert!(client.call(ping).is_err());
+    assert_eq!(server.connections(), 2);
+}
+
+#[test]
+fn test_does_not_retry_sent_calls() {
+    let (client, server) = reconnecting();
+    let mut client = client.idempotent(&["ping"]);
+    assert_eq!(client.call(ping).unwrap(), 7);
+
+    server.silence(0);
+    assert!(client.call(ping).is_err());
+    assert_eq!(client.client().unwrap().last_call(), Some(LastCall { method: "ping", sent: true, idempotent: false }));
+    assert_eq!(server.connections(), 1);
+    assert_eq!(server.pings(), 2);
+}
+
+#[test]
+fn test_retries_connecting() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let server = PingerServer::new();
+    let connect = {
+        let attempts = attempts.clone();
+        move || -> io::Result<PingerConnection> {
+            if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"))
+            } else {
+                server.accept()
+            }
+        }
+    };
+
+    let mut client: Reconnecting<PingerClient> = Reconnecting::new(|| BinaryProtocol, connect)
+        .backoff(Duration::from_millis(1), Duration::from_millis(2));
+    assert_eq!(client.call(ping).unwrap(), 7);
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+    attempts.store(0, Ordering::SeqCst);
+    client.client().unwrap().transport.switches.closed.store(true, Ordering::SeqCst);
+    let mut client = client.retries(1);
+    match client.call(ping) {
+        Err(::Error::TransportError(ref e)) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+    assert_eq!(attempts.load(Ordering::SeqCst), 0);
+    match client.call(ping) {
+        Err(::Error::TransportError(ref e)) if e.kind() == io::ErrorKind::ConnectionRefused => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
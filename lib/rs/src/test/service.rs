@@ -0,0 +1,568 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use test::*;
+use mock::*;
+use test::generated::*;
+use compiletest::{self, ChildService, ServiceWithExceptionClient, ServiceWithExceptionOperationError};
+
+use protocol::{MessageType, Type};
+use protocol::binary_protocol::BinaryProtocol;
+use {Processor, Protocol, Error, ApplicationException, ApplicationExceptionKind};
+use processor::{MissingArguments, RequestContext};
+use reflect::{Requiredness, ThriftService, TypeDescriptor};
+use transport::{BufferTransport, Transport, DEADLINE_HEADER};
+use transport::framed::FramedTransport;
+use transport::server::{Connections, TransportServer};
+use protocol;
+
+#[derive(Default)]
+struct Handler {
+    notified: RefCell<Vec<String>>
+}
+
+impl<'a> Notifier for &'a Handler {
+    fn notify(&self, message: String) {
+        self.notified.borrow_mut().push(message);
+    }
+
+    fn ping(&self) -> i32 { 7 }
+
+    fn clear(&self) {
+        self.notified.borrow_mut().clear();
+    }
+
+    fn echo(&self, message: String, times: i32) -> String {
+        (0..times).map(|_| &message[..]).collect()
+    }
+}
+
+fn message<E: Encode>(name: &str, ty: MessageType, id: i32, args: &E) -> MockProtocol {
+    let mut protocol = MockProtocol::new();
+    let mut transport = MockTransport::new(vec![]);
+    protocol.write_message_begin(&mut transport, name, ty, id).unwrap();
+    args.encode(&mut protocol, &mut transport).unwrap();
+    protocol.write_message_end(&mut transport).unwrap();
+    protocol
+}
+
+#[test]
+fn test_client_oneway_does_not_wait_for_reply() {
+    let mut client = NotifierClient::new(MockProtocol::new(), MockTransport::new(vec![]));
+    client.notify(String::from("hello")).unwrap();
+
+    assert_eq!(client.protocol.log(), &[
+        Message(Begin((String::from("notify"), MessageType::Oneway, 1))),
+        Struct(Begin(String::from("NotifierNotifyArgs"))),
+        Field(Begin((String::from("message"), Type::String, 1))),
+        Prim(PString(String::from("hello"))),
+        Field(End),
+        field_end(),
+        Struct(End),
+        Message(End)
+    ]);
+}
+
+#[test]
+fn test_processor_oneway_does_not_reply() {
+    let handler = Handler::default();
+    let processor = NotifierProcessor::new(&handler);
+    let args = NotifierNotifyArgs { message: Some(String::from("hello")) };
+    let mut protocol = message("notify", MessageType::Oneway, 3, &args);
+
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+
+    assert_eq!(*handler.notified.borrow(), vec![String::from("hello")]);
+    assert_eq!(protocol.log(), &[]);
+}
+
+#[test]
+fn test_processor_call_replies() {
+    let handler = Handler::default();
+    let processor = NotifierProcessor::new(&handler);
+    let mut protocol = message("ping", MessageType::Call, 3, &NotifierPingArgs);
+
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+
+    assert_eq!(protocol.log(), &[
+        Message(Begin((String::from("ping"), MessageType::Reply, 3))),
+        Struct(Begin(String::from("NotifierPingResult"))),
+        Field(Begin((String::from("success"), Type::I32, 0))),
+        Prim(I32(7)),
+        Field(End),
+        field_end(),
+        Struct(End),
+        Message(End)
+    ]);
+}
+
+fn ping_reply(name: &str, ty: MessageType, id: i32) -> MockProtocol {
+    message(name, ty, id, &NotifierPingResult { success: Some(7) })
+}
+
+#[test]
+fn test_client_increments_sequence_id() {
+    let mut client = NotifierClient::new(ping_reply("ping", MessageType::Reply, 1),
+                                         MockTransport::new(vec![]));
+    assert_eq!(client.ping().unwrap(), 7);
+    client.notify(String::from("hello")).unwrap();
+
+    let ids: Vec<i32> = client.protocol.log().iter().filter_map(|action| match *action {
+        Message(Begin((_, _, id))) => Some(id),
+        _ => None
+    }).collect();
+    assert_eq!(ids, vec![1, 2]);
+}
+
+#[test]
+fn test_client_rejects_bad_sequence_id() {
+    let mut client = NotifierClient::new(ping_reply("ping", MessageType::Reply, 5),
+                                         MockTransport::new(vec![]));
+    match client.ping() {
+        Err(Error::ProtocolError(protocol::Error::BadSequenceId { expected: 1, received: 5 })) => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_client_rejects_wrong_method_name() {
+    let mut client = NotifierClient::new(ping_reply("pong", MessageType::Reply, 1),
+                                         MockTransport::new(vec![]));
+    match client.ping() {
+        Err(Error::ProtocolError(protocol::Error::WrongMethodName { ref expected, ref received }))
+            if expected == "ping" && received == "pong" => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_client_rejects_call_message() {
+    let mut client = NotifierClient::new(ping_reply("ping", MessageType::Call, 1),
+                                         MockTransport::new(vec![]));
+    match client.ping() {
+        Err(Error::ProtocolError(protocol::Error::InvalidMessageType(MessageType::Call))) => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_client_rejects_exception_for_another_call() {
+    let exception = ApplicationException::new(ApplicationExceptionKind::InternalError, "oops");
+    let mut client = NotifierClient::new(message("ping", MessageType::Exception, 4, &exception),
+                                         MockTransport::new(vec![]));
+    match client.ping() {
+        Err(Error::ProtocolError(protocol::Error::BadSequenceId { expected: 1, received: 4 })) => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+
+    let mut client = NotifierClient::new(message("echo", MessageType::Exception, 1, &exception),
+                                         MockTransport::new(vec![]));
+    match client.ping() {
+        Err(Error::ProtocolError(protocol::Error::WrongMethodName { ref expected, ref received }))
+            if expected == "ping" && received == "echo" => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_processor_rejects_reply_message() {
+    let handler = Handler::default();
+    let processor = NotifierProcessor::new(&handler);
+    let mut protocol = message("ping", MessageType::Reply, 3, &NotifierPingArgs);
+
+    match processor.process(&mut protocol, &mut MockTransport::new(vec![])) {
+        Err(Error::ProtocolError(protocol::Error::InvalidMessageType(MessageType::Reply))) => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_processor_rejects_call_to_oneway_method() {
+    let handler = Handler::default();
+    let processor = NotifierProcessor::new(&handler);
+    let args = NotifierNotifyArgs { message: Some(String::from("hello")) };
+    let mut protocol = message("notify", MessageType::Call, 3, &args);
+
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+
+    assert!(handler.notified.borrow().is_empty());
+    assert_eq!(protocol.log()[0], Message(Begin((String::from("notify"), MessageType::Exception, 3))));
+    protocol.read_message_begin(&mut MockTransport::new(vec![])).unwrap();
+    assert_eq!(decode::<ApplicationException>(&mut protocol).kind, Some(ApplicationExceptionKind::InvalidMessageType));
+}
+
+#[test]
+fn test_processor_ignores_oneway_message_to_call() {
+    let handler = Handler::default();
+    let processor = NotifierProcessor::new(&handler);
+    let mut protocol = message("clear", MessageType::Oneway, 3, &NotifierClearArgs);
+
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+    assert_eq!(protocol.log(), &[]);
+}
+
+#[test]
+fn test_client_decodes_application_exception() {
+    let exception = ApplicationException::new(ApplicationExceptionKind::InternalError, "oops");
+    let mut client = NotifierClient::new(message("ping", MessageType::Exception, 1, &exception),
+                                         MockTransport::new(vec![]));
+    match client.ping() {
+        Err(Error::ApplicationException(e)) => {
+            assert_eq!(e, exception);
+            assert_eq!(e.to_string(), "InternalError: oops");
+        }
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_processor_replies_to_unknown_method() {
+    let handler = Handler::default();
+    let processor = NotifierProcessor::new(&handler);
+    let mut protocol = message("missing", MessageType::Call, 3, &NotifierPingArgs);
+
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+
+    assert_eq!(protocol.log(), &[
+        Message(Begin((String::from("missing"), MessageType::Exception, 3))),
+        Struct(Begin(String::from("ApplicationException"))),
+        Field(Begin((String::from("message"), Type::String, 1))),
+        Prim(PString(String::from("Unknown method missing"))),
+        Field(End),
+        Field(Begin((String::from("kind"), Type::I32, 2))),
+        Prim(I32(ApplicationExceptionKind::UnknownMethod as i32)),
+        Field(End),
+        field_end(),
+        Struct(End),
+        Message(End)
+    ]);
+}
+
+#[test]
+fn test_processor_ignores_unknown_oneway_method() {
+    let handler = Handler::default();
+    let processor = NotifierProcessor::new(&handler);
+    let mut protocol = message("missing", MessageType::Oneway, 3, &NotifierPingArgs);
+
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+
+    assert_eq!(protocol.log(), &[]);
+}
+
+struct Panicking;
+
+impl Notifier for Panicking {
+    fn notify(&self, _: String) { panic!("notify failed") }
+    fn ping(&self) -> i32 { panic!("ping failed") }
+    fn clear(&self) { panic!("clear failed") }
+    fn echo(&self, _: String, _: i32) -> String { panic!("echo failed") }
+}
+
+#[test]
+fn test_processor_replies_to_handler_panic() {
+    let mut processor = NotifierProcessor::new(Panicking);
+    processor.catch_panics(true);
+    let mut protocol = message("ping", MessageType::Call, 3, &NotifierPingArgs);
+
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+
+    let mut reply = protocol.clone();
+    assert_eq!(reply.read_message_begin(&mut MockTransport::new(vec![])).unwrap(),
+               (String::from("ping"), MessageType::Exception, 3));
+    assert_eq!(decode::<ApplicationException>(&mut reply),
+               ApplicationException::new(ApplicationExceptionKind::InternalError, "ping failed"));
+}
+
+#[test]
+fn test_processor_survives_oneway_handler_panic() {
+    let mut processor = NotifierProcessor::new(Panicking);
+    processor.catch_panics(true);
+    let mut protocol = message("notify", MessageType::Oneway, 3,
+                               &NotifierNotifyArgs { message: Some(String::from("hello")) });
+
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+
+    assert_eq!(protocol.log(), &[]);
+}
+
+#[test]
+#[should_panic(expected = "ping failed")]
+fn test_processor_propagates_handler_panic_by_default() {
+    let processor = NotifierProcessor::new(Panicking);
+    let mut protocol = message("ping", MessageType::Call, 3, &NotifierPingArgs);
+
+    let _ = processor.process(&mut protocol, &mut MockTransport::new(vec![]));
+}
+
+fn echo_reply(protocol: &MockProtocol) -> Option<String> {
+    let mut reply = protocol.clone();
+    assert_eq!(reply.read_message_begin(&mut MockTransport::new(vec![])).unwrap(),
+               (String::from("echo"), MessageType::Reply, 3));
+    decode::<NotifierEchoResult>(&mut reply).success
+}
+
+#[test]
+fn test_processor_passes_all_arguments() {
+    let handler = Handler::default();
+    let processor = NotifierProcessor::new(&handler);
+    let args = NotifierEchoArgs { message: Some(String::from("ab")), times: Some(2) };
+    let mut protocol = message("echo", MessageType::Call, 3, &args);
+
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+
+    assert_eq!(echo_reply(&protocol), Some(String::from("abab")));
+}
+
+#[test]
+fn test_processor_defaults_missing_arguments() {
+    let handler = Handler::default();
+    let processor = NotifierProcessor::new(&handler);
+
+    let args = NotifierEchoArgs { message: Some(String::from("ab")), times: None };
+    let mut protocol = message("echo", MessageType::Call, 3, &args);
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+    // `times` defaults to 2 in the IDL.
+    assert_eq!(echo_reply(&protocol), Some(String::from("abab")));
+
+    let mut protocol = message("notify", MessageType::Oneway, 3, &NotifierNotifyArgs { message: None });
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+    assert_eq!(*handler.notified.borrow(), vec![String::new()]);
+}
+
+#[test]
+fn test_processor_rejects_missing_arguments() {
+    let handler = Handler::default();
+    let mut processor = NotifierProcessor::new(&handler);
+    processor.missing_arguments(MissingArguments::Reject);
+
+    let args = NotifierEchoArgs { message: None, times: Some(2) };
+    let mut protocol = message("echo", MessageType::Call, 3, &args);
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+
+    assert_eq!(protocol.read_message_begin(&mut MockTransport::new(vec![])).unwrap(),
+               (String::from("echo"), MessageType::Exception, 3));
+    assert_eq!(decode::<ApplicationException>(&mut protocol),
+               ApplicationException::new(ApplicationExceptionKind::ProtocolError,
+                                         "Missing argument message to echo"));
+
+    let mut protocol = message("notify", MessageType::Oneway, 3, &NotifierNotifyArgs { message: None });
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+    assert_eq!(protocol.log(), &[]);
+    assert!(handler.notified.borrow().is_empty());
+}
+
+// A result struct as sent by a server whose IDL declares an exception the
+// client's version does not know about.
+strukt! {
+    name = ForeignResult,
+    fields = {
+        foreign: Option<Simple> => 3,
+    }
+}
+
+fn foreign_result(name: &str) -> MockProtocol {
+    let result = ForeignResult { foreign: Some(Simple { key: String::from("foreign") }) };
+    message(name, MessageType::Reply, 1, &result)
+}
+
+#[test]
+fn test_client_reports_missing_result() {
+    let mut client = NotifierClient::new(message("ping", MessageType::Reply, 1, &NotifierPingResult::default()),
+                                         MockTransport::new(vec![]));
+    match client.ping() {
+        Err(Error::MissingResult(ref method)) if method == "ping" => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_client_reports_unknown_exception() {
+    let mut client = NotifierClient::new(foreign_result("ping"), MockTransport::new(vec![]));
+    match client.ping() {
+        Err(Error::UnknownException { ref method, ref field_ids }) => {
+            assert_eq!(method, "ping");
+            assert_eq!(field_ids, &[3]);
+        }
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_client_void_method_without_result() {
+    let mut client = NotifierClient::new(message("clear", MessageType::Reply, 1, &NotifierClearResult::default()),
+                                         MockTransport::new(vec![]));
+    client.clear().unwrap();
+
+    let mut client = NotifierClient::new(foreign_result("clear"), MockTransport::new(vec![]));
+    match client.clear() {
+        Err(Error::UnknownException { ref field_ids, .. }) => assert_eq!(field_ids, &[3]),
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_client_declared_and_unknown_exceptions() {
+    let exception = compiletest::Exception { name: String::from("bad"), message: String::from("worse") };
+    let result = compiletest::ServiceWithExceptionOperationResult { success: None, bad: Some(exception.clone()) };
+    let mut client = ServiceWithExceptionClient::new(message("operation", MessageType::Reply, 1, &result),
+                                                     MockTransport::new(vec![]));
+    match client.operation() {
+        Ok(Err(ServiceWithExceptionOperationError::Bad(ref e))) if *e == exception => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+
+    let mut client = ServiceWithExceptionClient::new(foreign_result("operation"), MockTransport::new(vec![]));
+    match client.operation() {
+        Err(Error::UnknownException { ref field_ids, .. }) => assert_eq!(field_ids, &[3]),
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[derive(Default)]
+struct Recorder {
+    contexts: RefCell<Vec<RequestContext>>
+}
+
+impl<'a> Tracer for &'a Recorder {
+    fn whoami(&self, context: &RequestContext) -> String {
+        self.contexts.borrow_mut().push(context.clone());
+        format!("{}#{}", context.method, context.sequence_id)
+    }
+
+    fn record(&self, context: &RequestContext, _event: String) {
+        self.contexts.borrow_mut().push(context.clone());
+    }
+}
+
+#[test]
+fn test_processor_passes_request_context() {
+    let recorder = Recorder::default();
+    let processor = TracerProcessor::new(&recorder);
+
+    let mut protocol = message("whoami", MessageType::Call, 5, &TracerWhoamiArgs);
+    processor.process(&mut protocol, &mut MockTransport::new(vec![])).unwrap();
+    let args = TracerRecordArgs { event: Some(String::from("login")) };
+    let mut oneway = message("record", MessageType::Oneway, 6, &args);
+    processor.process(&mut oneway, &mut MockTransport::new(vec![])).unwrap();
+
+    assert_eq!(protocol.read_message_begin(&mut MockTransport::new(vec![])).unwrap(),
+               (String::from("whoami"), MessageType::Reply, 5));
+    assert_eq!(decode::<TracerWhoamiResult>(&mut protocol).success, Some(String::from("whoami#5")));
+    let contexts = recorder.contexts.borrow();
+    assert_eq!(contexts.len(), 2);
+    assert_eq!(contexts[0].message_type, MessageType::Call);
+    assert_eq!(contexts[1].method, "record");
+    assert_eq!(contexts[1].message_type, MessageType::Oneway);
+    assert_eq!(contexts[1].sequence_id, 6);
+    assert_eq!(contexts[1].peer_addr, None);
+    assert_eq!(contexts[1].connection_id, None);
+}
+
+#[test]
+fn test_processor_passes_headers_and_deadline() {
+    let recorder = Recorder::default();
+    let processor = TracerProcessor::new(&recorder);
+
+    let mut headers = BTreeMap::new();
+    headers.insert(String::from("user"), String::from("alice"));
+    headers.insert(String::from(DEADLINE_HEADER), String::from("5000"));
+    let mut request = FramedTransport::new(BufferTransport::default());
+    request.set_headers(&headers).unwrap();
+    let args = TracerRecordArgs { event: Some(String::from("login")) };
+    protocol::helpers::send(&mut BinaryProtocol, &mut request, "record", MessageType::Oneway, &args, 1).unwrap();
+    protocol::helpers::send(&mut BinaryProtocol, &mut request, "record", MessageType::Oneway, &args, 2).unwrap();
+
+    let before = Instant::now();
+    let mut transport = FramedTransport::new(BufferTransport::new(request.into_inner().into_output()));
+    processor.process(&mut BinaryProtocol, &mut transport).unwrap();
+    processor.process(&mut BinaryProtocol, &mut transport).unwrap();
+    let after = Instant::now();
+
+    let contexts = recorder.contexts.borrow();
+    assert_eq!(contexts[0].headers.keys().collect::<Vec<_>>(), ["user"]);
+    assert_eq!(contexts[0].headers["user"], "alice");
+    let deadline = contexts[0].deadline.unwrap();
+    assert!(deadline >= before + Duration::from_secs(5) && deadline <= after + Duration::from_secs(5));
+
+    // The headers only came with the first request.
+    assert!(contexts[1].headers.is_empty());
+    assert_eq!(contexts[1].deadline, None);
+}
+
+#[test]
+fn test_connections_number_accepted_transports() {
+    let recorder = Recorder::default();
+    let processor = TracerProcessor::new(&recorder);
+    let server = Connections::new(|| -> io::Result<MockTransport> { Ok(MockTransport::new(vec![])) });
+
+    for _ in 0..2 {
+        let mut connection = server.accept().unwrap();
+        let mut protocol = message("whoami", MessageType::Call, 1, &TracerWhoamiArgs);
+        processor.process(&mut protocol, &mut connection).unwrap();
+    }
+
+    let ids: Vec<_> = recorder.contexts.borrow().iter().map(|c| c.connection_id).collect();
+    assert_eq!(ids, vec![Some(0), Some(1)]);
+}
+
+#[test]
+fn test_tcp_transport_fills_peer_addr() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let accepted = TransportServer::accept(&listener).unwrap();
+
+    let context = RequestContext::new(&accepted, "whoami", MessageType::Call, 1);
+    assert_eq!(context.peer_addr, Some(client.local_addr().unwrap()));
+}
+
+fn method_names<S: ThriftService + ?Sized>() -> Vec<&'static str> {
+    S::descriptor().all_methods().iter().map(|method| method.name).collect()
+}
+
+#[test]
+fn test_service_descriptor() {
+    let handler = Handler::default();
+    let processor = NotifierProcessor::new(&handler);
+    assert_eq!(method_names_of(&processor), vec!["notify", "ping", "clear", "echo"]);
+
+    let descriptor = <NotifierClient<MockProtocol, MockTransport> as ThriftService>::descriptor();
+    assert_eq!(descriptor.name, "Notifier");
+    assert!(descriptor.parents().is_empty());
+
+    let notify = descriptor.method("notify").unwrap();
+    assert!(notify.oneway && !notify.idempotent);
+    assert_eq!(format!("{:?}", notify.returns), "Void");
+
+    let echo = descriptor.method("echo").unwrap();
+    assert!(!echo.oneway && echo.idempotent);
+    assert_eq!(echo.args.iter().map(|arg| (arg.id, arg.name, arg.typ)).collect::<Vec<_>>(),
+               vec![(1, "message", Type::String), (2, "times", Type::I32)]);
+    assert_eq!(echo.args[0].requiredness, Requiredness::Default);
+    assert_eq!(echo.returns.typ(), Type::String);
+    assert!(descriptor.method("missing").is_none());
+
+    fn method_names_of<S: ThriftService>(_: &S) -> Vec<&'static str> {
+        method_names::<S>()
+    }
+}
+
+#[test]
+fn test_service_descriptor_with_parent_and_exceptions() {
+    let child = <ChildService as ThriftService>::descriptor();
+    assert_eq!(child.parents().iter().map(|parent| parent.name).collect::<Vec<_>>(),
+               vec!["SharedService"]);
+    assert_eq!(method_names::<ChildService>(), vec!["operation", "get_struct"]);
+    match child.method("operation").unwrap().returns {
+        TypeDescriptor::Enum(operation) => assert_eq!(operation().name, "Operation"),
+        other => panic!("unexpected type {:?}", other)
+    }
+
+    let operation = ServiceWithExceptionClient::<MockProtocol, MockTransport>::descriptor()
+        .method("operation").unwrap();
+    assert_eq!(operation.exceptions.len(), 1);
+    assert_eq!((operation.exceptions[0].id, operation.exceptions[0].name), (1, "bad"));
+    assert_eq!(operation.exceptions[0].typ, Type::Struct);
+}
@@ -2,7 +2,13 @@ use test::*;
 use mock::*;
 use test::generated::*;
 
-use protocol::Type;
+use std::collections::BTreeMap;
+
+use transport::BufferTransport;
+use protocol::{self, Type, Encode, Decode, Protocol, StrictFields, Value};
+use protocol::binary_protocol::BinaryProtocol;
+use reflect::{Requiredness, TypeDescriptor};
+use {BuildError, Error, ThriftEnum, ThriftStruct};
 
 #[test]
 fn test_simple_struct() {
@@ -197,3 +203,246 @@ fn test_struct_with_optional_field_as_none() {
     assert_eq!(instance.this, second.this);
 }
 
+
+#[test]
+fn test_builder() {
+    let request = Request::builder()
+        .id(7i64)
+        .name("seven")
+        .tags(vec![String::from("odd")])
+        .build()
+        .unwrap();
+
+    assert_eq!(request, Request {
+        id: 7,
+        name: Some(String::from("seven")),
+        tags: Some(vec![String::from("odd")]),
+        priority: 5,
+        attempts: 0,
+    });
+
+    assert_eq!(Request::builder().id(1i64).build().unwrap(),
+               Request { id: 1, name: None, tags: None, priority: 5, attempts: 0 });
+    assert_eq!(NothingBuilder::default().build(), Ok(Nothing));
+    assert_eq!(Failure::builder().code(2).build().unwrap(), Failure { code: 2, reason: None });
+}
+
+#[test]
+fn test_builder_default_requiredness_fields() {
+    // Fields that are neither optional nor required keep their default,
+    // from the IDL if it declares one, unless they are set.
+    assert_eq!(Request::default().priority, 5);
+    let request = Request::builder().id(1i64).attempts(3).build().unwrap();
+    assert_eq!((request.priority, request.attempts), (5, 3));
+    let request = Request::builder().id(1i64).priority(9).build().unwrap();
+    assert_eq!((request.priority, request.attempts), (9, 0));
+    assert_eq!(Failure::builder().reason("none").build().unwrap(),
+               Failure { code: 0, reason: Some(String::from("none")) });
+}
+
+#[test]
+fn test_builder_missing_required_field() {
+    assert_eq!(Request::builder().name("nameless").build(),
+               Err(BuildError::MissingField { strukt: "Request", field: "id" }));
+    assert_eq!(Request::builder().build().unwrap_err().to_string(),
+               "missing required field Request.id");
+}
+
+fn binary<E: Encode>(value: &E) -> Vec<u8> {
+    let mut transport = BufferTransport::default();
+    value.encode(&mut BinaryProtocol, &mut transport).unwrap();
+    transport.into_output()
+}
+
+fn from_binary<D: Decode>(bytes: Vec<u8>) -> D {
+    from_binary_with(BinaryProtocol, bytes)
+}
+
+fn from_binary_with<D: Decode, P: Protocol>(mut protocol: P, bytes: Vec<u8>) -> D {
+    let mut value = D::default();
+    value.decode(&mut protocol, &mut BufferTransport::new(bytes)).unwrap();
+    value
+}
+
+fn record() -> Record {
+    let mut tags = BTreeMap::new();
+    tags.insert(String::from("primes"), vec![2, 3, 5]);
+    Record {
+        id: 9,
+        name: String::from("nine"),
+        tags: tags,
+        nested: Simple { key: String::from("inner") },
+        flag: Some(true),
+    }
+}
+
+#[test]
+fn test_unknown_fields_round_trip() {
+    let bytes = binary(&record());
+
+    let old = from_binary::<OldRecord>(bytes.clone());
+    assert_eq!(old.id, 9);
+    assert_eq!(old.unknown_fields.len(), 4);
+    assert_eq!(old.unknown_fields.get(2), Some(&Value::String(b"nine".to_vec())));
+    assert_eq!(old.unknown_fields.get(5), Some(&Value::Bool(true)));
+
+    assert_eq!(binary(&old), bytes);
+    assert_eq!(from_binary::<Record>(binary(&old)), record());
+}
+
+#[test]
+fn test_unknown_fields_between_known_fields() {
+    let bytes = binary(&record());
+
+    let sparse = from_binary::<SparseRecord>(bytes.clone());
+    assert_eq!((sparse.id, sparse.flag), (9, Some(true)));
+    assert_eq!(sparse.unknown_fields.iter().map(|&(id, _)| id).collect::<Vec<_>>(), vec![2, 3, 4]);
+    assert_eq!(binary(&sparse), bytes);
+}
+
+#[test]
+fn test_unknown_fields_in_field_id_order() {
+    let mut transport = BufferTransport::default();
+    BinaryProtocol.write_struct_begin(&mut transport, "").unwrap();
+    for &id in &[5, 1, 3] {
+        BinaryProtocol.write_field_begin(&mut transport, "", Type::I32, id).unwrap();
+        BinaryProtocol.write_i32(&mut transport, id as i32).unwrap();
+    }
+    BinaryProtocol.write_field_stop(&mut transport).unwrap();
+
+    let opaque = from_binary::<Opaque>(transport.into_output());
+    assert_eq!(opaque.unknown_fields.iter().map(|&(id, _)| id).collect::<Vec<_>>(), vec![1, 3, 5]);
+}
+
+#[test]
+fn test_unknown_fields_replaced_when_decoding_again() {
+    let bytes = binary(&record());
+
+    let mut old = from_binary::<OldRecord>(bytes.clone());
+    old.decode(&mut BinaryProtocol, &mut BufferTransport::new(bytes.clone())).unwrap();
+    assert_eq!(old.unknown_fields.len(), 4);
+    assert_eq!(binary(&old), bytes);
+}
+
+#[test]
+fn test_unknown_fields_of_empty_struct() {
+    let bytes = binary(&record());
+
+    let opaque = from_binary::<Opaque>(bytes.clone());
+    assert_eq!(opaque.unknown_fields.len(), 5);
+    assert_eq!(binary(&opaque), bytes);
+}
+
+#[test]
+fn test_unknown_fields_dropped_by_default() {
+    let simple = from_binary::<Simple>(binary(&record()));
+    assert_eq!(simple, Simple::default());
+}
+
+fn nested_lists(depth: usize) -> Vec<u8> {
+    let mut transport = BufferTransport::default();
+    for _ in 0..depth {
+        BinaryProtocol.write_list_begin(&mut transport, Type::List, 1).unwrap();
+    }
+    BinaryProtocol.write_list_begin(&mut transport, Type::I32, 0).unwrap();
+    transport.into_output()
+}
+
+#[test]
+fn test_value_depth_limit() {
+    let mut transport = BufferTransport::new(nested_lists(protocol::value::MAX_DEPTH));
+    let value = Value::read(&mut BinaryProtocol, &mut transport, Type::List).unwrap();
+    assert_eq!(value.typ(), Type::List);
+
+    let mut transport = BufferTransport::new(nested_lists(100000));
+    match Value::read(&mut BinaryProtocol, &mut transport, Type::List) {
+        Err(Error::ProtocolError(protocol::Error::DepthLimitExceeded)) => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_field_type_mismatch_is_skipped_by_default() {
+    let simple = from_binary::<Simple>(binary(&Misfit { key: 3 }));
+    assert_eq!(simple, Simple::default());
+}
+
+#[test]
+fn test_strict_fields_reports_type_mismatch() {
+    let mismatch = protocol::Error::FieldTypeMismatch {
+        strukt: "Simple",
+        field: "key",
+        expected: Type::String,
+        actual: Type::I64,
+    };
+
+    let mut simple = Simple::default();
+    let mut transport = BufferTransport::new(binary(&Misfit { key: 3 }));
+    match simple.decode(&mut StrictFields(BinaryProtocol), &mut transport) {
+        Err(Error::ProtocolError(ref e)) if *e == mismatch => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+
+    // Nested structs are decoded strictly too.
+    let mut list: Vec<Simple> = Vec::new();
+    let mut protocol = BinaryProtocol;
+    let mut transport = BufferTransport::new(binary(&vec![Misfit { key: 3 }]));
+    match list.decode(&mut StrictFields(&mut protocol), &mut transport) {
+        Err(Error::ProtocolError(ref e)) if *e == mismatch => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+
+    let strict = from_binary_with::<Simple, _>(StrictFields(BinaryProtocol),
+                                               binary(&Simple { key: String::from("fine") }));
+    assert_eq!(strict.key, "fine");
+}
+
+#[test]
+fn test_struct_descriptor() {
+    let descriptor = Record::descriptor();
+    assert_eq!(descriptor.name, "Record");
+    assert_eq!(descriptor.fields.iter().map(|f| (f.id, f.name)).collect::<Vec<_>>(),
+               vec![(1, "id"), (2, "name"), (3, "tags"), (4, "nested"), (5, "flag")]);
+
+    let tags = descriptor.field_by_name("tags").unwrap();
+    assert_eq!(tags.typ, Type::Map);
+    assert_eq!(descriptor.field(1).unwrap().requiredness, Requiredness::Required);
+    assert_eq!(tags.requiredness, Requiredness::Default);
+    match tags.ty {
+        TypeDescriptor::Map(key, value) => {
+            assert_eq!(key.typ(), Type::String);
+            assert_eq!(format!("{:?}", value), "List(I64)");
+        }
+        other => panic!("unexpected type {:?}", other)
+    }
+
+    let nested = descriptor.field(4).unwrap();
+    match nested.ty {
+        TypeDescriptor::Struct(inner) => assert_eq!(inner().fields[0].name, "key"),
+        other => panic!("unexpected type {:?}", other)
+    }
+
+    let flag = descriptor.field(5).unwrap();
+    assert_eq!(flag.typ, Type::Bool);
+    assert_eq!(flag.requiredness, Requiredness::Optional);
+
+    assert!(Empty::descriptor().fields.is_empty());
+    assert!(OldRecord::descriptor().field_by_name("unknown_fields").is_none());
+}
+
+#[test]
+fn test_recursive_and_enum_descriptors() {
+    let recurse = &Recursive::descriptor().fields[0];
+    assert_eq!(format!("{:?}", recurse.ty), "List(Struct(Recursive))");
+
+    let colors = &Palette::descriptor().fields[0];
+    assert_eq!(colors.typ, Type::List);
+    match colors.ty {
+        TypeDescriptor::List(&TypeDescriptor::Enum(color)) => {
+            assert_eq!(color().name, "Color");
+            assert_eq!(color().values, &[("Red", 1), ("green", 2)]);
+        }
+        other => panic!("unexpected type {:?}", other)
+    }
+    assert_eq!(Color::descriptor().name, "Color");
+}
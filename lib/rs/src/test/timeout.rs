@@ -0,0 +1,141 @@
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+use mock::*;
+use test::generated::*;
+
+use client::{Client, LastCall};
+use protocol::binary_protocol::BinaryProtocol;
+use transport::tcp::{self, Timeouts};
+use transport::{Deadline, RwTransport, Transport};
+use Error;
+
+/// A server that accepts connections but never replies.
+fn silent_server() -> TcpListener {
+    TcpListener::bind("127.0.0.1:0").unwrap()
+}
+
+fn assert_timeout<T: ::std::fmt::Debug>(result: ::Result<T>) {
+    match result {
+        Err(Error::Timeout) => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_call_timeout_breaks_connection() {
+    let server = silent_server();
+    let stream = tcp::connect(server.local_addr().unwrap(), &Timeouts::default()).unwrap();
+    let _accepted = server.accept().unwrap();
+
+    let mut client = NotifierClient::new(BinaryProtocol, stream);
+    client.set_timeout(Some(Duration::from_millis(30)));
+    assert_timeout(client.ping());
+
+    assert!(client.is_broken());
+    assert_eq!(client.last_call(), Some(LastCall { method: "ping", sent: true, idempotent: false }));
+    assert_eq!(client.transport.read_timeout().unwrap(), None);
+
+    match client.ping() {
+        Err(Error::TransportError(ref e)) if e.kind() == io::ErrorKind::NotConnected => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_call_timeout_restores_connection_timeouts() {
+    let server = silent_server();
+    let timeouts = Timeouts {
+        read: Some(Duration::from_secs(5)),
+        write: Some(Duration::from_secs(6)),
+        ..Timeouts::default()
+    };
+    let stream = tcp::connect(server.local_addr().unwrap(), &timeouts).unwrap();
+    let _accepted = server.accept().unwrap();
+
+    let mut client = NotifierClient::new(BinaryProtocol, stream);
+    client.set_timeout(Some(Duration::from_millis(30)));
+    assert_timeout(client.ping());
+    assert_eq!(client.transport.read_timeout().unwrap(), timeouts.read);
+    assert_eq!(client.transport.write_timeout().unwrap(), timeouts.write);
+}
+
+#[test]
+fn test_tcp_timeouts() {
+    let server = silent_server();
+    let timeouts = Timeouts {
+        connect: Some(Duration::from_secs(5)),
+        read: Some(Duration::from_millis(100)),
+        write: Some(Duration::from_secs(5)),
+    };
+    let stream = tcp::connect(server.local_addr().unwrap(), &timeouts).unwrap();
+    assert_eq!(stream.read_timeout().unwrap(), timeouts.read);
+    assert_eq!(stream.write_timeout().unwrap(), timeouts.write);
+
+    let mut client = NotifierClient::new(BinaryProtocol, stream);
+    assert_timeout(client.ping());
+    assert!(client.is_broken());
+}
+
+#[test]
+fn test_expired_deadline_fails_before_sending() {
+    let mut client = NotifierClient::new(MockProtocol::new(), MockTransport::new(vec![]));
+    client.set_timeout(Some(Duration::from_millis(0)));
+    assert_timeout(client.clear());
+    assert_eq!(client.last_call(), Some(LastCall { method: "clear", sent: false, idempotent: false }));
+}
+
+#[test]
+fn test_timeout_needs_transport_support() {
+    let mut client = NotifierClient::new(MockProtocol::new(), MockTransport::new(vec![]));
+    client.set_timeout(Some(Duration::from_secs(1)));
+    match client.clear() {
+        Err(Error::TransportError(ref e)) if e.kind() == io::ErrorKind::Other => {}
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_io_timeouts_become_timeout_errors() {
+    assert_timeout::<()>(Err(Error::from(io::Error::from(io::ErrorKind::TimedOut))));
+    assert!(Error::Timeout.breaks_connection());
+
+    match Error::from(io::Error::from(io::ErrorKind::WouldBlock)) {
+        Error::TransportError(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+        other => panic!("unexpected error {:?}", other)
+    }
+}
+
+/// A non-blocking transport with nothing to read yet.
+struct Pending;
+
+impl Read for Pending {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::from(io::ErrorKind::WouldBlock))
+    }
+}
+
+impl Write for Pending {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> { Ok(buf.len()) }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+#[test]
+fn test_deadline_reports_timeouts_it_knows_of() {
+    let mut deadline = Deadline::new(RwTransport(Pending), None);
+    assert_eq!(deadline.read(&mut [0]).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+
+    let server = silent_server();
+    let timeouts = Timeouts { read: Some(Duration::from_millis(30)), ..Timeouts::default() };
+    let mut stream = tcp::connect(server.local_addr().unwrap(), &timeouts).unwrap();
+    let _accepted = server.accept().unwrap();
+    {
+        let mut deadline = Deadline::new(&mut stream, None);
+        let e = deadline.read(&mut [0]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+        assert!(e.get_ref().is_some());
+    }
+    assert!(stream.read_timeout().unwrap().is_some());
+}
@@ -0,0 +1,85 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::io::{self, Cursor, Read, Write};
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use processor::RequestContext;
+use super::Transport;
+use super::framed::fill_headers;
+
+/// An in-memory transport reading from a buffer and collecting what is
+/// written, e.g. to encode or decode one message.
+#[derive(Debug, Default)]
+pub struct BufferTransport {
+    input: Cursor<Vec<u8>>,
+    output: Vec<u8>,
+    peer_addr: Option<SocketAddr>,
+    connection_id: Option<u64>,
+    headers: Option<(BTreeMap<String, String>, Instant)>,
+}
+
+impl BufferTransport {
+    pub fn new(input: Vec<u8>) -> BufferTransport {
+        BufferTransport { input: Cursor::new(input), ..Default::default() }
+    }
+
+    /// Describe the connection the input arrived on to service handlers.
+    pub fn connection(mut self, peer_addr: Option<SocketAddr>, connection_id: Option<u64>) -> BufferTransport {
+        self.peer_addr = peer_addr;
+        self.connection_id = connection_id;
+        self
+    }
+
+    /// Hand `headers`, received with the input now, to service handlers.
+    pub fn headers(mut self, headers: BTreeMap<String, String>) -> BufferTransport {
+        self.headers = Some((headers, Instant::now()));
+        self
+    }
+
+    pub fn output(&self) -> &[u8] { &self.output }
+
+    pub fn into_output(self) -> Vec<u8> { self.output }
+}
+
+impl Read for BufferTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for BufferTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+impl Transport for BufferTransport {
+    fn fill_context(&self, context: &mut RequestContext) {
+        context.peer_addr = self.peer_addr;
+        context.connection_id = self.connection_id;
+        if let Some((ref headers, received)) = self.headers {
+            fill_headers(context, headers, received);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use processor::RequestContext;
+use super::Transport;
+
+/// Limits the time a sequence of reads and writes may take in total, by
+/// setting the time left as the timeout of the underlying transport before
+/// each of them. The timeouts the transport had before are restored when the
+/// `Deadline` is dropped.
+///
+/// Reads and writes that fail with `WouldBlock` because of a timeout, the
+/// deadline's or one the transport already had, fail with `TimedOut`
+/// instead, wrapping the original error. Other `WouldBlock` errors, such as
+/// those of non-blocking transports, are left as they are.
+pub struct Deadline<T: Transport> {
+    transport: T,
+    deadline: Option<Instant>,
+    saved: Option<(Option<Duration>, Option<Duration>)>,
+}
+
+impl<T: Transport> Deadline<T> {
+    /// Without a timeout, the transport is left as it is.
+    pub fn new(transport: T, timeout: Option<Duration>) -> Deadline<T> {
+        Deadline { transport: transport, deadline: timeout.map(|timeout| Instant::now() + timeout), saved: None }
+    }
+
+    fn arm(&mut self) -> io::Result<()> {
+        if let Some(deadline) = self.deadline {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "deadline exceeded"));
+            }
+            if self.saved.is_none() {
+                // Transports that cannot tell had none, as far as we know.
+                self.saved = Some(self.transport.timeouts().unwrap_or((None, None)));
+            }
+            try!(self.transport.set_timeout(Some(deadline - now)));
+        }
+        Ok(())
+    }
+
+    fn timed_out<R>(&self, result: io::Result<R>, reading: bool) -> io::Result<R> {
+        result.map_err(|e| {
+            if e.kind() != io::ErrorKind::WouldBlock {
+                return e;
+            }
+            let armed = self.deadline.is_some() || match self.transport.timeouts() {
+                Ok((read, write)) => if reading { read.is_some() } else { write.is_some() },
+                Err(_) => false,
+            };
+            if armed { io::Error::new(io::ErrorKind::TimedOut, e) } else { e }
+        })
+    }
+}
+
+impl<T: Transport> Read for Deadline<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        try!(self.arm());
+        let result = self.transport.read(buf);
+        self.timed_out(result, true)
+    }
+}
+
+impl<T: Transport> Write for Deadline<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        try!(self.arm());
+        let result = self.transport.write(buf);
+        self.timed_out(result, false)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        try!(self.arm());
+        let result = self.transport.flush();
+        self.timed_out(result, false)
+    }
+}
+
+impl<T: Transport> Transport for Deadline<T> {
+    fn fill_context(&self, context: &mut RequestContext) {
+        self.transport.fill_context(context)
+    }
+
+    fn carries_headers(&self) -> bool { self.transport.carries_headers() }
+
+    fn set_headers(&mut self, headers: &BTreeMap<String, String>) -> io::Result<()> {
+        self.transport.set_headers(headers)
+    }
+}
+
+impl<T: Transport> Drop for Deadline<T> {
+    fn drop(&mut self) {
+        if let Some((read, write)) = self.saved {
+            let _ = self.transport.set_timeouts(read, write);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::cmp;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use processor::RequestContext;
+use protocol::{Decode, Encode};
+use protocol::binary_protocol::BinaryProtocol;
+use super::{BufferTransport, Transport, DEADLINE_HEADER};
+
+/// The largest frame `FramedTransport` and the nonblocking server accept
+/// before giving up on the peer.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Set in the length prefix of a frame holding the headers of the message in
+/// the frame after it.
+const HEADER_FRAME: u32 = 0x8000_0000;
+
+/// Prefixes every message with its length as a big-endian `i32`, like the
+/// framed transports of the other Thrift implementations.
+///
+/// Writes are buffered until `flush`, which sends them as one frame. Reads
+/// pull in a whole frame before handing out any of it.
+///
+/// Headers set through `Transport::set_headers` are sent in a frame of their
+/// own before the next message, with the top bit of its length set, and
+/// reach handlers through `RequestContext::headers`. Only this transport and
+/// `nonblocking::Server` understand such frames, so peers using the framed
+/// transports of other Thrift implementations must not be sent headers.
+pub struct FramedTransport<T> {
+    transport: T,
+    rbuf: Vec<u8>,
+    rpos: usize,
+    wbuf: Vec<u8>,
+    max_frame_size: usize,
+    /// The headers to send with the next message.
+    wheaders: Option<Vec<u8>>,
+    /// The headers that came with the message being read, and when.
+    rheaders: BTreeMap<String, String>,
+    received: Option<Instant>,
+}
+
+impl<T: Transport> FramedTransport<T> {
+    pub fn new(transport: T) -> FramedTransport<T> {
+        FramedTransport {
+            transport: transport,
+            rbuf: Vec::new(),
+            rpos: 0,
+            wbuf: Vec::new(),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            wheaders: None,
+            rheaders: BTreeMap::new(),
+            received: None,
+        }
+    }
+
+    pub fn set_max_frame_size(&mut self, size: usize) {
+        self.max_frame_size = size;
+    }
+
+    pub fn get_ref(&self) -> &T { &self.transport }
+
+    /// The headers sent with the message being read, if any.
+    pub fn headers(&self) -> &BTreeMap<String, String> { &self.rheaders }
+
+    pub fn into_inner(self) -> T { self.transport }
+
+    fn read_frame(&mut self) -> io::Result<()> {
+        let mut headers = None;
+        loop {
+            let mut header = [0; 4];
+            try!(self.transport.read_exact(&mut header));
+            let (kind, size) = try!(frame_size(header, self.max_frame_size));
+
+            self.rbuf.clear();
+            self.rbuf.resize(size, 0);
+            self.rpos = 0;
+            try!(self.transport.read_exact(&mut self.rbuf));
+
+            match kind {
+                FrameKind::Headers => headers = Some(try!(decode_headers(&self.rbuf))),
+                FrameKind::Message => break,
+            }
+        }
+
+        self.rheaders = headers.unwrap_or_default();
+        self.received = Some(Instant::now());
+        Ok(())
+    }
+}
+
+/// What a frame holds, as told by its length prefix.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FrameKind {
+    Message,
+    /// The headers of the message in the next frame.
+    Headers,
+}
+
+/// Check the length prefix of a frame.
+pub fn frame_size(header: [u8; 4], max_frame_size: usize) -> io::Result<(FrameKind, usize)> {
+    let prefix = u32::from_be_bytes(header);
+    let (kind, size) = if prefix & HEADER_FRAME == 0 {
+        (FrameKind::Message, prefix as usize)
+    } else {
+        (FrameKind::Headers, (prefix & !HEADER_FRAME) as usize)
+    };
+
+    if size > max_frame_size {
+        Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid frame size {}", size)))
+    } else {
+        Ok((kind, size))
+    }
+}
+
+/// The length prefix of a frame of `kind` holding `size` bytes.
+pub fn frame_prefix(kind: FrameKind, size: usize) -> io::Result<[u8; 4]> {
+    if size > i32::max_value() as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame too large"));
+    }
+
+    Ok(match kind {
+        FrameKind::Message => size as u32,
+        FrameKind::Headers => size as u32 | HEADER_FRAME,
+    }.to_be_bytes())
+}
+
+/// Encode the body of a header frame, a binary protocol `map<string, string>`.
+pub fn encode_headers(headers: &BTreeMap<String, String>) -> io::Result<Vec<u8>> {
+    let mut buffer = BufferTransport::default();
+    try!(headers.encode(&mut BinaryProtocol, &mut buffer).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+    }));
+    Ok(buffer.into_output())
+}
+
+/// Decode the body of a header frame.
+pub fn decode_headers(frame: &[u8]) -> io::Result<BTreeMap<String, String>> {
+    let mut headers = BTreeMap::new();
+    try!(headers.decode(&mut BinaryProtocol, &mut BufferTransport::new(frame.to_vec())).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("invalid header frame: {}", e))
+    }));
+    Ok(headers)
+}
+
+
+/// Record the headers a request arrived with at `received` in `context`,
+/// turning `DEADLINE_HEADER` into `context.deadline`.
+pub fn fill_headers(context: &mut RequestContext, headers: &BTreeMap<String, String>, received: Instant) {
+    context.headers = headers.clone();
+    if let Some(timeout) = context.headers.remove(DEADLINE_HEADER) {
+        match timeout.parse() {
+            Ok(millis) => context.deadline = Some(received + Duration::from_millis(millis)),
+            Err(_) => warn!("ignoring invalid {} header {:?}", DEADLINE_HEADER, timeout),
+        }
+    }
+}
+
+impl<T: Transport> Read for FramedTransport<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.rpos == self.rbuf.len() {
+            try!(self.read_frame());
+        }
+
+        let n = cmp::min(buf.len(), self.rbuf.len() - self.rpos);
+        buf[..n].copy_from_slice(&self.rbuf[self.rpos..self.rpos + n]);
+        self.rpos += n;
+        Ok(n)
+    }
+}
+
+impl<T: Transport> Write for FramedTransport<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.wbuf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.wbuf.is_empty() {
+            return self.transport.flush();
+        }
+
+        if let Some(headers) = self.wheaders.take() {
+            try!(self.transport.write_all(&try!(frame_prefix(FrameKind::Headers, headers.len()))));
+            try!(self.transport.write_all(&headers));
+        }
+
+        let header = try!(frame_prefix(FrameKind::Message, self.wbuf.len()));
+        try!(self.transport.write_all(&header));
+        try!(self.transport.write_all(&self.wbuf));
+        self.wbuf.clear();
+        self.transport.flush()
+    }
+}
+
+impl<T: Transport> Transport for FramedTransport<T> {
+    fn fill_context(&self, context: &mut RequestContext) {
+        self.transport.fill_context(context);
+        if let Some(received) = self.received {
+            fill_headers(context, &self.rheaders, received);
+        }
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.transport.set_timeout(timeout)
+    }
+
+    fn timeouts(&self) -> io::Result<(Option<Duration>, Option<Duration>)> {
+        self.transport.timeouts()
+    }
+
+    fn set_timeouts(&mut self, read: Option<Duration>, write: Option<Duration>) -> io::Result<()> {
+        self.transport.set_timeouts(read, write)
+    }
+
+    fn carries_headers(&self) -> bool { true }
+
+    fn set_headers(&mut self, headers: &BTreeMap<String, String>) -> io::Result<()> {
+        self.wheaders = Some(try!(encode_headers(headers)));
+        Ok(())
+    }
+}
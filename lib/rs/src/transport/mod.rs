@@ -17,14 +17,96 @@
  * under the License.
  */
 
+use std::collections::BTreeMap;
 use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use processor::RequestContext;
 
 pub mod server;
+pub mod framed;
+pub mod deadline;
+pub mod tcp;
+pub mod buffer;
+
+pub use self::buffer::BufferTransport;
+pub use self::deadline::Deadline;
+
+/// The header a client with a timeout sends the time it will wait for the
+/// reply in, in milliseconds. Servers report it as `RequestContext::deadline`.
+pub const DEADLINE_HEADER: &'static str = "thrift-deadline-ms";
+
+pub trait Transport: Write + Read {
+    /// Record what this transport knows about the connection, such as the
+    /// peer address, in the context handed to service handlers.
+    fn fill_context(&self, _context: &mut RequestContext) {}
+
+    /// Make reads and writes that block for longer than `timeout` fail with
+    /// `TimedOut` or `WouldBlock`, or block for as long as it takes if it is
+    /// `None`. Transports that cannot time out return an error.
+    fn set_timeout(&mut self, _timeout: Option<Duration>) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Other, "transport does not support timeouts"))
+    }
+
+    /// The current read and write timeouts, so that they can be restored
+    /// after `set_timeout`. Transports that cannot time out return an error.
+    fn timeouts(&self) -> io::Result<(Option<Duration>, Option<Duration>)> {
+        Err(io::Error::new(io::ErrorKind::Other, "transport does not support timeouts"))
+    }
+
+    /// Set the read and write timeouts separately, as returned by
+    /// `timeouts`. By default, only the same timeout can be set for both.
+    fn set_timeouts(&mut self, read: Option<Duration>, write: Option<Duration>) -> io::Result<()> {
+        if read == write {
+            self.set_timeout(read)
+        } else {
+            Err(io::Error::new(io::ErrorKind::Other, "transport does not support separate timeouts"))
+        }
+    }
+
+    /// Whether `set_headers` sends headers to the peer, as `FramedTransport`
+    /// does.
+    fn carries_headers(&self) -> bool { false }
+
+    /// Send `headers` with the next request, for transports that carry
+    /// them. Others drop them with a warning, leaving the call unaffected.
+    fn set_headers(&mut self, headers: &BTreeMap<String, String>) -> io::Result<()> {
+        warn!("dropping headers {:?}, which the transport cannot carry", headers.keys().collect::<Vec<_>>());
+        Ok(())
+    }
+}
 
-pub trait Transport: Write + Read { }
+impl<'t, T> Transport for &'t mut T where T: Transport {
+    fn fill_context(&self, context: &mut RequestContext) { (**self).fill_context(context) }
 
-impl<'t, T> Transport for &'t mut T where T: Transport {}
-impl<'t> Transport for &'t mut Transport {}
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> { (**self).set_timeout(timeout) }
+
+    fn timeouts(&self) -> io::Result<(Option<Duration>, Option<Duration>)> { (**self).timeouts() }
+
+    fn set_timeouts(&mut self, read: Option<Duration>, write: Option<Duration>) -> io::Result<()> {
+        (**self).set_timeouts(read, write)
+    }
+
+    fn carries_headers(&self) -> bool { (**self).carries_headers() }
+
+    fn set_headers(&mut self, headers: &BTreeMap<String, String>) -> io::Result<()> { (**self).set_headers(headers) }
+}
+
+impl<'t> Transport for &'t mut Transport {
+    fn fill_context(&self, context: &mut RequestContext) { (**self).fill_context(context) }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> { (**self).set_timeout(timeout) }
+
+    fn timeouts(&self) -> io::Result<(Option<Duration>, Option<Duration>)> { (**self).timeouts() }
+
+    fn set_timeouts(&mut self, read: Option<Duration>, write: Option<Duration>) -> io::Result<()> {
+        (**self).set_timeouts(read, write)
+    }
+
+    fn carries_headers(&self) -> bool { (**self).carries_headers() }
+
+    fn set_headers(&mut self, headers: &BTreeMap<String, String>) -> io::Result<()> { (**self).set_headers(headers) }
+}
 
 pub struct RwTransport<Rw>(pub Rw);
 
@@ -17,11 +17,33 @@
  * under the License.
  */
 
-use std::io;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
 use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use processor::RequestContext;
 use super::Transport;
 
-impl Transport for TcpStream {}
+impl Transport for TcpStream {
+    fn fill_context(&self, context: &mut RequestContext) {
+        context.peer_addr = self.peer_addr().ok();
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.set_timeouts(timeout, timeout)
+    }
+
+    fn timeouts(&self) -> io::Result<(Option<Duration>, Option<Duration>)> {
+        Ok((try!(self.read_timeout()), try!(self.write_timeout())))
+    }
+
+    fn set_timeouts(&mut self, read: Option<Duration>, write: Option<Duration>) -> io::Result<()> {
+        try!(self.set_read_timeout(read));
+        self.set_write_timeout(write)
+    }
+}
 
 pub trait TransportServer {
     type Transport: Transport;
@@ -44,3 +66,80 @@ where F: Fn() -> io::Result<T>, T: Transport {
     fn accept(&self) -> io::Result<T> { self() }
 }
 
+
+/// A `TransportServer` that numbers the connections it accepts, so handlers
+/// can tell requests from different connections apart through
+/// `RequestContext::connection_id`.
+pub struct Connections<TS> {
+    server: TS,
+    next_id: AtomicUsize,
+}
+
+impl<TS: TransportServer> Connections<TS> {
+    pub fn new(server: TS) -> Connections<TS> {
+        Connections { server: server, next_id: AtomicUsize::new(0) }
+    }
+}
+
+impl<TS: TransportServer> TransportServer for Connections<TS> {
+    type Transport = Connection<TS::Transport>;
+
+    fn accept(&self) -> io::Result<Connection<TS::Transport>> {
+        let transport = try!(self.server.accept());
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) as u64;
+        Ok(Connection { id: id, transport: transport })
+    }
+}
+
+/// A transport accepted through `Connections`.
+pub struct Connection<T> {
+    id: u64,
+    transport: T,
+}
+
+impl<T> Connection<T> {
+    pub fn id(&self) -> u64 { self.id }
+
+    pub fn into_inner(self) -> T { self.transport }
+}
+
+impl<T: Read> Read for Connection<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.transport.read(buf)
+    }
+}
+
+impl<T: Write> Write for Connection<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.transport.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.transport.flush()
+    }
+}
+
+impl<T: Transport> Transport for Connection<T> {
+    fn fill_context(&self, context: &mut RequestContext) {
+        self.transport.fill_context(context);
+        context.connection_id = Some(self.id);
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.transport.set_timeout(timeout)
+    }
+
+    fn timeouts(&self) -> io::Result<(Option<Duration>, Option<Duration>)> {
+        self.transport.timeouts()
+    }
+
+    fn set_timeouts(&mut self, read: Option<Duration>, write: Option<Duration>) -> io::Result<()> {
+        self.transport.set_timeouts(read, write)
+    }
+
+    fn carries_headers(&self) -> bool { self.transport.carries_headers() }
+
+    fn set_headers(&mut self, headers: &BTreeMap<String, String>) -> io::Result<()> {
+        self.transport.set_headers(headers)
+    }
+}
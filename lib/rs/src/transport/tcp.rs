@@ -0,0 +1,61 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// How long connecting to a server, and each read and write on the
+/// connection, may block. `None` means forever.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Timeouts {
+    pub connect: Option<Duration>,
+    pub read: Option<Duration>,
+    pub write: Option<Duration>,
+}
+
+/// Connect to the first address that accepts the connection in time, and
+/// set the read and write timeouts of the connection.
+///
+/// Operations that time out fail with `TimedOut` or `WouldBlock`, which
+/// become `Error::Timeout` when they happen during a call made by a
+/// generated client.
+pub fn connect<A: ToSocketAddrs>(addr: A, timeouts: &Timeouts) -> io::Result<TcpStream> {
+    let mut last_error = None;
+
+    for addr in try!(addr.to_socket_addrs()) {
+        let connected = match timeouts.connect {
+            Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+            None => TcpStream::connect(addr),
+        };
+
+        match connected {
+            Ok(stream) => {
+                try!(stream.set_read_timeout(timeouts.read));
+                try!(stream.set_write_timeout(timeouts.write));
+                return Ok(stream);
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "could not resolve to any address")
+    }))
+}
@@ -1,6 +1,8 @@
+#[macro_use]
 extern crate thrift;
 
-mod thrift_test;
+include!(concat!(env!("OUT_DIR"), "/thrift_test.rs"));
+include!(concat!(env!("OUT_DIR"), "/small_test.rs"));
 
 
 #[test]
@@ -27,8 +27,8 @@ use thrift::protocol::binary_protocol::BinaryProtocol;
 use thrift::transport::RwTransport;
 use tutorial::CalculatorClient;
 
-mod tutorial;
-mod shared;
+include!(concat!(env!("OUT_DIR"), "/tutorial.rs"));
+include!(concat!(env!("OUT_DIR"), "/shared.rs"));
 
 // Minimalistic performance test
 // Start C++ TutorialServer with output redirected to /dev/null
@@ -26,8 +26,8 @@ use bufstream::BufStream;
 use thrift::protocol::binary_protocol::BinaryProtocol;
 use thrift::transport::RwTransport;
 
-mod tutorial;
-mod shared;
+include!(concat!(env!("OUT_DIR"), "/tutorial.rs"));
+include!(concat!(env!("OUT_DIR"), "/shared.rs"));
 
 pub fn main() {
     let stream = RwTransport(BufStream::new(TcpStream::connect("127.0.0.1:9090").unwrap()));
@@ -19,7 +19,7 @@ use bufstream::BufStream;
 use shared::*;
 
 mod bufferserver;
-mod shared;
+include!(concat!(env!("OUT_DIR"), "/shared.rs"));
 
 #[derive(Clone)]
 struct Handler {
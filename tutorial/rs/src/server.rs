@@ -21,8 +21,8 @@
 extern crate terminal_thrift as thrift;
 extern crate bufstream;
 
-mod tutorial;
-mod shared;
+include!(concat!(env!("OUT_DIR"), "/tutorial.rs"));
+include!(concat!(env!("OUT_DIR"), "/shared.rs"));
 
 use std::net::TcpListener;
 use std::cell::RefCell;